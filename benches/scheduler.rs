@@ -0,0 +1,112 @@
+//! Dispatcher benchmarks at 100k-job scale: `cargo bench --bench scheduler`
+
+use chrono::Utc;
+use criterion::{BatchSize, Criterion, black_box, criterion_group, criterion_main};
+use cron::Schedule;
+use std::str::FromStr;
+
+#[path = "../src/scheduler/wheel.rs"]
+#[allow(dead_code)]
+mod wheel;
+
+use wheel::TimingWheel;
+
+const JOBS: u64 = 100_000;
+const START: u64 = 1_750_000_000;
+
+/// Deterministic spread of deadlines over the next `horizon` seconds.
+fn deadline(job: u64, horizon: u64) -> u64 {
+    START + 1 + job.wrapping_mul(2_654_435_761) % horizon
+}
+
+fn wheel_with(horizon: u64) -> TimingWheel<u64> {
+    let mut wheel = TimingWheel::new(START);
+    for job in 0..JOBS {
+        wheel.insert(job, deadline(job, horizon));
+    }
+    wheel
+}
+
+fn insert_and_cancel(c: &mut Criterion) {
+    c.bench_function("insert 100k jobs over one day", |b| {
+        b.iter_batched(|| (), |_| wheel_with(86_400), BatchSize::LargeInput)
+    });
+    c.bench_function("cancel 100k jobs", |b| {
+        b.iter_batched(
+            || wheel_with(86_400),
+            |mut wheel| {
+                for job in 0..JOBS {
+                    black_box(wheel.cancel(&job));
+                }
+                wheel
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn dispatch(c: &mut Criterion) {
+    // ~28 fires per second: the steady-state cost of one dispatcher tick
+    c.bench_function("dispatch one tick, 100k jobs over one hour", |b| {
+        b.iter_batched(
+            || (wheel_with(3_600), Vec::new()),
+            |(mut wheel, mut expired)| {
+                wheel.advance(START + 1, &mut expired);
+                black_box(expired.len());
+                (wheel, expired)
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    // Every job on the same cron expression, e.g. "0 0 * * * *"
+    c.bench_function("dispatch burst of 100k jobs due at once", |b| {
+        b.iter_batched(
+            || {
+                let mut wheel = TimingWheel::new(START);
+                for job in 0..JOBS {
+                    wheel.insert(job, START + 3_600);
+                }
+                (wheel, Vec::with_capacity(JOBS as usize))
+            },
+            |(mut wheel, mut expired)| {
+                wheel.advance(START + 3_600, &mut expired);
+                black_box(expired.len());
+                (wheel, expired)
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    let wheel = wheel_with(86_400);
+    c.bench_function("next expiry hint, 100k jobs", |b| {
+        b.iter(|| black_box(wheel.next_expiry_hint()))
+    });
+    c.bench_function("advance a full day, 100k jobs", |b| {
+        b.iter_batched(
+            || (wheel_with(86_400), Vec::with_capacity(JOBS as usize)),
+            |(mut wheel, mut expired)| {
+                wheel.advance(START + 86_400, &mut expired);
+                black_box(expired.len());
+                (wheel, expired)
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn next_fire(c: &mut Criterion) {
+    let cron = "0 */5 9-17 * * Mon-Fri";
+    let now = Utc::now();
+    c.bench_function("next fire, parsing cron each time", |b| {
+        b.iter(|| {
+            let schedule = Schedule::from_str(black_box(cron)).unwrap();
+            black_box(schedule.after(&now).next())
+        })
+    });
+    let schedule = Schedule::from_str(cron).unwrap();
+    c.bench_function("next fire, cached schedule", |b| {
+        b.iter(|| black_box(schedule.after(&now).next()))
+    });
+}
+
+criterion_group!(benches, insert_and_cancel, dispatch, next_fire);
+criterion_main!(benches);
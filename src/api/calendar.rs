@@ -0,0 +1,171 @@
+use crate::api::JobApiError;
+use crate::api::schedule::MAX_PREVIEW_FIRES;
+use crate::domain::calendar::{Calendar, TimeWindow};
+use crate::domain::schedule::{fires_after, parse_timezone};
+use crate::engine::engine::JobEngine;
+use crate::job::store::SqliteJobStore;
+use actix_web::{HttpResponse, Scope, delete, get, post, put, web};
+use chrono::{NaiveDate, Utc};
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct CalendarRequest {
+    pub name: String,
+    /// IANA timezone dates and windows are read in; the job's own timezone if omitted
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub holidays: Vec<NaiveDate>,
+    #[serde(default)]
+    pub include: Vec<TimeWindow>,
+    #[serde(default)]
+    pub exclude: Vec<TimeWindow>,
+}
+
+impl CalendarRequest {
+    fn to_calendar(&self, id: String) -> Result<Calendar, JobApiError> {
+        let calendar = Calendar {
+            id,
+            name: self.name.clone(),
+            timezone: self.timezone.clone(),
+            holidays: self.holidays.clone(),
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+        };
+        calendar.validate().map_err(JobApiError::InvalidCalendar)?;
+        Ok(calendar)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CalendarPreviewQuery {
+    pub cron: String,
+    /// Timezone of the job the calendar would be attached to; UTC if omitted
+    pub timezone: Option<String>,
+    pub count: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CalendarPreview {
+    /// Next fires the calendar allows, in UTC
+    pub fires: Vec<String>,
+    /// Fires of the cron expression the calendar drops before the last allowed one
+    pub excluded: Vec<String>,
+}
+
+#[get("")]
+async fn list_calendars(
+    store: web::Data<Arc<SqliteJobStore>>,
+) -> Result<HttpResponse, JobApiError> {
+    Ok(HttpResponse::Ok().json(store.list_calendars().await?))
+}
+
+#[post("")]
+async fn create_calendar(
+    data: web::Json<CalendarRequest>,
+    store: web::Data<Arc<SqliteJobStore>>,
+) -> Result<HttpResponse, JobApiError> {
+    let calendar = data.to_calendar(Uuid::new_v4().to_string())?;
+    store.save_calendar(&calendar).await?;
+    Ok(HttpResponse::Created().json(calendar))
+}
+
+#[get("/{id}")]
+async fn get_calendar(
+    path: web::Path<String>,
+    store: web::Data<Arc<SqliteJobStore>>,
+) -> Result<HttpResponse, JobApiError> {
+    match store.get_calendar(&path.into_inner()).await? {
+        Some(calendar) => Ok(HttpResponse::Ok().json(calendar)),
+        None => Err(JobApiError::CalendarNotFound),
+    }
+}
+
+/// Replaces the calendar and reschedules the jobs attached to it.
+#[put("/{id}")]
+async fn update_calendar(
+    path: web::Path<String>,
+    data: web::Json<CalendarRequest>,
+    store: web::Data<Arc<SqliteJobStore>>,
+    engine: web::Data<Arc<JobEngine>>,
+) -> Result<HttpResponse, JobApiError> {
+    let calendar = data.to_calendar(path.into_inner())?;
+    if store.get_calendar(&calendar.id).await?.is_none() {
+        return Err(JobApiError::CalendarNotFound);
+    }
+    store.save_calendar(&calendar).await?;
+    for job_id in store.jobs_using_calendar(&calendar.id).await? {
+        engine.reload_job_by_id(&job_id).await;
+    }
+    Ok(HttpResponse::Ok().json(calendar))
+}
+
+#[delete("/{id}")]
+async fn delete_calendar(
+    path: web::Path<String>,
+    store: web::Data<Arc<SqliteJobStore>>,
+) -> Result<HttpResponse, JobApiError> {
+    let id = path.into_inner();
+    if store.get_calendar(&id).await?.is_none() {
+        return Err(JobApiError::CalendarNotFound);
+    }
+    let jobs = store.jobs_using_calendar(&id).await?;
+    if !jobs.is_empty() {
+        return Err(JobApiError::CalendarInUse(format!(
+            "attached to {} job(s): {}",
+            jobs.len(),
+            jobs.join(", ")
+        )));
+    }
+    store.delete_calendar(&id).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Next fires of a cron expression with the calendar applied, and the fires it drops.
+#[get("/{id}/preview")]
+async fn preview_calendar(
+    path: web::Path<String>,
+    query: web::Query<CalendarPreviewQuery>,
+    store: web::Data<Arc<SqliteJobStore>>,
+) -> Result<HttpResponse, JobApiError> {
+    let Some(calendar) = store.get_calendar(&path.into_inner()).await? else {
+        return Err(JobApiError::CalendarNotFound);
+    };
+    let schedule =
+        Schedule::from_str(&query.cron).map_err(|e| JobApiError::InvalidCron(e.to_string()))?;
+    let tz = parse_timezone(query.timezone.as_deref()).map_err(JobApiError::InvalidTimezone)?;
+    let count = query.count.unwrap_or(10).clamp(1, MAX_PREVIEW_FIRES);
+
+    let now = Utc::now();
+    let fires: Vec<_> = fires_after(&schedule, tz, Some(&calendar), now)
+        .take(count)
+        .collect();
+    let excluded = match fires.last() {
+        Some(last) => fires_after(&schedule, tz, None, now)
+            .take_while(|fire| fire < last)
+            .filter(|fire| !calendar.allows(*fire, tz))
+            .take(MAX_PREVIEW_FIRES)
+            .map(|fire| fire.to_rfc3339())
+            .collect(),
+        None => Vec::new(),
+    };
+
+    Ok(HttpResponse::Ok().json(CalendarPreview {
+        fires: fires.iter().map(|fire| fire.to_rfc3339()).collect(),
+        excluded,
+    }))
+}
+
+pub fn calendar_routes() -> Scope {
+    web::scope("/calendars")
+        .service(list_calendars)
+        .service(create_calendar)
+        .service(get_calendar)
+        .service(update_calendar)
+        .service(delete_calendar)
+        .service(preview_calendar)
+}
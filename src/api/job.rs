@@ -1,48 +1,275 @@
-use crate::domain::model::{Job, JobRaw, JobStatus};
+use crate::api::schedule::{MAX_PREVIEW_FIRES, SchedulePreview};
+use crate::auth::requesting_user;
+use crate::domain::action::FollowUpAction;
+use crate::domain::dependency::{DependencyGraph, find_cycle};
+use crate::domain::describe::describe;
+use crate::domain::model::{Job, JobRaw, JobRun, JobSchedule, JobStatus};
+use crate::domain::policy::{MisfirePolicy, OverlapPolicy, RetryPolicy};
+use crate::domain::schedule::parse_timezone;
 use crate::engine::engine::JobEngine;
-use actix_web::{HttpResponse, Responder, ResponseError, Scope, delete, get, post, put, web};
-use chrono::Utc;
-use cron::Schedule;
+use actix_web::{HttpRequest, HttpResponse, ResponseError, Scope, delete, get, post, put, web};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
+use std::collections::HashMap;
 use std::sync::Arc;
 use thiserror::Error;
 use uuid::Uuid;
 
 use crate::job::store::{JobStore, SqliteJobStore};
+use crate::utils::{merge_patch, redact_signatures};
 
 #[derive(Debug, Deserialize)]
 pub struct JobRequest {
     pub name: String,
-    pub cron: String,
+    /// Cron expression; shorthand for a `cron` schedule
+    #[serde(default)]
+    pub cron: Option<String>,
+    /// One-shot, fixed-delay, fixed-rate or cron schedule, instead of `cron`
+    #[serde(default)]
+    pub schedule: Option<JobSchedule>,
     pub task_type: String,
     pub payload: String,
+    #[serde(default)]
+    pub retry_policy: Option<RetryPolicy>,
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub overlap_policy: OverlapPolicy,
+    #[serde(default)]
+    pub misfire_policy: MisfirePolicy,
+    /// IANA timezone the cron expression is evaluated in, e.g. `Asia/Bangkok`; UTC if omitted
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Position in the worker queue when the engine is at capacity; higher runs first
+    #[serde(default)]
+    pub priority: i32,
+    /// Upstream job ids; the job then runs when they all succeed instead of on `cron`
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Actions taken after a fire succeeds
+    #[serde(default)]
+    pub on_success: Vec<FollowUpAction>,
+    /// Actions taken after a fire fails, times out or is lost
+    #[serde(default)]
+    pub on_failure: Vec<FollowUpAction>,
+    /// Business calendar whose holidays and blackout windows the job skips
+    #[serde(default)]
+    pub calendar_id: Option<String>,
+    /// Fires are delayed by a stable offset within this many seconds; engine default if omitted
+    #[serde(default)]
+    pub jitter_secs: Option<u64>,
+}
+
+impl JobRequest {
+    /// Validates the request and turns it into a row for the store.
+    fn to_raw(&self, id: String) -> Result<JobRaw, JobApiError> {
+        let schedule = self.schedule()?;
+        parse_timezone(self.timezone.as_deref()).map_err(JobApiError::InvalidTimezone)?;
+        if let Some(policy) = &self.retry_policy {
+            policy.validate().map_err(JobApiError::InvalidPolicy)?;
+        }
+        self.misfire_policy
+            .validate()
+            .map_err(JobApiError::InvalidPolicy)?;
+        if self.timeout_secs == Some(0) {
+            return Err(JobApiError::InvalidPolicy(
+                "timeout_secs must be greater than 0".to_string(),
+            ));
+        }
+
+        let job = JobRaw {
+            id,
+            name: self.name.clone(),
+            schedule,
+            task_type: self.task_type.clone(),
+            payload: self.payload.clone(),
+            last_run: None,
+            status: JobStatus::Scheduled,
+            message: None,
+            retry_policy: self.retry_policy.clone(),
+            timeout_secs: self.timeout_secs,
+            overlap_policy: self.overlap_policy,
+            misfire_policy: self.misfire_policy,
+            timezone: self.timezone.clone(),
+            priority: self.priority,
+            depends_on: self.depends_on.clone(),
+            on_success: self.on_success.clone(),
+            on_failure: self.on_failure.clone(),
+            calendar_id: self.calendar_id.clone(),
+            calendar: None,
+            jitter_secs: self.jitter_secs,
+            dependencies_met_at: None,
+        };
+        job.to_job()
+            .map_err(|e| JobApiError::InvalidPayload(e.to_string()))?
+            .task_type
+            .validate()
+            .map_err(JobApiError::InvalidPayload)?;
+        Ok(job)
+    }
+
+    fn schedule(&self) -> Result<JobSchedule, JobApiError> {
+        parse_schedule(self.schedule.as_ref(), self.cron.as_deref())
+    }
+}
+
+/// The validated schedule, given either as `schedule` or as a `cron` expression.
+pub(crate) fn parse_schedule(
+    schedule: Option<&JobSchedule>,
+    cron: Option<&str>,
+) -> Result<JobSchedule, JobApiError> {
+    let schedule = match (schedule, cron) {
+        (Some(schedule), None) => schedule.clone(),
+        (None, Some(expression)) => JobSchedule::Cron {
+            expression: expression.to_string(),
+        },
+        (Some(_), Some(_)) => {
+            return Err(JobApiError::InvalidSchedule(
+                "give either cron or schedule, not both".to_string(),
+            ));
+        }
+        (None, None) => {
+            return Err(JobApiError::InvalidSchedule(
+                "cron or schedule is required".to_string(),
+            ));
+        }
+    };
+    schedule.validate().map_err(|e| match schedule {
+        JobSchedule::Cron { .. } => JobApiError::InvalidCron(e),
+        _ => JobApiError::InvalidSchedule(e),
+    })?;
+    Ok(schedule)
 }
 
 #[derive(Debug, Serialize)]
 pub struct JobResponse {
     pub id: String,
     pub name: String,
-    pub cron: String,
+    /// Cron expression of a cron schedule
+    pub cron: Option<String>,
+    pub schedule: JobSchedule,
     pub task_type: String,
     pub payload: String,
     pub last_run: Option<String>,
     pub status: String,
+    pub retry_policy: Option<RetryPolicy>,
+    pub timeout_secs: Option<u64>,
+    pub overlap_policy: OverlapPolicy,
+    pub misfire_policy: MisfirePolicy,
+    pub timezone: String,
+    pub priority: i32,
+    pub depends_on: Vec<String>,
+    pub on_success: Vec<FollowUpAction>,
+    pub on_failure: Vec<FollowUpAction>,
+    pub calendar_id: Option<String>,
+    pub jitter_secs: Option<u64>,
+    /// Next fire time in UTC, jitter included; `None` for jobs started by their upstream jobs
+    pub next_run: Option<String>,
+    /// Next fire time in the job's timezone
+    pub next_run_local: Option<String>,
 }
 
-impl From<JobRaw> for JobResponse {
-    fn from(job: JobRaw) -> Self {
+impl JobResponse {
+    /// `default_jitter_secs` applies to jobs without their own jitter window.
+    pub fn new(job: JobRaw, default_jitter_secs: u64) -> Self {
+        let timezone = parse_timezone(job.timezone.as_deref()).unwrap_or(Tz::UTC);
+        let next_run = job
+            .next_run(default_jitter_secs)
+            .filter(|_| job.depends_on.is_empty());
         Self {
             id: job.id,
             name: job.name,
-            cron: job.cron,
+            cron: job.schedule.cron().map(str::to_string),
+            schedule: job.schedule,
             task_type: job.task_type,
             payload: job.payload,
             last_run: job.last_run.map(|dt| dt.to_rfc3339()),
             status: job.status.to_string(),
+            retry_policy: job.retry_policy,
+            timeout_secs: job.timeout_secs,
+            overlap_policy: job.overlap_policy,
+            misfire_policy: job.misfire_policy,
+            timezone: timezone.name().to_string(),
+            priority: job.priority,
+            depends_on: job.depends_on,
+            on_success: job.on_success,
+            on_failure: job.on_failure,
+            calendar_id: job.calendar_id,
+            jitter_secs: job.jitter_secs,
+            next_run: next_run.map(|dt| dt.to_rfc3339()),
+            next_run_local: next_run.map(|dt| dt.with_timezone(&timezone).to_rfc3339()),
+        }
+    }
+}
+#[derive(Debug, Serialize)]
+pub struct JobRunResponse {
+    pub id: String,
+    pub job_id: String,
+    pub scheduled_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub duration_ms: Option<i64>,
+    pub status: String,
+    pub message: Option<String>,
+    pub node_id: String,
+    pub attempt: u32,
+    pub trigger: String,
+    pub triggered_by: Option<String>,
+    /// Remote execution the run started, with any `sig` in it redacted
+    pub external_ref: Option<String>,
+    /// Run whose outcome started this one, for dependency and follow-up runs
+    pub parent_run_id: Option<String>,
+    pub output: Option<serde_json::Value>,
+}
+
+impl From<JobRun> for JobRunResponse {
+    fn from(run: JobRun) -> Self {
+        Self {
+            id: run.id,
+            job_id: run.job_id,
+            scheduled_at: run.scheduled_at.to_rfc3339(),
+            started_at: run.started_at.map(|dt| dt.to_rfc3339()),
+            finished_at: run.finished_at.map(|dt| dt.to_rfc3339()),
+            duration_ms: run.duration_ms,
+            status: run.status.to_string(),
+            message: run.message,
+            node_id: run.node_id,
+            attempt: run.attempt,
+            trigger: run.trigger.to_string(),
+            triggered_by: run.triggered_by,
+            external_ref: run.external_ref.as_deref().map(redact_signatures),
+            parent_run_id: run.parent_run_id,
+            output: run.output,
         }
     }
 }
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TriggerRequest {
+    /// JSON merge patch applied over the stored payload, for this run only
+    #[serde(default)]
+    pub payload: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TriggerResponse {
+    pub job_id: String,
+    pub scheduled_at: String,
+    pub triggered_by: String,
+    pub payload: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RunListQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NextRunsQuery {
+    pub count: Option<usize>,
+}
+
 #[derive(Debug, Error)]
 pub enum JobApiError {
     #[error("Database error: {0}")]
@@ -51,11 +278,44 @@ pub enum JobApiError {
     #[error("Job not found")]
     NotFound,
 
+    #[error("Run not found")]
+    RunNotFound,
+
     #[error("Invalid cron expression: {0}")]
     InvalidCron(String),
 
+    #[error("Invalid schedule: {0}")]
+    InvalidSchedule(String),
+
     #[error("Invalid payload format: {0}")]
     InvalidPayload(String),
+
+    #[error("Invalid policy: {0}")]
+    InvalidPolicy(String),
+
+    #[error("Invalid timezone: {0}")]
+    InvalidTimezone(String),
+
+    #[error("Engine is shutting down")]
+    ShuttingDown,
+
+    #[error("Invalid dependency: {0}")]
+    InvalidDependency(String),
+
+    #[error("Invalid follow-up action: {0}")]
+    InvalidAction(String),
+
+    #[error("Calendar not found")]
+    CalendarNotFound,
+
+    #[error("Invalid calendar: {0}")]
+    InvalidCalendar(String),
+
+    #[error("Calendar is in use: {0}")]
+    CalendarInUse(String),
+
+    #[error("Job is completed: {0}")]
+    JobCompleted(String),
 }
 
 impl ResponseError for JobApiError {
@@ -65,12 +325,41 @@ impl ResponseError for JobApiError {
                 HttpResponse::InternalServerError().body(format!("Database error: {}", e))
             }
             JobApiError::NotFound => HttpResponse::NotFound().body("Job not found"),
+            JobApiError::RunNotFound => HttpResponse::NotFound().body("Run not found"),
             JobApiError::InvalidCron(msg) => {
                 HttpResponse::BadRequest().body(format!("Invalid cron: {}", msg))
             }
+            JobApiError::InvalidSchedule(msg) => {
+                HttpResponse::BadRequest().body(format!("Invalid schedule: {}", msg))
+            }
             JobApiError::InvalidPayload(msg) => {
                 HttpResponse::BadRequest().body(format!("Invalid payload: {}", msg))
             }
+            JobApiError::InvalidPolicy(msg) => {
+                HttpResponse::BadRequest().body(format!("Invalid policy: {}", msg))
+            }
+            JobApiError::InvalidTimezone(msg) => {
+                HttpResponse::BadRequest().body(format!("Invalid timezone: {}", msg))
+            }
+            JobApiError::ShuttingDown => {
+                HttpResponse::ServiceUnavailable().body("Engine is shutting down")
+            }
+            JobApiError::InvalidDependency(msg) => {
+                HttpResponse::BadRequest().body(format!("Invalid dependency: {}", msg))
+            }
+            JobApiError::InvalidAction(msg) => {
+                HttpResponse::BadRequest().body(format!("Invalid follow-up action: {}", msg))
+            }
+            JobApiError::CalendarNotFound => HttpResponse::NotFound().body("Calendar not found"),
+            JobApiError::InvalidCalendar(msg) => {
+                HttpResponse::BadRequest().body(format!("Invalid calendar: {}", msg))
+            }
+            JobApiError::CalendarInUse(msg) => {
+                HttpResponse::Conflict().body(format!("Calendar is in use: {}", msg))
+            }
+            JobApiError::JobCompleted(msg) => {
+                HttpResponse::Conflict().body(format!("Job is completed: {}", msg))
+            }
         }
     }
 }
@@ -81,31 +370,184 @@ async fn create_job(
     store: web::Data<Arc<SqliteJobStore>>,
     engine: web::Data<Arc<JobEngine>>,
 ) -> Result<HttpResponse, JobApiError> {
-    Schedule::from_str(&data.cron).map_err(|e| JobApiError::InvalidCron(e.to_string()))?;
-
-    let job = JobRaw {
-        id: Uuid::new_v4().to_string(),
-        name: data.name.clone(),
-        cron: data.cron.clone(),
-        task_type: data.task_type.clone(),
-        payload: data.payload.clone(),
-        last_run: None,
-        status: JobStatus::Scheduled,
-        message: None,
-    };
+    let mut job = data.to_raw(Uuid::new_v4().to_string())?;
+    validate_dependencies(&store, &job).await?;
+    validate_actions(&store, &job).await?;
+    attach_calendar(&store, &mut job).await?;
 
     store.insert_job(&job).await?;
 
     engine.reload_job_by_id(&job.id).await;
 
-    let job = JobResponse::from(job);
+    let job = JobResponse::new(job, engine.default_jitter_secs());
     Ok(HttpResponse::Created().json(job))
 }
 
+/// Checks that every upstream job exists and that the new edges don't close a cycle.
+async fn validate_dependencies(store: &SqliteJobStore, job: &JobRaw) -> Result<(), JobApiError> {
+    let names: HashMap<String, String> = store
+        .load_jobs()
+        .await
+        .into_iter()
+        .map(|job| (job.id, job.name))
+        .collect();
+    for upstream in &job.depends_on {
+        if *upstream == job.id {
+            return Err(JobApiError::InvalidDependency(
+                "a job cannot depend on itself".to_string(),
+            ));
+        }
+        if !names.contains_key(upstream) {
+            return Err(JobApiError::InvalidDependency(format!(
+                "unknown job '{}'",
+                upstream
+            )));
+        }
+    }
+
+    let graph = store.dependency_graph().await?;
+    if let Some(cycle) = find_cycle(&graph, &job.id, &job.depends_on) {
+        let path: Vec<&str> = cycle
+            .iter()
+            .map(|id| names.get(id).map_or(job.name.as_str(), String::as_str))
+            .collect();
+        return Err(JobApiError::InvalidDependency(format!(
+            "cycle {}",
+            path.join(" -> ")
+        )));
+    }
+    Ok(())
+}
+
+/// Jobs started by the follow-up actions of `job`, whatever the outcome.
+fn triggered_jobs(job: &JobRaw) -> Vec<String> {
+    job.on_success
+        .iter()
+        .chain(&job.on_failure)
+        .filter_map(|action| match action {
+            FollowUpAction::TriggerJob { job_id } => Some(job_id.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Checks the tasks that follow-up actions run, that every job they trigger exists, and that
+/// the jobs do not trigger one another in a cycle, which would run them in a loop.
+async fn validate_actions(store: &SqliteJobStore, job: &JobRaw) -> Result<(), JobApiError> {
+    for action in job.on_success.iter().chain(&job.on_failure) {
+        if let FollowUpAction::RunTask { task } = action {
+            task.validate().map_err(JobApiError::InvalidPayload)?;
+        }
+    }
+
+    let jobs = store.load_jobs().await;
+    let names: HashMap<&str, &str> = jobs
+        .iter()
+        .map(|job| (job.id.as_str(), job.name.as_str()))
+        .collect();
+    let triggers = triggered_jobs(job);
+    for job_id in &triggers {
+        if *job_id != job.id && !names.contains_key(job_id.as_str()) {
+            return Err(JobApiError::InvalidAction(format!(
+                "unknown job '{}'",
+                job_id
+            )));
+        }
+    }
+
+    // Trigger edges of the stored jobs; the saved job's are the ones being checked
+    let graph: DependencyGraph = jobs
+        .iter()
+        .map(|job| (job.id.clone(), triggered_jobs(job)))
+        .collect();
+    if let Some(cycle) = find_cycle(&graph, &job.id, &triggers) {
+        let path: Vec<&str> = cycle
+            .iter()
+            .map(|id| match *id == job.id {
+                true => job.name.as_str(),
+                false => names.get(id.as_str()).copied().unwrap_or(id),
+            })
+            .collect();
+        return Err(JobApiError::InvalidAction(format!(
+            "trigger cycle {}",
+            path.join(" -> ")
+        )));
+    }
+    Ok(())
+}
+
+/// Checks that the job's calendar exists and attaches it, so the response's `next_run`
+/// honours it.
+async fn attach_calendar(store: &SqliteJobStore, job: &mut JobRaw) -> Result<(), JobApiError> {
+    if let Some(id) = &job.calendar_id {
+        let calendar = store.get_calendar(id).await?;
+        if calendar.is_none() {
+            return Err(JobApiError::InvalidCalendar(format!(
+                "unknown calendar '{}'",
+                id
+            )));
+        }
+        job.calendar = calendar;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphNode {
+    pub id: String,
+    pub name: String,
+    pub task_type: String,
+    pub status: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphEdge {
+    /// Upstream job id
+    pub from: String,
+    /// Downstream job id
+    pub to: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JobGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+#[get("/graph")]
+async fn job_graph(store: web::Data<Arc<SqliteJobStore>>) -> Result<HttpResponse, JobApiError> {
+    let jobs = store.load_jobs().await;
+    let edges = jobs
+        .iter()
+        .flat_map(|job| {
+            job.depends_on.iter().map(|upstream| GraphEdge {
+                from: upstream.clone(),
+                to: job.id.clone(),
+            })
+        })
+        .collect();
+    let nodes = jobs
+        .into_iter()
+        .map(|job| GraphNode {
+            id: job.id,
+            name: job.name,
+            task_type: job.task_type,
+            status: job.status.to_string(),
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(JobGraph { nodes, edges }))
+}
+
 #[get("")]
-async fn list_jobs(store: web::Data<Arc<SqliteJobStore>>) -> Result<HttpResponse, JobApiError> {
+async fn list_jobs(
+    store: web::Data<Arc<SqliteJobStore>>,
+    engine: web::Data<Arc<JobEngine>>,
+) -> Result<HttpResponse, JobApiError> {
     let result = store.load_jobs().await;
-    let response: Vec<JobResponse> = result.into_iter().map(JobResponse::from).collect();
+    let response: Vec<JobResponse> = result
+        .into_iter()
+        .map(|job| JobResponse::new(job, engine.default_jitter_secs()))
+        .collect();
     Ok(HttpResponse::Ok().json(response))
 }
 
@@ -113,45 +555,217 @@ async fn list_jobs(store: web::Data<Arc<SqliteJobStore>>) -> Result<HttpResponse
 async fn get_job_by_id(
     path: web::Path<String>,
     store: web::Data<Arc<SqliteJobStore>>,
+    engine: web::Data<Arc<JobEngine>>,
 ) -> Result<HttpResponse, JobApiError> {
     let id = path.into_inner();
     match store.get_job_by_id(&id).await? {
-        Some(job) => Ok(HttpResponse::Ok().json(JobResponse::from(job))),
+        Some(job) => {
+            Ok(HttpResponse::Ok().json(JobResponse::new(job, engine.default_jitter_secs())))
+        }
         None => Err(JobApiError::NotFound),
     }
 }
 
+/// Upcoming fires of a job, jitter included, with a description of its schedule.
+#[get("/{id}/next")]
+async fn next_runs(
+    path: web::Path<String>,
+    query: web::Query<NextRunsQuery>,
+    store: web::Data<Arc<SqliteJobStore>>,
+    engine: web::Data<Arc<JobEngine>>,
+) -> Result<HttpResponse, JobApiError> {
+    let Some(job) = store.get_job_by_id(&path.into_inner()).await? else {
+        return Err(JobApiError::NotFound);
+    };
+    let tz = parse_timezone(job.timezone.as_deref()).unwrap_or(Tz::UTC);
+    if !job.depends_on.is_empty() {
+        let description = format!("When its {} upstream job(s) succeed", job.depends_on.len());
+        return Ok(HttpResponse::Ok().json(SchedulePreview::new(description, tz, &[])));
+    }
+
+    let count = query.count.unwrap_or(10).clamp(1, MAX_PREVIEW_FIRES);
+    let default_jitter_secs = engine.default_jitter_secs();
+    let fires = job.next_runs(default_jitter_secs, count);
+    let description = describe(
+        &job.schedule,
+        tz,
+        job.calendar.as_ref(),
+        job.jitter(default_jitter_secs),
+    );
+    Ok(HttpResponse::Ok().json(SchedulePreview::new(description, tz, &fires)))
+}
+
+#[get("/{id}/runs")]
+async fn list_job_runs(
+    path: web::Path<String>,
+    query: web::Query<RunListQuery>,
+    store: web::Data<Arc<SqliteJobStore>>,
+) -> Result<HttpResponse, JobApiError> {
+    let id = path.into_inner();
+    if store.get_job_by_id(&id).await?.is_none() {
+        return Err(JobApiError::NotFound);
+    }
+    let limit = query.limit.unwrap_or(50).clamp(1, 1000);
+    let runs = store.list_runs(&id, limit).await?;
+    let response: Vec<JobRunResponse> = runs.into_iter().map(JobRunResponse::from).collect();
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[get("/{run_id}")]
+async fn get_run_by_id(
+    path: web::Path<String>,
+    store: web::Data<Arc<SqliteJobStore>>,
+) -> Result<HttpResponse, JobApiError> {
+    let run_id = path.into_inner();
+    match store.get_run_by_id(&run_id).await? {
+        Some(run) => Ok(HttpResponse::Ok().json(JobRunResponse::from(run))),
+        None => Err(JobApiError::RunNotFound),
+    }
+}
+
 #[put("/{id}")]
 async fn update_job(
     path: web::Path<String>,
     data: web::Json<JobRequest>,
     store: web::Data<Arc<SqliteJobStore>>,
+    engine: web::Data<Arc<JobEngine>>,
 ) -> Result<HttpResponse, JobApiError> {
-    Schedule::from_str(&data.cron).map_err(|e| JobApiError::InvalidCron(e.to_string()))?;
+    let mut job = data.to_raw(path.into_inner())?;
+    match store.get_job_by_id(&job.id).await? {
+        None => return Err(JobApiError::NotFound),
+        // Editing a paused job must not resume it
+        Some(existing) if existing.status == JobStatus::Disabled => {
+            job.status = JobStatus::Disabled
+        }
+        Some(_) => {}
+    }
+    validate_dependencies(&store, &job).await?;
+    validate_actions(&store, &job).await?;
+    attach_calendar(&store, &mut job).await?;
+    store.update_job(&job).await?;
+    engine.reload_job_by_id(&job.id).await;
+    Ok(HttpResponse::Ok().body("Job updated"))
+}
 
+#[post("/{id}/pause")]
+async fn pause_job(
+    path: web::Path<String>,
+    store: web::Data<Arc<SqliteJobStore>>,
+    engine: web::Data<Arc<JobEngine>>,
+) -> Result<HttpResponse, JobApiError> {
+    let id = path.into_inner();
+    if store.get_job_by_id(&id).await?.is_none() {
+        return Err(JobApiError::NotFound);
+    }
+    store.set_enabled(&id, false).await?;
+    engine.unschedule(&id);
+    Ok(HttpResponse::Ok().body("Job paused"))
+}
+
+#[post("/{id}/resume")]
+async fn resume_job(
+    path: web::Path<String>,
+    store: web::Data<Arc<SqliteJobStore>>,
+    engine: web::Data<Arc<JobEngine>>,
+) -> Result<HttpResponse, JobApiError> {
     let id = path.into_inner();
-    let job = JobRaw {
-        id,
-        name: data.name.clone(),
-        cron: data.cron.clone(),
-        task_type: data.task_type.clone(),
-        payload: data.payload.clone(),
-        last_run: Some(Utc::now()),
-        status: JobStatus::Scheduled,
-        message: None,
+    let job = store
+        .get_job_by_id(&id)
+        .await?
+        .ok_or(JobApiError::NotFound)?;
+    check_fires_left(&job, Utc::now())?;
+    store.set_enabled(&id, true).await?;
+    engine.reload_job_by_id(&id).await;
+    Ok(HttpResponse::Ok().body("Job resumed"))
+}
+
+/// Refuses to resume a one-shot job whose only fire is over, which would otherwise stay
+/// `scheduled` without ever firing again.
+fn check_fires_left(job: &JobRaw, now: DateTime<Utc>) -> Result<(), JobApiError> {
+    match job.schedule {
+        JobSchedule::Once { at } if at <= now => Err(JobApiError::JobCompleted(format!(
+            "its one-shot fire at {} is over",
+            at.to_rfc3339()
+        ))),
+        _ if job.status == JobStatus::Completed => Err(JobApiError::JobCompleted(
+            "its schedule has no fire left".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Runs the job immediately. The body is optional: `{"payload": {...}}` overrides payload fields.
+#[post("/{id}/trigger")]
+async fn trigger_job(
+    path: web::Path<String>,
+    req: HttpRequest,
+    body: web::Bytes,
+    store: web::Data<Arc<SqliteJobStore>>,
+    engine: web::Data<Arc<JobEngine>>,
+) -> Result<HttpResponse, JobApiError> {
+    let request: TriggerRequest = if body.iter().all(u8::is_ascii_whitespace) {
+        TriggerRequest::default()
+    } else {
+        serde_json::from_slice(&body).map_err(|e| JobApiError::InvalidPayload(e.to_string()))?
     };
 
-    store.update_job(&job).await?;
-    Ok(HttpResponse::Ok().body("Job updated"))
+    let mut raw = store
+        .get_job_by_id(&path.into_inner())
+        .await?
+        .ok_or(JobApiError::NotFound)?;
+    let job = patch_payload(&mut raw, request.payload.as_ref())?;
+
+    if engine.is_shutting_down() {
+        return Err(JobApiError::ShuttingDown);
+    }
+    let triggered_by = requesting_user(&req);
+    let scheduled_at = engine.trigger_now(job, &triggered_by);
+    Ok(HttpResponse::Accepted().json(TriggerResponse {
+        job_id: raw.id,
+        scheduled_at: scheduled_at.to_rfc3339(),
+        triggered_by,
+        payload: raw.payload,
+    }))
+}
+
+/// Applies the merge `patch` of a manual run over the stored payload, checking the result as
+/// a saved payload is.
+fn patch_payload(raw: &mut JobRaw, patch: Option<&serde_json::Value>) -> Result<Job, JobApiError> {
+    if let Some(patch) = patch {
+        let mut payload: serde_json::Value = serde_json::from_str(&raw.payload)
+            .map_err(|e| JobApiError::InvalidPayload(e.to_string()))?;
+        merge_patch(&mut payload, patch);
+        raw.payload = payload.to_string();
+    }
+    let job = raw
+        .to_job()
+        .map_err(|e| JobApiError::InvalidPayload(e.to_string()))?;
+    job.task_type
+        .validate()
+        .map_err(JobApiError::InvalidPayload)?;
+    Ok(job)
 }
 
 #[delete("/{id}")]
 async fn delete_job(
     path: web::Path<String>,
     store: web::Data<Arc<SqliteJobStore>>,
+    engine: web::Data<Arc<JobEngine>>,
 ) -> Result<HttpResponse, JobApiError> {
     let id = path.into_inner();
+    let downstream: Vec<String> = store
+        .dependency_graph()
+        .await?
+        .into_iter()
+        .filter(|(_, upstream)| upstream.contains(&id))
+        .map(|(job_id, _)| job_id)
+        .collect();
     store.delete_job(&id).await?;
+    engine.unschedule(&id);
+    // A job whose last upstream was removed goes back to its own schedule
+    for job_id in downstream {
+        engine.reload_job_by_id(&job_id).await;
+    }
     Ok(HttpResponse::Ok().body("Job deleted"))
 }
 
@@ -159,7 +773,146 @@ pub fn job_routes() -> Scope {
     web::scope("/jobs")
         .service(create_job)
         .service(list_jobs)
+        .service(job_graph)
         .service(get_job_by_id)
+        .service(next_runs)
+        .service(list_job_runs)
         .service(update_job)
+        .service(pause_job)
+        .service(resume_job)
+        .service(trigger_job)
         .service(delete_job)
 }
+
+pub fn run_routes() -> Scope {
+    web::scope("/runs").service(get_run_by_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::TempStore;
+    use serde_json::json;
+
+    fn job(id: &str, actions: serde_json::Value) -> JobRaw {
+        let request: JobRequest = serde_json::from_value(json!({
+            "name": id,
+            "cron": "0 0 2 * * *",
+            "task_type": "print",
+            "payload": "{\"message\":\"hi\"}",
+            "on_success": actions,
+        }))
+        .unwrap();
+        request.to_raw(id.to_string()).unwrap()
+    }
+
+    fn trigger(job_id: &str) -> serde_json::Value {
+        json!([{ "action": "trigger_job", "job_id": job_id }])
+    }
+
+    fn message(result: Result<(), JobApiError>) -> String {
+        result.unwrap_err().to_string()
+    }
+
+    #[tokio::test]
+    async fn trigger_cycles_are_rejected() {
+        let store = TempStore::new().await;
+        store.insert_job(&job("b", json!([]))).await.unwrap();
+        store.insert_job(&job("a", trigger("b"))).await.unwrap();
+
+        assert_eq!(
+            message(validate_actions(&store, &job("b", trigger("a"))).await),
+            "Invalid follow-up action: trigger cycle b -> a -> b"
+        );
+        assert_eq!(
+            message(validate_actions(&store, &job("c", trigger("c"))).await),
+            "Invalid follow-up action: trigger cycle c -> c"
+        );
+        assert_eq!(
+            message(validate_actions(&store, &job("c", trigger("x"))).await),
+            "Invalid follow-up action: unknown job 'x'"
+        );
+        assert!(
+            validate_actions(&store, &job("c", trigger("a")))
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn tasks_run_by_actions_are_validated() {
+        let store = TempStore::new().await;
+        let task = json!({ "task_type": "http", "payload": { "url": "not a url" } });
+        let job = job("a", json!([{ "action": "run_task", "task": task }]));
+        assert!(matches!(
+            validate_actions(&store, &job).await,
+            Err(JobApiError::InvalidPayload(_))
+        ));
+    }
+
+    #[test]
+    fn patched_payload_is_validated() {
+        let shell = |payload: serde_json::Value| {
+            let request: JobRequest = serde_json::from_value(json!({
+                "name": "job",
+                "cron": "0 0 2 * * *",
+                "task_type": "shell_command",
+                "payload": payload.to_string(),
+            }))
+            .unwrap();
+            request.to_raw("job".to_string()).unwrap()
+        };
+
+        let mut raw = shell(json!({ "command": "true" }));
+        let patch = json!({ "argv": ["echo", "hi"] });
+        assert!(matches!(
+            patch_payload(&mut raw, Some(&patch)),
+            Err(JobApiError::InvalidPayload(_))
+        ));
+
+        let mut raw = shell(json!({ "command": "true" }));
+        let patch = json!({ "command": null, "argv": ["echo", "hi"] });
+        patch_payload(&mut raw, Some(&patch)).unwrap();
+        assert_eq!(raw.payload, json!({ "argv": ["echo", "hi"] }).to_string());
+
+        let mut raw = job("a", json!([]));
+        raw.task_type = "http".to_string();
+        raw.payload = json!({ "url": "http://localhost/" }).to_string();
+        for patch in [
+            json!({ "url": "not a url" }),
+            json!({ "method": "NOT A METHOD" }),
+        ] {
+            assert!(matches!(
+                patch_payload(&mut raw.clone(), Some(&patch)),
+                Err(JobApiError::InvalidPayload(_))
+            ));
+        }
+        patch_payload(&mut raw, None).unwrap();
+    }
+
+    #[test]
+    fn one_shot_job_past_its_fire_cannot_be_resumed() {
+        let now = Utc::now();
+        let mut raw = job("a", json!([]));
+        assert!(check_fires_left(&raw, now).is_ok());
+
+        raw.schedule = JobSchedule::Once {
+            at: now + chrono::Duration::hours(1),
+        };
+        raw.status = JobStatus::Disabled;
+        assert!(check_fires_left(&raw, now).is_ok());
+
+        // Paused through its fire, or completed before being paused
+        raw.schedule = JobSchedule::Once {
+            at: now - chrono::Duration::hours(1),
+        };
+        for status in [JobStatus::Disabled, JobStatus::Completed] {
+            raw.status = status;
+            let error = check_fires_left(&raw, now).unwrap_err();
+            assert_eq!(
+                error.error_response().status(),
+                actix_web::http::StatusCode::CONFLICT
+            );
+        }
+    }
+}
@@ -1,3 +1,9 @@
+mod calendar;
 mod job;
+mod schedule;
+mod worker;
 
+pub use calendar::*;
 pub use job::*;
+pub use schedule::*;
+pub use worker::*;
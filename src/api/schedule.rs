@@ -0,0 +1,121 @@
+use crate::api::JobApiError;
+use crate::api::job::parse_schedule;
+use crate::domain::describe::describe;
+use crate::domain::model::JobSchedule;
+use crate::domain::schedule::{parse_timezone, upcoming_fires};
+use crate::engine::engine::JobEngine;
+use crate::job::store::SqliteJobStore;
+use actix_web::{HttpResponse, Scope, post, web};
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Upper bound for `count` in a schedule or calendar preview.
+pub const MAX_PREVIEW_FIRES: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct SchedulePreviewRequest {
+    /// Cron expression; shorthand for a `cron` schedule
+    #[serde(default)]
+    pub cron: Option<String>,
+    #[serde(default)]
+    pub schedule: Option<JobSchedule>,
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub calendar_id: Option<String>,
+    /// Job being edited: its id fixes the jitter offset and its last run is where a fixed
+    /// delay counts from. New jobs are previewed without jitter.
+    #[serde(default)]
+    pub job_id: Option<String>,
+    /// Jitter window of the job being edited; its saved one or the engine default if omitted
+    #[serde(default)]
+    pub jitter_secs: Option<u64>,
+    pub count: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SchedulePreview {
+    pub description: String,
+    pub timezone: String,
+    /// Upcoming fire times in UTC, jitter included
+    pub fires: Vec<String>,
+    /// The same fire times in `timezone`
+    pub fires_local: Vec<String>,
+}
+
+impl SchedulePreview {
+    pub fn new(description: String, tz: Tz, fires: &[DateTime<Utc>]) -> Self {
+        Self {
+            description,
+            timezone: tz.name().to_string(),
+            fires: fires.iter().map(|fire| fire.to_rfc3339()).collect(),
+            fires_local: fires
+                .iter()
+                .map(|fire| fire.with_timezone(&tz).to_rfc3339())
+                .collect(),
+        }
+    }
+}
+
+/// Upcoming fires and a description of a schedule before it is saved, so an editor can show
+/// what the expression means and report why it is invalid.
+#[post("/preview")]
+async fn preview_schedule(
+    data: web::Json<SchedulePreviewRequest>,
+    store: web::Data<Arc<SqliteJobStore>>,
+    engine: web::Data<Arc<JobEngine>>,
+) -> Result<HttpResponse, JobApiError> {
+    let schedule = parse_schedule(data.schedule.as_ref(), data.cron.as_deref())?;
+    let cron = schedule
+        .cron()
+        .map(Schedule::from_str)
+        .transpose()
+        .map_err(|e| JobApiError::InvalidCron(e.to_string()))?;
+    let tz = parse_timezone(data.timezone.as_deref()).map_err(JobApiError::InvalidTimezone)?;
+    let calendar = match &data.calendar_id {
+        Some(id) => match store.get_calendar(id).await? {
+            Some(calendar) => Some(calendar),
+            None => {
+                return Err(JobApiError::InvalidCalendar(format!(
+                    "unknown calendar '{}'",
+                    id
+                )));
+            }
+        },
+        None => None,
+    };
+    let (jitter, last_run) = match &data.job_id {
+        Some(id) => {
+            let Some(job) = store.get_job_by_id(id).await? else {
+                return Err(JobApiError::NotFound);
+            };
+            let window = data
+                .jitter_secs
+                .or(job.jitter_secs)
+                .unwrap_or(engine.default_jitter_secs());
+            (schedule.jitter(&job.id, window), job.last_run)
+        }
+        None => (Duration::zero(), None),
+    };
+
+    let count = data.count.unwrap_or(10).clamp(1, MAX_PREVIEW_FIRES);
+    let fires = upcoming_fires(
+        &schedule,
+        cron.as_ref(),
+        tz,
+        calendar.as_ref(),
+        jitter,
+        last_run,
+        count,
+    );
+    let description = describe(&schedule, tz, calendar.as_ref(), jitter);
+    Ok(HttpResponse::Ok().json(SchedulePreview::new(description, tz, &fires)))
+}
+
+pub fn schedule_routes() -> Scope {
+    web::scope("/schedules").service(preview_schedule)
+}
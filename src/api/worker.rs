@@ -0,0 +1,13 @@
+use crate::engine::engine::JobEngine;
+use actix_web::{HttpResponse, Scope, get, web};
+use std::sync::Arc;
+
+/// Worker usage and queue depth, globally and per task type.
+#[get("")]
+async fn worker_stats(engine: web::Data<Arc<JobEngine>>) -> HttpResponse {
+    HttpResponse::Ok().json(engine.worker_stats())
+}
+
+pub fn worker_routes() -> Scope {
+    web::scope("/workers").service(worker_stats)
+}
@@ -1,5 +1,4 @@
 use std::env;
-use std::fs::metadata;
 use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder, Scope};
 use actix_web::cookie::Cookie;
 use log::{debug, error};
@@ -7,9 +6,23 @@ use serde::Deserialize;
 use urlencoding::encode;
 use uuid::Uuid;
 use crate::auth::oidc::{fetch_metadata, verify_id_token};
+use crate::auth::session::Sessions;
 
 static COOKIE_OIDC_NONCE: &str = "oidc_nonce";
+static COOKIE_SESSION: &str = "session";
+static ANONYMOUS_USER: &str = "anonymous";
+const SESSION_MINUTES: i64 = 30;
+
+/// User on whose behalf an API request is made: the one whose ID token was verified
+/// when their session started, else `anonymous`.
+pub fn requesting_user(req: &HttpRequest) -> String {
+    let sessions = req.app_data::<web::Data<Sessions>>();
+    req.cookie(COOKIE_SESSION)
+        .and_then(|cookie| sessions?.user(cookie.value()))
+        .unwrap_or_else(|| ANONYMOUS_USER.to_string())
+}
 #[derive(Debug, Deserialize)]
+#[allow(dead_code)]
 pub struct OidcCallbackForm {
     code: Option<String>,
     state: Option<String>,
@@ -83,8 +96,11 @@ pub async fn login() -> impl Responder {
 }
 
 #[post("/logout")]
-pub async fn logout() -> impl Responder {
+pub async fn logout(req: HttpRequest, sessions: web::Data<Sessions>) -> impl Responder {
     debug!("Logout");
+    if let Some(session) = req.cookie(COOKIE_SESSION) {
+        sessions.remove(session.value());
+    }
     
     let tenant_id = env::var("AZURE_TENANT_ID").expect("Missing AZURE_TENANT_ID");
 
@@ -105,6 +121,12 @@ pub async fn logout() -> impl Responder {
         .http_only(true)
         .max_age(time::Duration::seconds(0))
         .finish();
+    let session_cookie = Cookie::build(COOKIE_SESSION, "")
+        .path("/")
+        .secure(true)
+        .http_only(true)
+        .max_age(time::Duration::seconds(0))
+        .finish();
 
     if let Some(logout_url) = metadata.end_session_endpoint {
         
@@ -118,6 +140,7 @@ pub async fn logout() -> impl Responder {
         HttpResponse::Found()
             .append_header(("Location", redirect))  // ✅ redirect จริงไปที่ Azure logout
             .cookie(cookie)
+            .cookie(session_cookie)
             .finish()
     } else {
         HttpResponse::BadRequest().body("Missing end_session_endpoint")
@@ -126,7 +149,8 @@ pub async fn logout() -> impl Responder {
 
 #[post("/callback")]
 pub async fn callback(form: web::Form<OidcCallbackForm>,
-                      req: HttpRequest) -> impl Responder {
+                      req: HttpRequest,
+                      sessions: web::Data<Sessions>) -> impl Responder {
     // 🧱 Step 1: ตรวจสอบว่ามี error กลับมาจาก IDP หรือไม่
     debug!("OIDC Callback Form: {:#?}", form);
     if let Some(error) = &form.error {
@@ -166,12 +190,21 @@ pub async fn callback(form: web::Form<OidcCallbackForm>,
             HttpResponse::Unauthorized().body("Invalid ID token")
         }).unwrap();
         debug!("ID Token Claims: {:#?}", id);
+        // remember who signed in, e.g. for manually triggered runs
+        let session = sessions.create(id.display_name(), chrono::Duration::minutes(SESSION_MINUTES));
+        let session_cookie = Cookie::build(COOKIE_SESSION, session)
+            .path("/")
+            .secure(true)
+            .http_only(true)
+            .max_age(time::Duration::minutes(SESSION_MINUTES))
+            .finish();
         // finish login process 
-        return HttpResponse::Found()
+        HttpResponse::Found()
             .append_header(("Location", "/index.html"))
-            .finish();
+            .cookie(session_cookie)
+            .finish()
     }else {
-        return HttpResponse::BadRequest().body("Missing id_token");
+        HttpResponse::BadRequest().body("Missing id_token")
     }
 }
 
@@ -1,4 +1,6 @@
 mod handler;
 mod oidc;
+mod session;
 
-pub use handler::*;
\ No newline at end of file
+pub use handler::*;
+pub use session::*;
\ No newline at end of file
@@ -1,13 +1,11 @@
 use jsonwebtoken::{decode, decode_header, DecodingKey, Validation, Algorithm};
 use log::debug;
-use serde::{Deserialize};
-use serde_json::Value;
 use crate::auth::oidc::IdTokenClaims;
 
 async fn fetch_jwks(jwks_uri: &str) -> Result<serde_json::Value, reqwest::Error> {
     let client = reqwest::Client::new();
     let res = client.get(jwks_uri).send().await?;
-    Ok(res.json::<serde_json::Value>().await?)
+    res.json::<serde_json::Value>().await
 }
 pub async fn verify_id_token(id_token: &str, client_id: &str, expected_nonce: &str, jwks_uri: &str, expected_issuer: &str)
                          -> Result<IdTokenClaims, String>
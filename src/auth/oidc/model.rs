@@ -1,6 +1,7 @@
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize, Clone)]
+#[allow(dead_code)]
 pub struct OidcMetadata {
     pub issuer: String,
     pub authorization_endpoint: String,
@@ -14,6 +15,7 @@ pub struct OidcMetadata {
 }
 
 #[derive(Debug, Deserialize)]
+#[allow(dead_code)]
 pub struct IdTokenClaims {
     sub: String,
     email: Option<String>,
@@ -26,6 +28,14 @@ pub struct IdTokenClaims {
 }
 
 impl IdTokenClaims {
+    /// Name shown for the signed-in user: email if present, otherwise name, otherwise subject.
+    pub fn display_name(&self) -> &str {
+        self.email
+            .as_deref()
+            .or(self.name.as_deref())
+            .unwrap_or(&self.sub)
+    }
+
     pub fn is_nonce_valid(&self, expected_nonce: &str) -> bool {
         if let Some(nonce) = &self.nonce {
             nonce == expected_nonce
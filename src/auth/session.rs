@@ -0,0 +1,51 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use uuid::Uuid;
+
+/// Signed-in users, keyed by the random id kept in their session cookie.
+///
+/// Sessions are only created once the ID token has been verified, so a client cannot
+/// pick its own identity by setting a cookie or header. They live in memory: a restart
+/// signs everyone out.
+#[derive(Default)]
+pub struct Sessions {
+    sessions: RwLock<HashMap<String, Session>>,
+}
+
+struct Session {
+    user: String,
+    expires_at: DateTime<Utc>,
+}
+
+impl Sessions {
+    /// Starts a session for `user` and returns its id.
+    pub fn create(&self, user: &str, ttl: Duration) -> String {
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now();
+        let mut sessions = self.sessions.write().unwrap();
+        sessions.retain(|_, session| session.expires_at > now);
+        sessions.insert(
+            id.clone(),
+            Session {
+                user: user.to_string(),
+                expires_at: now + ttl,
+            },
+        );
+        id
+    }
+
+    /// User of the session `id`, unless it is unknown or expired.
+    pub fn user(&self, id: &str) -> Option<String> {
+        self.sessions
+            .read()
+            .unwrap()
+            .get(id)
+            .filter(|session| session.expires_at > Utc::now())
+            .map(|session| session.user.clone())
+    }
+
+    pub fn remove(&self, id: &str) {
+        self.sessions.write().unwrap().remove(id);
+    }
+}
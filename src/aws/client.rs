@@ -0,0 +1,200 @@
+use crate::aws::sigv4::{AwsCredentials, SigV4Signer};
+use chrono::Utc;
+use log::debug;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use serde_json::json;
+use std::env;
+use std::fmt::Display;
+
+pub struct StepFnClient {
+    pub endpoint: Url,
+    pub signer: SigV4Signer,
+    pub client: Client,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum StepFnExecutionStatus {
+    Running,
+    Succeeded,
+    Failed,
+    TimedOut,
+    Aborted,
+    /// Failed, waiting for a possible redrive
+    PendingRedrive,
+    #[serde(other)]
+    Unknown,
+}
+
+impl Display for StepFnExecutionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            StepFnExecutionStatus::Running => "RUNNING",
+            StepFnExecutionStatus::Succeeded => "SUCCEEDED",
+            StepFnExecutionStatus::Failed => "FAILED",
+            StepFnExecutionStatus::TimedOut => "TIMED_OUT",
+            StepFnExecutionStatus::Aborted => "ABORTED",
+            StepFnExecutionStatus::PendingRedrive => "PENDING_REDRIVE",
+            StepFnExecutionStatus::Unknown => "UNKNOWN",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Error response of a Step Functions API action.
+#[derive(Debug)]
+pub struct StepFnError {
+    pub action: String,
+    pub status: u16,
+    /// Error type, e.g. `ExecutionAlreadyExists`
+    pub kind: String,
+    pub message: String,
+}
+
+impl Display for StepFnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} failed with {} {}: {}",
+            self.action, self.status, self.kind, self.message
+        )
+    }
+}
+
+impl std::error::Error for StepFnError {}
+
+/// Result of `DescribeExecution`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepFnExecution {
+    pub execution_arn: String,
+    pub status: StepFnExecutionStatus,
+    /// JSON output of a successful execution
+    pub output: Option<String>,
+    pub error: Option<String>,
+    pub cause: Option<String>,
+}
+
+impl StepFnClient {
+    /// Client for `region`, calling `endpoint` if given, else `AWS_ENDPOINT_URL_SFN` or
+    /// `AWS_ENDPOINT_URL` if set, else the regional Step Functions endpoint.
+    pub fn new(region: String, endpoint: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        let endpoint = match endpoint {
+            Some(endpoint) => endpoint.to_string(),
+            None => env::var("AWS_ENDPOINT_URL_SFN")
+                .or_else(|_| env::var("AWS_ENDPOINT_URL"))
+                .unwrap_or_else(|_| format!("https://states.{}.amazonaws.com/", region)),
+        };
+        debug!("Step Functions endpoint: {}", endpoint);
+        Ok(Self {
+            endpoint: Url::parse(&endpoint)?,
+            signer: SigV4Signer {
+                credentials: AwsCredentials::from_env()?,
+                region,
+                service: "states".to_string(),
+            },
+            client: Client::new(),
+        })
+    }
+
+    /// Calls a Step Functions API action with its JSON request body.
+    async fn call(
+        &self,
+        action: &str,
+        body: serde_json::Value,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let payload = body.to_string();
+        let headers = [
+            ("content-type", "application/x-amz-json-1.0".to_string()),
+            ("x-amz-target", format!("AWSStepFunctions.{}", action)),
+        ];
+        let signed = self.signer.sign(
+            "POST",
+            &self.endpoint,
+            &headers,
+            payload.as_bytes(),
+            Utc::now(),
+        );
+
+        let mut request = self.client.post(self.endpoint.clone()).body(payload);
+        for (name, value) in headers
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.clone()))
+            .chain(signed)
+        {
+            request = request.header(name, value);
+        }
+        let res = request.send().await?;
+        if res.status().is_success() {
+            Ok(res.json().await?)
+        } else {
+            let status = res.status().as_u16();
+            let error: serde_json::Value = res.json().await.unwrap_or_default();
+            // `__type` is e.g. "com.amazonaws.states#ExecutionDoesNotExist"
+            let kind = error["__type"].as_str().unwrap_or_default();
+            let kind = kind.rsplit('#').next().unwrap_or(kind);
+            let message = error["message"]
+                .as_str()
+                .or(error["Message"].as_str())
+                .unwrap_or_default();
+            Err(Box::new(StepFnError {
+                action: action.to_string(),
+                status,
+                kind: kind.to_string(),
+                message: message.to_string(),
+            }))
+        }
+    }
+
+    /// Starts an execution and returns its ARN. Starting again with the same `name` and input
+    /// while it runs returns the same execution instead of a second one; once it has ended,
+    /// the name is taken and the call fails with `ExecutionAlreadyExists`.
+    pub async fn start_execution(
+        &self,
+        state_machine_arn: &str,
+        name: &str,
+        input: &serde_json::Value,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let res = self
+            .call(
+                "StartExecution",
+                json!({
+                    "stateMachineArn": state_machine_arn,
+                    "name": name,
+                    "input": input.to_string(),
+                }),
+            )
+            .await?;
+        res["executionArn"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| "StartExecution returned no executionArn".into())
+    }
+
+    pub async fn describe_execution(
+        &self,
+        execution_arn: &str,
+    ) -> Result<StepFnExecution, Box<dyn std::error::Error>> {
+        let res = self
+            .call(
+                "DescribeExecution",
+                json!({ "executionArn": execution_arn }),
+            )
+            .await?;
+        Ok(serde_json::from_value(res)?)
+    }
+
+    pub async fn stop_execution(
+        &self,
+        execution_arn: &str,
+        cause: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.call(
+            "StopExecution",
+            json!({ "executionArn": execution_arn, "cause": cause }),
+        )
+        .await?;
+        Ok(())
+    }
+}
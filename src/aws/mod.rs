@@ -0,0 +1,4 @@
+mod client;
+mod sigv4;
+
+pub use client::*;
@@ -1,3 +1,4 @@
+use crate::azure::credential;
 use azure_core::credentials::TokenCredential;
 use azure_identity::DefaultAzureCredential;
 use log::debug;
@@ -104,7 +105,7 @@ impl AdfClient {
         factory_name: String,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         debug!("Checking environment variables...");
-        let credential = DefaultAzureCredential::new()?;
+        let credential = credential()?;
         Ok(Self {
             subscription_id,
             resource_group,
@@ -170,4 +171,30 @@ impl AdfClient {
             Err(format!("Failed to get pipeline status: {}", res.text().await?).into())
         }
     }
-}
\ No newline at end of file
+
+    pub async fn cancel_pipeline_run(
+        &self,
+        run_id: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let token = self
+            .credential
+            .get_token(&["https://management.azure.com/.default"])
+            .await?;
+        let url = format!(
+            "https://management.azure.com/subscriptions/{}/resourceGroups/{}/providers/Microsoft.DataFactory/factories/{}/pipelineruns/{}/cancel?api-version=2018-06-01",
+            self.subscription_id, self.resource_group, self.factory_name, run_id
+        );
+        let res = self
+            .client
+            .post(&url)
+            .bearer_auth(token.token.secret())
+            .header("Content-Length", "0")
+            .send()
+            .await?;
+        if res.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("Failed to cancel pipeline run: {}", res.text().await?).into())
+        }
+    }
+}
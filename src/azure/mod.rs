@@ -1,3 +1,5 @@
 mod client;
+mod token;
 
 pub use client::*;
+pub use token::*;
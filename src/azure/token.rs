@@ -0,0 +1,22 @@
+use azure_core::credentials::TokenCredential;
+use azure_identity::DefaultAzureCredential;
+use std::sync::{Arc, OnceLock};
+
+static CREDENTIAL: OnceLock<Arc<DefaultAzureCredential>> = OnceLock::new();
+
+/// The engine's own identity: environment service principal, managed identity or Azure CLI
+/// login. Built once and shared, so tokens it caches are reused across runs.
+pub fn credential() -> azure_core::Result<Arc<DefaultAzureCredential>> {
+    if let Some(credential) = CREDENTIAL.get() {
+        return Ok(credential.clone());
+    }
+    let credential = DefaultAzureCredential::new()?;
+    Ok(CREDENTIAL.get_or_init(|| credential).clone())
+}
+
+/// Microsoft Entra ID access token for `scope` (e.g. `https://management.azure.com/.default`),
+/// obtained with the engine's [`credential`].
+pub async fn access_token(scope: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let token = credential()?.get_token(&[scope]).await?;
+    Ok(token.token.secret().to_string())
+}
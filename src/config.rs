@@ -1,4 +1,6 @@
+use std::collections::HashMap;
 use std::env;
+use std::str::FromStr;
 
 #[derive(Debug, Clone)]
 pub enum ShardMode {
@@ -13,7 +15,21 @@ pub enum ShardMode {
 pub struct AppConfig {
     pub shard_mode: ShardMode,
     pub database_url: String,
+    #[allow(dead_code)]
     pub tick_interval_secs: u64,
+    pub node_id: String,
+    /// Default execution timeout per task type, used when a job sets none
+    pub task_timeouts: HashMap<String, u64>,
+    /// A fire later than this is treated as missed and handled by the job's misfire policy
+    pub misfire_threshold_secs: u64,
+    /// Maximum number of task handlers running at once on this node
+    pub max_in_flight: usize,
+    /// Concurrency limit per task type, on top of `max_in_flight`
+    pub task_concurrency: HashMap<String, usize>,
+    /// How long a shutdown waits for runs in progress before interrupting them
+    pub shutdown_grace_secs: u64,
+    /// Jitter window for jobs that set none; 0 fires exactly on schedule
+    pub default_jitter_secs: u64,
 }
 
 impl AppConfig {
@@ -51,10 +67,66 @@ impl AppConfig {
             .and_then(|v| v.parse().ok())
             .unwrap_or(1);
 
+        // Recorded on every run so history shows which node executed it
+        let node_id = env::var("NODE_ID")
+            .or_else(|_| env::var("HOSTNAME"))
+            .unwrap_or_else(|_| match &shard_mode {
+                ShardMode::Distributed { shard_id, .. } => format!("shard-{}", shard_id),
+                ShardMode::Local => "local".to_string(),
+            });
+
+        // e.g. TASK_TIMEOUTS=adf_pipeline=7200,print=60
+        let task_timeouts = env::var("TASK_TIMEOUTS")
+            .map(|v| parse_key_values(&v))
+            .unwrap_or_default();
+
+        let misfire_threshold_secs = env::var("MISFIRE_THRESHOLD_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(60);
+
+        let max_in_flight = env::var("MAX_IN_FLIGHT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(64);
+
+        // e.g. TASK_CONCURRENCY=adf_pipeline=10
+        let task_concurrency = env::var("TASK_CONCURRENCY")
+            .map(|v| parse_key_values(&v))
+            .unwrap_or_default();
+
+        let shutdown_grace_secs = env::var("SHUTDOWN_GRACE_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(30);
+
+        let default_jitter_secs = env::var("DEFAULT_JITTER_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+
         AppConfig {
             shard_mode,
             database_url,
             tick_interval_secs,
+            node_id,
+            task_timeouts,
+            misfire_threshold_secs,
+            max_in_flight,
+            task_concurrency,
+            shutdown_grace_secs,
+            default_jitter_secs,
         }
     }
 }
+
+fn parse_key_values<T: FromStr>(value: &str) -> HashMap<String, T> {
+    value
+        .split(',')
+        .filter_map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            let value = value.trim().parse().ok()?;
+            Some((key.trim().to_string(), value))
+        })
+        .collect()
+}
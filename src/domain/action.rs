@@ -0,0 +1,25 @@
+use crate::domain::task_payload::TaskPayload;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// Something the engine does after a fire of a job finished with a given outcome.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum FollowUpAction {
+    /// Start another job now, outside its schedule
+    TriggerJob { job_id: String },
+    /// Run a task once, e.g. a compensating cleanup pipeline; recorded with the job
+    RunTask { task: Box<TaskPayload> },
+    /// Pause the job that just ran
+    DisableJob,
+}
+
+impl Display for FollowUpAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FollowUpAction::TriggerJob { job_id } => write!(f, "trigger job {}", job_id),
+            FollowUpAction::RunTask { task } => write!(f, "run {} task", task),
+            FollowUpAction::DisableJob => write!(f, "disable job"),
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::schedule::parse_timezone;
+
+/// Named business calendar that narrows the fires of the jobs attached to it: fires on a
+/// holiday, outside every include window (if there are any) or inside an exclude window are
+/// dropped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Calendar {
+    pub id: String,
+    pub name: String,
+    /// IANA timezone dates and windows are read in; the job's own timezone if omitted
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Whole days without fires, e.g. public holidays
+    #[serde(default)]
+    pub holidays: Vec<NaiveDate>,
+    /// Windows fires are limited to; no limit if empty
+    #[serde(default)]
+    pub include: Vec<TimeWindow>,
+    /// Windows without fires, e.g. a maintenance window
+    #[serde(default)]
+    pub exclude: Vec<TimeWindow>,
+}
+
+/// Recurring window of wall-clock time. A window whose `end` is not after its `start` runs
+/// past midnight into the next day; equal times cover a full day.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeWindow {
+    /// Days the window opens on; every day if empty
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl TimeWindow {
+    fn opens_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    fn contains(&self, day: Weekday, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.opens_on(day) && self.start <= time && time < self.end
+        } else {
+            (self.opens_on(day) && time >= self.start)
+                || (self.opens_on(day.pred()) && time < self.end)
+        }
+    }
+}
+
+impl Calendar {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name must not be empty".to_string());
+        }
+        parse_timezone(self.timezone.as_deref())?;
+        Ok(())
+    }
+
+    /// Whether a fire at `at` is allowed, reading dates and windows in the calendar's
+    /// timezone or else in `job_tz`.
+    pub fn allows(&self, at: DateTime<Utc>, job_tz: Tz) -> bool {
+        let tz = match &self.timezone {
+            Some(name) => name.parse().unwrap_or(job_tz),
+            None => job_tz,
+        };
+        let local = at.with_timezone(&tz).naive_local();
+        let (day, time) = (local.weekday(), local.time());
+
+        !self.holidays.contains(&local.date())
+            && (self.include.is_empty() || self.include.iter().any(|w| w.contains(day, time)))
+            && !self.exclude.iter().any(|w| w.contains(day, time))
+    }
+}
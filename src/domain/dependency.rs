@@ -0,0 +1,84 @@
+use std::collections::{HashMap, HashSet};
+
+/// Upstream edges of the job graph: job id → ids of the jobs it depends on.
+pub type DependencyGraph = HashMap<String, Vec<String>>;
+
+/// The cycle that making `job_id` depend on `depends_on` would close, as a path that starts
+/// and ends with `job_id`. The job's current edges in `graph` are ignored.
+pub fn find_cycle(
+    graph: &DependencyGraph,
+    job_id: &str,
+    depends_on: &[String],
+) -> Option<Vec<String>> {
+    let mut visited = HashSet::new();
+    for upstream in depends_on {
+        let mut path = vec![job_id.to_string()];
+        if reaches(graph, upstream, job_id, &mut visited, &mut path) {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// Depth-first walk along upstream edges; on success `path` holds the route to `target`.
+fn reaches(
+    graph: &DependencyGraph,
+    from: &str,
+    target: &str,
+    visited: &mut HashSet<String>,
+    path: &mut Vec<String>,
+) -> bool {
+    path.push(from.to_string());
+    if from == target {
+        return true;
+    }
+    if visited.insert(from.to_string()) {
+        for next in graph.get(from).into_iter().flatten() {
+            if reaches(graph, next, target, visited, path) {
+                return true;
+            }
+        }
+    }
+    path.pop();
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(edges: &[(&str, &[&str])]) -> DependencyGraph {
+        edges
+            .iter()
+            .map(|(job, upstream)| {
+                let upstream = upstream.iter().map(|id| id.to_string()).collect();
+                (job.to_string(), upstream)
+            })
+            .collect()
+    }
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn self_loop_is_a_cycle() {
+        let cycle = find_cycle(&DependencyGraph::new(), "a", &ids(&["a"]));
+        assert_eq!(cycle, Some(ids(&["a", "a"])));
+    }
+
+    #[test]
+    fn indirect_cycle_is_found() {
+        // c depends on b, b on a; making a depend on c closes a → c → b → a
+        let graph = graph(&[("b", &["a"]), ("c", &["b"])]);
+        let cycle = find_cycle(&graph, "a", &ids(&["c"]));
+        assert_eq!(cycle, Some(ids(&["a", "c", "b", "a"])));
+    }
+
+    #[test]
+    fn diamond_is_not_a_cycle() {
+        // b and c both depend on a; d joins them
+        let graph = graph(&[("b", &["a"]), ("c", &["a"])]);
+        assert_eq!(find_cycle(&graph, "d", &ids(&["b", "c"])), None);
+    }
+}
@@ -0,0 +1,400 @@
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use cron::{Schedule, TimeUnitSpec};
+use std::str::FromStr;
+
+use crate::domain::calendar::Calendar;
+use crate::domain::model::JobSchedule;
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Plain-English description of when a job fires, e.g. "At 09:00, on Monday through Friday
+/// (Asia/Bangkok)", including what its calendar and jitter add.
+pub fn describe(
+    schedule: &JobSchedule,
+    tz: Tz,
+    calendar: Option<&Calendar>,
+    jitter: Duration,
+) -> String {
+    let mut description = match schedule {
+        JobSchedule::Cron { expression } => match Schedule::from_str(expression) {
+            Ok(cron) => format!("{} ({})", describe_cron(&cron), tz.name()),
+            Err(_) => format!("cron '{}'", expression),
+        },
+        JobSchedule::Once { at } => format!("once at {}", timestamp(*at)),
+        JobSchedule::FixedDelay { interval_secs } => {
+            let (n, unit) = in_units(*interval_secs);
+            format!("{} after the previous run finishes", count(n, unit))
+        }
+        JobSchedule::FixedRate {
+            interval_secs,
+            start_at,
+        } => {
+            let (n, unit) = in_units(*interval_secs);
+            match start_at {
+                Some(start_at) => {
+                    format!("{}, starting at {}", every(n, unit), timestamp(*start_at))
+                }
+                None => every(n, unit),
+            }
+        }
+    };
+    if let Some(calendar) = calendar {
+        description.push_str(&format!(
+            ", except when calendar '{}' excludes it",
+            calendar.name
+        ));
+    }
+    if jitter > Duration::zero() {
+        description.push_str(&format!(
+            ", delayed by {}",
+            count(jitter.num_seconds() as u64, "second")
+        ));
+    }
+    capitalize(description)
+}
+
+/// Values of one cron field.
+enum Values {
+    All,
+    /// Every `step` values, the first being `from`
+    Step {
+        step: u32,
+        from: u32,
+    },
+    List(Vec<u32>),
+}
+
+impl Values {
+    fn of(field: &impl TimeUnitSpec, min: u32, max: u32) -> Self {
+        if field.is_all() {
+            return Values::All;
+        }
+        let list: Vec<u32> = field.iter().collect();
+        if let [first, second, .., last] = list[..] {
+            let step = second - first;
+            if step > 1
+                && first - min < step
+                && last + step > max
+                && list.windows(2).all(|pair| pair[1] - pair[0] == step)
+            {
+                return Values::Step { step, from: first };
+            }
+        }
+        Values::List(list)
+    }
+
+    fn is_single(&self, value: u32) -> bool {
+        matches!(self, Values::List(list) if list[..] == [value])
+    }
+
+    /// "every 15 minutes", "at minute 30" or "at minutes 0 through 5".
+    fn phrase(&self, unit: &str, min: u32) -> String {
+        match self {
+            Values::All => every(1_u32, unit),
+            Values::Step { step, from } if *from == min => every(*step, unit),
+            Values::Step { step, from } => {
+                format!("{} starting at {} {}", every(*step, unit), unit, from)
+            }
+            Values::List(list) => format!("at {}", numbered(list, unit)),
+        }
+    }
+}
+
+fn describe_cron(cron: &Schedule) -> String {
+    let (time, at_fixed_times) = describe_time(cron);
+    let mut parts = vec![time];
+
+    let days_of_month = Values::of(cron.days_of_month(), 1, 31);
+    match &days_of_month {
+        Values::All => {}
+        Values::List(days) => parts.push(format!("on {} of the month", numbered(days, "day"))),
+        step => parts.push(format!("{} of the month", step.phrase("day", 1))),
+    }
+    if !cron.days_of_week().is_all() {
+        let days: Vec<u32> = cron.days_of_week().iter().collect();
+        let days = list(&days, |day| WEEKDAYS[day as usize - 1].to_string());
+        match days_of_month {
+            Values::All => parts.push(format!("on {}", days)),
+            _ => parts.push(format!("only on {}", days)),
+        }
+    }
+    match Values::of(cron.months(), 1, 12) {
+        Values::All => {}
+        Values::List(months) => parts.push(format!(
+            "in {}",
+            list(&months, |month| MONTHS[month as usize - 1].to_string())
+        )),
+        Values::Step { step, from } => parts.push(format!(
+            "{} starting in {}",
+            every(step, "month"),
+            MONTHS[from as usize - 1]
+        )),
+    }
+    if !cron.years().is_all() {
+        let years: Vec<u32> = cron.years().iter().collect();
+        parts.push(format!("in {}", list(&years, |year| year.to_string())));
+    }
+
+    if parts.len() == 1 && at_fixed_times {
+        parts.push("every day".to_string());
+    }
+    parts.join(", ")
+}
+
+/// Time-of-day part of a cron description, and whether it names fixed times of day.
+fn describe_time(cron: &Schedule) -> (String, bool) {
+    let seconds = Values::of(cron.seconds(), 0, 59);
+    let minutes = Values::of(cron.minutes(), 0, 59);
+    let hours = Values::of(cron.hours(), 0, 23);
+
+    if let (Values::List(s), Values::List(m), Values::List(h)) = (&seconds, &minutes, &hours)
+        && let ([second], [minute]) = (&s[..], &m[..])
+        && h.len() <= 4
+    {
+        let times: Vec<_> = h
+            .iter()
+            .map(|hour| clock(*hour, *minute, *second))
+            .collect();
+        return (format!("at {}", join(times)), true);
+    }
+
+    let mut parts = Vec::new();
+    if !seconds.is_single(0) {
+        parts.push(seconds.phrase("second", 0));
+    }
+    let on_the_minute = parts.is_empty();
+    match &minutes {
+        Values::All if !on_the_minute => {
+            if let Values::List(_) = seconds {
+                parts.push("of every minute".to_string());
+            }
+        }
+        _ if minutes.is_single(0) && on_the_minute && !matches!(hours, Values::List(_)) => {}
+        _ => parts.push(minutes.phrase("minute", 0)),
+    }
+    match &hours {
+        Values::All if parts.is_empty() => parts.push("every hour".to_string()),
+        Values::All if matches!(minutes, Values::List(_)) => {
+            parts.push("of every hour".to_string())
+        }
+        Values::All => {}
+        Values::List(list) if matches!(minutes, Values::List(_)) => {
+            parts.push(format!("of {}", numbered(list, "hour")))
+        }
+        Values::List(list) => parts.push(format!("during {}", numbered(list, "hour"))),
+        step => parts.push(step.phrase("hour", 0)),
+    }
+    // "of ..." narrows the part before it rather than adding another condition
+    let mut time = String::new();
+    for part in parts {
+        if !time.is_empty() {
+            time.push_str(if part.starts_with("of ") { " " } else { ", " });
+        }
+        time.push_str(&part);
+    }
+    (time, false)
+}
+
+/// Splits a number of seconds into the largest unit it is a whole multiple of.
+fn in_units(secs: u64) -> (u64, &'static str) {
+    [(86_400, "day"), (3_600, "hour"), (60, "minute")]
+        .into_iter()
+        .find(|(size, _)| secs >= *size && secs.is_multiple_of(*size))
+        .map(|(size, unit)| (secs / size, unit))
+        .unwrap_or((secs, "second"))
+}
+
+fn every(n: impl Into<u64>, unit: &str) -> String {
+    match n.into() {
+        1 => format!("every {}", unit),
+        n => format!("every {} {}s", n, unit),
+    }
+}
+
+fn count(n: u64, unit: &str) -> String {
+    match n {
+        1 => format!("1 {}", unit),
+        n => format!("{} {}s", n, unit),
+    }
+}
+
+/// "minute 5" or "minutes 0, 15 and 30".
+fn numbered(values: &[u32], unit: &str) -> String {
+    let plural = if values.len() == 1 { "" } else { "s" };
+    format!("{}{} {}", unit, plural, list(values, |v| v.to_string()))
+}
+
+/// Lists sorted values, collapsing runs of three or more into "first through last".
+fn list(values: &[u32], label: impl Fn(u32) -> String) -> String {
+    let mut items = Vec::new();
+    let mut start = 0;
+    while start < values.len() {
+        let mut end = start;
+        while end + 1 < values.len() && values[end + 1] == values[end] + 1 {
+            end += 1;
+        }
+        if end - start >= 2 {
+            items.push(format!(
+                "{} through {}",
+                label(values[start]),
+                label(values[end])
+            ));
+        } else {
+            items.extend(values[start..=end].iter().map(|v| label(*v)));
+        }
+        start = end + 1;
+    }
+    join(items)
+}
+
+fn join(mut items: Vec<String>) -> String {
+    match items.pop() {
+        Some(last) if items.is_empty() => last,
+        Some(last) => format!("{} and {}", items.join(", "), last),
+        None => String::new(),
+    }
+}
+
+fn clock(hour: u32, minute: u32, second: u32) -> String {
+    match second {
+        0 => format!("{:02}:{:02}", hour, minute),
+        _ => format!("{:02}:{:02}:{:02}", hour, minute, second),
+    }
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+}
+
+fn capitalize(text: String) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cron(expression: &str) -> String {
+        let schedule = JobSchedule::Cron {
+            expression: expression.to_string(),
+        };
+        describe(&schedule, Tz::UTC, None, Duration::zero())
+    }
+
+    #[test]
+    fn fixed_times_of_day() {
+        assert_eq!(
+            cron("0 0 9 * * Mon-Fri"),
+            "At 09:00, on Monday through Friday (UTC)"
+        );
+        assert_eq!(
+            cron("0 30 9,17 * * *"),
+            "At 09:30 and 17:30, every day (UTC)"
+        );
+        assert_eq!(
+            cron("15 0 8 1 * *"),
+            "At 08:00:15, on day 1 of the month (UTC)"
+        );
+    }
+
+    #[test]
+    fn evenly_spaced_values_read_as_steps() {
+        assert_eq!(cron("0 */15 * * * *"), "Every 15 minutes (UTC)");
+        assert_eq!(
+            cron("0 5/20 * * * *"),
+            "Every 20 minutes starting at minute 5 (UTC)"
+        );
+        assert_eq!(cron("0 0 */6 * * *"), "Every 6 hours (UTC)");
+        assert_eq!(
+            cron("0 0 0 1 */3 *"),
+            "At 00:00, on day 1 of the month, every 3 months starting in January (UTC)"
+        );
+    }
+
+    #[test]
+    fn other_value_sets_are_listed() {
+        // Uneven gaps, a step that leaves room before the first value, and too few values
+        assert_eq!(
+            cron("0 0,10,25 * * * *"),
+            "At minutes 0, 10 and 25 of every hour (UTC)"
+        );
+        assert_eq!(
+            cron("0 10,20,30 * * * *"),
+            "At minutes 10, 20 and 30 of every hour (UTC)"
+        );
+        assert_eq!(
+            cron("0 0,30 * * * *"),
+            "At minutes 0 and 30 of every hour (UTC)"
+        );
+        assert_eq!(
+            cron("0 0-5 * * * *"),
+            "At minutes 0 through 5 of every hour (UTC)"
+        );
+    }
+
+    #[test]
+    fn of_parts_narrow_the_part_before_them() {
+        assert_eq!(cron("30 * * * * *"), "At second 30 of every minute (UTC)");
+        assert_eq!(
+            cron("0 15 1,2,3,4,5 * * *"),
+            "At minute 15 of hours 1 through 5 (UTC)"
+        );
+        assert_eq!(
+            cron("0 */10 9-17 * * *"),
+            "Every 10 minutes, during hours 9 through 17 (UTC)"
+        );
+    }
+
+    #[test]
+    fn interval_schedules_timezone_and_jitter() {
+        let bangkok: Tz = "Asia/Bangkok".parse().unwrap();
+        let delay = JobSchedule::FixedDelay { interval_secs: 300 };
+        assert_eq!(
+            describe(&delay, bangkok, None, Duration::zero()),
+            "5 minutes after the previous run finishes"
+        );
+        let rate = JobSchedule::FixedRate {
+            interval_secs: 7_200,
+            start_at: None,
+        };
+        assert_eq!(
+            describe(&rate, bangkok, None, Duration::seconds(30)),
+            "Every 2 hours, delayed by 30 seconds"
+        );
+        let cron = JobSchedule::Cron {
+            expression: "0 0 6 * * *".to_string(),
+        };
+        assert_eq!(
+            describe(&cron, bangkok, None, Duration::zero()),
+            "At 06:00, every day (Asia/Bangkok)"
+        );
+    }
+}
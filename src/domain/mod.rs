@@ -1,2 +1,8 @@
+pub mod action;
+pub mod calendar;
+pub mod dependency;
+pub mod describe;
 pub mod model;
+pub mod policy;
+pub mod schedule;
 pub mod task_payload;
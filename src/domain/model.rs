@@ -1,18 +1,36 @@
+use crate::domain::action::FollowUpAction;
+use crate::domain::calendar::Calendar;
+use crate::domain::policy::{MisfirePolicy, OverlapPolicy, RetryPolicy};
+use crate::domain::schedule::{jitter_offset, parse_timezone, schedule_fires, upcoming_fires};
 use crate::domain::task_payload::TaskPayload;
 use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use cron::Schedule;
 use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::fmt::Display;
 use std::str::FromStr;
+use uuid::Uuid;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum JobStatus {
     Start,
     Scheduled,
+    Queued,
     Running,
     Success,
     Failed,
+    Retrying,
+    TimedOut,
+    Cancelled,
+    Interrupted,
+    /// Left `running` by a process that died and could not be reattached
+    Lost,
+    Skipped,
     Disabled,
+    /// A one-shot job after its fire
+    Completed,
 }
 
 impl Display for JobStatus {
@@ -20,10 +38,18 @@ impl Display for JobStatus {
         let str = match self {
             JobStatus::Start => "start",
             JobStatus::Scheduled => "scheduled",
+            JobStatus::Queued => "queued",
             JobStatus::Running => "running",
             JobStatus::Success => "success",
             JobStatus::Failed => "failed",
+            JobStatus::Retrying => "retrying",
+            JobStatus::TimedOut => "timed_out",
+            JobStatus::Cancelled => "cancelled",
+            JobStatus::Interrupted => "interrupted",
+            JobStatus::Lost => "lost",
+            JobStatus::Skipped => "skipped",
             JobStatus::Disabled => "disabled",
+            JobStatus::Completed => "completed",
         }
         .to_string();
         write!(f, "{}", str)
@@ -37,47 +63,383 @@ impl std::str::FromStr for JobStatus {
         match s {
             "start" => Ok(JobStatus::Start),
             "scheduled" => Ok(JobStatus::Scheduled),
+            "queued" => Ok(JobStatus::Queued),
             "running" => Ok(JobStatus::Running),
             "success" => Ok(JobStatus::Success),
             "failed" => Ok(JobStatus::Failed),
+            "retrying" => Ok(JobStatus::Retrying),
+            "timed_out" => Ok(JobStatus::TimedOut),
+            "cancelled" => Ok(JobStatus::Cancelled),
+            "interrupted" => Ok(JobStatus::Interrupted),
+            "lost" => Ok(JobStatus::Lost),
+            "skipped" => Ok(JobStatus::Skipped),
             "disabled" => Ok(JobStatus::Disabled),
+            "completed" => Ok(JobStatus::Completed),
             _ => Err(()),
         }
     }
 }
 
+/// What caused a run to be started.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum RunTrigger {
+    /// A regular (or caught-up) fire of the job's schedule
+    #[default]
+    Schedule,
+    /// Requested through `POST /api/jobs/{id}/trigger`
+    Manual,
+    /// Started because all upstream jobs succeeded for the same schedule window
+    Dependency,
+    /// Started by an `on_success` / `on_failure` action of another run
+    FollowUp,
+}
+
+impl Display for RunTrigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            RunTrigger::Schedule => "schedule",
+            RunTrigger::Manual => "manual",
+            RunTrigger::Dependency => "dependency",
+            RunTrigger::FollowUp => "follow_up",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+impl std::str::FromStr for RunTrigger {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "schedule" => Ok(RunTrigger::Schedule),
+            "manual" => Ok(RunTrigger::Manual),
+            "dependency" => Ok(RunTrigger::Dependency),
+            "follow_up" => Ok(RunTrigger::FollowUp),
+            _ => Err(()),
+        }
+    }
+}
+
+/// When a job fires.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobSchedule {
+    /// Cron expression evaluated on the wall clock of the job's timezone
+    Cron { expression: String },
+    /// A single fire at `at`; the job is completed afterwards
+    Once { at: DateTime<Utc> },
+    /// Fires `interval_secs` after the previous run finished
+    FixedDelay { interval_secs: u64 },
+    /// Fires every `interval_secs` however long runs take, counted from `start_at` (the Unix
+    /// epoch if omitted, so `3600` fires on the hour)
+    FixedRate {
+        interval_secs: u64,
+        #[serde(default)]
+        start_at: Option<DateTime<Utc>>,
+    },
+}
+
+impl JobSchedule {
+    pub fn cron(&self) -> Option<&str> {
+        match self {
+            JobSchedule::Cron { expression } => Some(expression),
+            _ => None,
+        }
+    }
+
+    /// Whether fires are timed from the end of the previous run rather than by the clock.
+    pub fn is_fixed_delay(&self) -> bool {
+        matches!(self, JobSchedule::FixedDelay { .. })
+    }
+
+    /// Delay of every fire of job `job_id` within a jitter window of `window_secs`. One-shot
+    /// and fixed-delay fires are not spread.
+    pub fn jitter(&self, job_id: &str, window_secs: u64) -> chrono::Duration {
+        match self {
+            JobSchedule::Cron { .. } | JobSchedule::FixedRate { .. } => {
+                jitter_offset(job_id, window_secs)
+            }
+            JobSchedule::Once { .. } | JobSchedule::FixedDelay { .. } => chrono::Duration::zero(),
+        }
+    }
+
+    /// Whether the fire at `scheduled_at` is the last one the schedule will ever make.
+    pub fn is_last_fire(&self, scheduled_at: DateTime<Utc>) -> bool {
+        matches!(self, JobSchedule::Once { at } if *at == scheduled_at)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            JobSchedule::Cron { expression } => Schedule::from_str(expression)
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            JobSchedule::Once { at } if *at <= Utc::now() => {
+                Err("at must be in the future".to_string())
+            }
+            JobSchedule::FixedDelay { interval_secs: 0 }
+            | JobSchedule::FixedRate {
+                interval_secs: 0, ..
+            } => Err("interval_secs must be greater than 0".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Display for JobSchedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobSchedule::Cron { expression } => write!(f, "cron '{}'", expression),
+            JobSchedule::Once { at } => write!(f, "once at {}", at.to_rfc3339()),
+            JobSchedule::FixedDelay { interval_secs } => {
+                write!(f, "{}s after each run", interval_secs)
+            }
+            JobSchedule::FixedRate { interval_secs, .. } => write!(f, "every {}s", interval_secs),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
+#[allow(dead_code)]
 pub struct Job {
     pub id: String,
     pub name: String,
-    pub cron: String,
+    pub schedule: JobSchedule,
     pub task_type: TaskPayload,
     pub payload: String,
     pub last_run: Option<DateTime<Utc>>,
     pub status: JobStatus,
     pub message: Option<String>,
+    pub retry_policy: Option<RetryPolicy>,
+    pub timeout_secs: Option<u64>,
+    pub overlap_policy: OverlapPolicy,
+    pub misfire_policy: MisfirePolicy,
+    /// Zone the cron expression is evaluated in
+    pub timezone: Tz,
+    /// Order in the worker queue when the engine is at capacity; higher runs first
+    pub priority: i32,
+    /// Upstream job ids; a job with dependencies runs when they all succeed instead of on `cron`
+    pub depends_on: Vec<String>,
+    /// Actions run after a fire succeeded
+    pub on_success: Vec<FollowUpAction>,
+    /// Actions run after a fire failed or timed out (after its last retry)
+    pub on_failure: Vec<FollowUpAction>,
+    /// Business calendar that drops fires on holidays and in blackout windows
+    pub calendar: Option<Calendar>,
+    /// Window fires are spread over to avoid bursts; the engine default if `None`
+    pub jitter_secs: Option<u64>,
+    /// Cron expression of `schedule` parsed once, reused for every fire
+    pub cron: Option<Schedule>,
 }
 
 #[derive(Debug, Clone)]
 pub struct JobRaw {
     pub id: String,
     pub name: String,
-    pub cron: String,
+    pub schedule: JobSchedule,
     pub task_type: String,
     pub payload: String,
     pub last_run: Option<DateTime<Utc>>,
     pub status: JobStatus,
     pub message: Option<String>,
+    pub retry_policy: Option<RetryPolicy>,
+    pub timeout_secs: Option<u64>,
+    pub overlap_policy: OverlapPolicy,
+    pub misfire_policy: MisfirePolicy,
+    /// IANA timezone name, `None` for UTC
+    pub timezone: Option<String>,
+    pub priority: i32,
+    pub depends_on: Vec<String>,
+    pub on_success: Vec<FollowUpAction>,
+    pub on_failure: Vec<FollowUpAction>,
+    pub calendar_id: Option<String>,
+    /// The calendar `calendar_id` refers to, filled in by the store
+    pub calendar: Option<Calendar>,
+    pub jitter_secs: Option<u64>,
+    /// When the job was last started by its upstream jobs; only successes after it count
+    /// towards the next start
+    pub dependencies_met_at: Option<DateTime<Utc>>,
+}
+
+/// One execution of a job, kept in the `job_runs` ledger.
+#[derive(Debug, Clone)]
+pub struct JobRun {
+    pub id: String,
+    pub job_id: String,
+    pub scheduled_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub duration_ms: Option<i64>,
+    pub status: JobStatus,
+    pub message: Option<String>,
+    pub node_id: String,
+    /// 1-based attempt number within the same scheduled fire
+    pub attempt: u32,
+    pub trigger: RunTrigger,
+    /// User who requested a manual run
+    pub triggered_by: Option<String>,
+    /// Id of the remote execution (e.g. ADF `runId`), checkpointed by the handler
+    pub external_ref: Option<String>,
+    /// Run whose outcome started this one (upstream job or follow-up action)
+    pub parent_run_id: Option<String>,
+    /// What the task reported, e.g. a command's exit code and captured output
+    pub output: Option<serde_json::Value>,
+}
+
+impl JobRun {
+    pub fn start(job_id: &str, scheduled_at: DateTime<Utc>, node_id: &str, attempt: u32) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            job_id: job_id.to_string(),
+            scheduled_at,
+            started_at: Some(Utc::now()),
+            finished_at: None,
+            duration_ms: None,
+            status: JobStatus::Running,
+            message: None,
+            node_id: node_id.to_string(),
+            attempt,
+            trigger: RunTrigger::Schedule,
+            triggered_by: None,
+            external_ref: None,
+            parent_run_id: None,
+            output: None,
+        }
+    }
+
+    /// A fire that never started, e.g. because the previous run was still going.
+    pub fn skipped(
+        job_id: &str,
+        scheduled_at: DateTime<Utc>,
+        node_id: &str,
+        message: &str,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            job_id: job_id.to_string(),
+            scheduled_at,
+            started_at: None,
+            finished_at: Some(Utc::now()),
+            duration_ms: None,
+            status: JobStatus::Skipped,
+            message: Some(message.to_string()),
+            node_id: node_id.to_string(),
+            attempt: 0,
+            trigger: RunTrigger::Schedule,
+            triggered_by: None,
+            external_ref: None,
+            parent_run_id: None,
+            output: None,
+        }
+    }
+
+    pub fn finish(&mut self, status: JobStatus, message: &str) {
+        let now = Utc::now();
+        self.duration_ms = self
+            .started_at
+            .map(|started| (now - started).num_milliseconds());
+        self.finished_at = Some(now);
+        self.status = status;
+        self.message = Some(message.to_string());
+    }
+}
+
+/// Fires that came due inside a window without being executed.
+#[derive(Debug, Clone, Default)]
+pub struct MissedFires {
+    pub total: usize,
+    pub first: Option<DateTime<Utc>>,
+    /// The most recent missed fires, oldest first
+    pub recent: Vec<DateTime<Utc>>,
 }
 
 impl Job {
-    pub fn next_run(&self) -> Option<DateTime<Utc>> {
-        let schedule = Schedule::from_str(&self.cron).ok()?;
-        schedule.upcoming(Utc).next()
+    /// Fires strictly after `after`; for a fixed-delay job, `after` is the end of the
+    /// previous run.
+    fn fires_after(&self, after: DateTime<Utc>) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+        schedule_fires(
+            &self.schedule,
+            self.cron.as_ref(),
+            self.timezone,
+            self.calendar.as_ref(),
+            after,
+        )
+    }
+
+    pub fn next_run_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.fires_after(after).next()
+    }
+
+    /// How long each fire is delayed past its scheduled time.
+    pub fn jitter(&self, default_jitter_secs: u64) -> chrono::Duration {
+        self.schedule
+            .jitter(&self.id, self.jitter_secs.unwrap_or(default_jitter_secs))
+    }
+
+    /// Follow-up actions for a fire that ended with `status`. Cancelled, interrupted and
+    /// skipped fires have none.
+    pub fn follow_ups(&self, status: &JobStatus) -> &[FollowUpAction] {
+        match status {
+            JobStatus::Success => &self.on_success,
+            JobStatus::Failed | JobStatus::TimedOut | JobStatus::Lost => &self.on_failure,
+            _ => &[],
+        }
+    }
+
+    /// Fires in `(since, until]`, keeping only the last `keep` of them.
+    pub fn missed_fires(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        keep: usize,
+    ) -> MissedFires {
+        let mut missed = MissedFires::default();
+        let mut recent = VecDeque::with_capacity(keep);
+        for fire in self.fires_after(since).take_while(|fire| *fire <= until) {
+            missed.total += 1;
+            missed.first.get_or_insert(fire);
+            recent.push_back(fire);
+            if recent.len() > keep {
+                recent.pop_front();
+            }
+        }
+        missed.recent = recent.into();
+        missed
     }
 }
 
 impl JobRaw {
+    /// When the next fire is dispatched, jitter included, or `None` if the schedule or
+    /// timezone is invalid, the schedule has no fire left or the calendar allows none.
+    /// Fixed-delay jobs count from their last run.
+    pub fn next_run(&self, default_jitter_secs: u64) -> Option<DateTime<Utc>> {
+        self.next_runs(default_jitter_secs, 1).into_iter().next()
+    }
+
+    /// Dispatch times of up to `count` upcoming fires; see [`upcoming_fires`].
+    pub fn next_runs(&self, default_jitter_secs: u64, count: usize) -> Vec<DateTime<Utc>> {
+        let (Ok(cron), Ok(tz)) = (
+            self.schedule.cron().map(Schedule::from_str).transpose(),
+            parse_timezone(self.timezone.as_deref()),
+        ) else {
+            return Vec::new();
+        };
+        upcoming_fires(
+            &self.schedule,
+            cron.as_ref(),
+            tz,
+            self.calendar.as_ref(),
+            self.jitter(default_jitter_secs),
+            self.last_run,
+            count,
+        )
+    }
+
+    /// How long each fire is delayed past its scheduled time.
+    pub fn jitter(&self, default_jitter_secs: u64) -> chrono::Duration {
+        self.schedule
+            .jitter(&self.id, self.jitter_secs.unwrap_or(default_jitter_secs))
+    }
+
     pub fn to_job(&self) -> Result<Job, Box<dyn std::error::Error>> {
         let task_json = format!(
             r#"{{ "task_type": "{}", "payload": {} }}"#,
@@ -90,12 +452,81 @@ impl JobRaw {
         Ok(Job {
             id: self.id.clone(),
             name: self.name.clone(),
-            cron: self.cron.clone(),
+            schedule: self.schedule.clone(),
             task_type: task,
             last_run: self.last_run,
             status: self.status.clone(),
             payload: "".to_string(),
             message: self.message.clone(),
+            retry_policy: self.retry_policy.clone(),
+            timeout_secs: self.timeout_secs,
+            overlap_policy: self.overlap_policy,
+            misfire_policy: self.misfire_policy,
+            timezone: parse_timezone(self.timezone.as_deref())?,
+            priority: self.priority,
+            depends_on: self.depends_on.clone(),
+            on_success: self.on_success.clone(),
+            on_failure: self.on_failure.clone(),
+            calendar: self.calendar.clone(),
+            jitter_secs: self.jitter_secs,
+            cron: self.schedule.cron().map(Schedule::from_str).transpose()?,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing;
+
+    fn utc(at: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(at).unwrap().to_utc()
+    }
+
+    fn hourly() -> Job {
+        let mut raw = testing::job_raw("job");
+        raw.schedule = JobSchedule::Cron {
+            expression: "0 0 * * * *".to_string(),
+        };
+        raw.to_job().unwrap()
+    }
+
+    #[test]
+    fn missed_fires_count_the_window_and_keep_the_latest() {
+        // (since, until, keep) -> (total, first, recent)
+        let cases = [
+            ("10:30", "10:59", 5, 0, None, vec![]),
+            ("10:00", "11:00", 5, 1, Some("11:00"), vec!["11:00"]),
+            (
+                "09:59",
+                "13:30",
+                5,
+                4,
+                Some("10:00"),
+                vec!["10:00", "11:00", "12:00", "13:00"],
+            ),
+            (
+                "09:59",
+                "13:30",
+                2,
+                4,
+                Some("10:00"),
+                vec!["12:00", "13:00"],
+            ),
+            ("09:59", "13:30", 0, 4, Some("10:00"), vec![]),
+        ];
+        let at = |time: &str| utc(&format!("2024-03-01T{}:00Z", time));
+        let job = hourly();
+        for (since, until, keep, total, first, recent) in cases {
+            let missed = job.missed_fires(at(since), at(until), keep);
+            assert_eq!(missed.total, total, "{} to {}", since, until);
+            assert_eq!(missed.first, first.map(at), "{} to {}", since, until);
+            let recent: Vec<_> = recent.into_iter().map(at).collect();
+            assert_eq!(
+                missed.recent, recent,
+                "{} to {}, keep {}",
+                since, until, keep
+            );
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::time::Duration;
+
+/// Coarse classification of a task failure, used to decide whether it is worth retrying.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    /// Network errors, throttling or 5xx responses from the remote service
+    Transient,
+    /// The task ran and reported a failure (e.g. ADF pipeline status `Failed`)
+    Failed,
+    /// The task exceeded its execution timeout and was aborted
+    Timeout,
+    /// The run was cancelled by the engine (e.g. replaced by a newer fire); never retried
+    Cancelled,
+}
+
+impl Display for ErrorClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            ErrorClass::Transient => "transient",
+            ErrorClass::Failed => "failed",
+            ErrorClass::Timeout => "timeout",
+            ErrorClass::Cancelled => "cancelled",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Total attempts per fire, including the first one
+    #[serde(default = "RetryPolicy::default_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "RetryPolicy::default_initial_delay_secs")]
+    pub initial_delay_secs: u64,
+    #[serde(default = "RetryPolicy::default_backoff_multiplier")]
+    pub backoff_multiplier: f64,
+    #[serde(default = "RetryPolicy::default_max_delay_secs")]
+    pub max_delay_secs: u64,
+    #[serde(default = "RetryPolicy::default_retry_on")]
+    pub retry_on: Vec<ErrorClass>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            initial_delay_secs: Self::default_initial_delay_secs(),
+            backoff_multiplier: Self::default_backoff_multiplier(),
+            max_delay_secs: Self::default_max_delay_secs(),
+            retry_on: Self::default_retry_on(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn default_max_attempts() -> u32 {
+        3
+    }
+
+    fn default_initial_delay_secs() -> u64 {
+        30
+    }
+
+    fn default_backoff_multiplier() -> f64 {
+        2.0
+    }
+
+    fn default_max_delay_secs() -> u64 {
+        600
+    }
+
+    fn default_retry_on() -> Vec<ErrorClass> {
+        vec![ErrorClass::Transient]
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_attempts == 0 {
+            return Err("max_attempts must be at least 1".to_string());
+        }
+        if !self.backoff_multiplier.is_finite() || self.backoff_multiplier < 1.0 {
+            return Err("backoff_multiplier must be >= 1.0".to_string());
+        }
+        if self.max_delay_secs < self.initial_delay_secs {
+            return Err("max_delay_secs must be >= initial_delay_secs".to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether a failure of `class` on `attempt` (1-based) should be followed by another attempt.
+    pub fn should_retry(&self, attempt: u32, class: ErrorClass) -> bool {
+        attempt < self.max_attempts
+            && class != ErrorClass::Cancelled
+            && self.retry_on.contains(&class)
+    }
+
+    /// Backoff to wait after the given failed attempt (1-based).
+    pub fn delay_after(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let secs = self.initial_delay_secs as f64 * self.backoff_multiplier.powi(exponent);
+        Duration::from_secs_f64(secs.min(self.max_delay_secs as f64))
+    }
+}
+
+/// What the engine does when a fire comes due while the previous run of the same job is still going.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlapPolicy {
+    /// Start another run in parallel
+    Allow,
+    /// Drop the new fire and record it as skipped
+    #[default]
+    Skip,
+    /// Keep at most one pending fire and start it when the current run ends
+    QueueOne,
+    /// Cancel the running one and start the new fire immediately
+    Replace,
+}
+
+impl Display for OverlapPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            OverlapPolicy::Allow => "allow",
+            OverlapPolicy::Skip => "skip",
+            OverlapPolicy::QueueOne => "queue_one",
+            OverlapPolicy::Replace => "replace",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+impl std::str::FromStr for OverlapPolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(OverlapPolicy::Allow),
+            "skip" => Ok(OverlapPolicy::Skip),
+            "queue_one" => Ok(OverlapPolicy::QueueOne),
+            "replace" => Ok(OverlapPolicy::Replace),
+            _ => Err(()),
+        }
+    }
+}
+
+/// What the engine does with fires that came due while it was not running or was stalled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum MisfirePolicy {
+    /// Record missed fires as skipped and wait for the next regular one
+    #[default]
+    Skip,
+    /// Run the most recent missed fire once, record the rest as skipped
+    FireOnce,
+    /// Run up to `max` of the most recent missed fires in order, record the rest as skipped
+    FireAll { max: u32 },
+}
+
+impl MisfirePolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            MisfirePolicy::FireAll { max: 0 } => Err("fire_all requires max >= 1".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    /// How many of the most recent missed fires should be executed.
+    pub fn fires_to_run(&self) -> usize {
+        match self {
+            MisfirePolicy::Skip => 0,
+            MisfirePolicy::FireOnce => 1,
+            MisfirePolicy::FireAll { max } => *max as usize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(max_attempts: u32, retry_on: Vec<ErrorClass>) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_delay_secs: 30,
+            backoff_multiplier: 2.0,
+            max_delay_secs: 600,
+            retry_on,
+        }
+    }
+
+    #[test]
+    fn delay_grows_by_the_multiplier_up_to_the_cap() {
+        let policy = policy(10, vec![ErrorClass::Transient]);
+        let delays: Vec<u64> = (1..=7)
+            .map(|attempt| policy.delay_after(attempt).as_secs())
+            .collect();
+        assert_eq!(delays, [30, 60, 120, 240, 480, 600, 600]);
+        assert_eq!(policy.delay_after(u32::MAX), Duration::from_secs(600));
+
+        let constant = RetryPolicy {
+            backoff_multiplier: 1.0,
+            ..policy
+        };
+        assert_eq!(constant.delay_after(5), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn fractional_multiplier_keeps_the_fraction() {
+        let policy = RetryPolicy {
+            initial_delay_secs: 1,
+            backoff_multiplier: 1.5,
+            ..policy(3, vec![ErrorClass::Transient])
+        };
+        assert_eq!(policy.delay_after(2), Duration::from_millis(1500));
+        assert_eq!(policy.delay_after(3), Duration::from_millis(2250));
+    }
+
+    #[test]
+    fn retries_listed_classes_until_the_last_attempt() {
+        let three = policy(3, vec![ErrorClass::Transient, ErrorClass::Timeout]);
+        assert!(three.should_retry(1, ErrorClass::Transient));
+        assert!(three.should_retry(2, ErrorClass::Timeout));
+        assert!(!three.should_retry(3, ErrorClass::Transient));
+        assert!(!three.should_retry(1, ErrorClass::Failed));
+
+        let single = policy(1, vec![ErrorClass::Transient]);
+        assert!(!single.should_retry(1, ErrorClass::Transient));
+    }
+
+    #[test]
+    fn cancelled_runs_are_never_retried() {
+        let policy = policy(3, vec![ErrorClass::Cancelled, ErrorClass::Failed]);
+        assert!(!policy.should_retry(1, ErrorClass::Cancelled));
+        assert!(policy.should_retry(1, ErrorClass::Failed));
+    }
+
+    #[test]
+    fn validation_rejects_unusable_policies() {
+        assert!(policy(3, vec![]).validate().is_ok());
+        assert!(policy(0, vec![]).validate().is_err());
+        for multiplier in [0.5, f64::NAN, f64::INFINITY] {
+            let policy = RetryPolicy {
+                backoff_multiplier: multiplier,
+                ..policy(3, vec![])
+            };
+            assert!(policy.validate().is_err(), "{}", multiplier);
+        }
+        let capped_below_start = RetryPolicy {
+            max_delay_secs: 10,
+            ..policy(3, vec![])
+        };
+        assert!(capped_below_start.validate().is_err());
+    }
+}
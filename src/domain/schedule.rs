@@ -0,0 +1,229 @@
+use chrono::{DateTime, Duration, LocalResult, NaiveDateTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
+
+use crate::domain::calendar::Calendar;
+use crate::domain::model::JobSchedule;
+use crate::utils::stable_hash_job_id;
+
+/// How far ahead fires are searched for one the calendar allows before giving up.
+const CALENDAR_HORIZON_DAYS: i64 = 366;
+
+/// Parses an IANA timezone name; jobs without one run on UTC.
+pub fn parse_timezone(name: Option<&str>) -> Result<Tz, String> {
+    match name {
+        None => Ok(Tz::UTC),
+        Some(name) => name
+            .parse::<Tz>()
+            .map_err(|_| format!("unknown IANA timezone '{}'", name)),
+    }
+}
+
+/// Fire times strictly after `after`, with the cron expression evaluated on the wall clock of
+/// `tz`. A fire inside a DST gap runs once at the end of the gap; a fire inside a repeated
+/// hour runs once, on its first occurrence. Fires the calendar excludes are skipped; the
+/// search stops once no allowed fire comes within a year.
+pub fn fires_after<'a>(
+    schedule: &'a Schedule,
+    tz: Tz,
+    calendar: Option<&'a Calendar>,
+    after: DateTime<Utc>,
+) -> impl Iterator<Item = DateTime<Utc>> + 'a {
+    // Walking the schedule on a UTC clock that shows local wall time visits every
+    // local time exactly once, without cron's own DST handling
+    let wall_after = after.with_timezone(&tz).naive_local().and_utc();
+    let mut last = after;
+    let fires = schedule.after(&wall_after).filter_map(move |wall| {
+        let fire = resolve_local(tz, wall.naive_utc()).with_timezone(&Utc);
+        (fire > last).then(|| {
+            last = fire;
+            fire
+        })
+    });
+    on_calendar(fires, tz, calendar, after)
+}
+
+/// Fire times of any schedule strictly after `after`; `cron` is the parsed expression of a
+/// cron schedule. A fixed-delay schedule counts from `after`, which is then the end of the
+/// previous run.
+pub fn schedule_fires<'a>(
+    schedule: &'a JobSchedule,
+    cron: Option<&'a Schedule>,
+    tz: Tz,
+    calendar: Option<&'a Calendar>,
+    after: DateTime<Utc>,
+) -> Box<dyn Iterator<Item = DateTime<Utc>> + Send + 'a> {
+    match (schedule, cron) {
+        (JobSchedule::Cron { .. }, Some(cron)) => Box::new(fires_after(cron, tz, calendar, after)),
+        (JobSchedule::Cron { .. }, None) => Box::new(std::iter::empty()),
+        (JobSchedule::Once { at }, _) => {
+            let fire = Some(*at).filter(|at| *at > after);
+            Box::new(on_calendar(fire.into_iter(), tz, calendar, after))
+        }
+        (JobSchedule::FixedDelay { interval_secs }, _) => Box::new(on_calendar(
+            interval_fires(after, *interval_secs, after),
+            tz,
+            calendar,
+            after,
+        )),
+        (
+            JobSchedule::FixedRate {
+                interval_secs,
+                start_at,
+            },
+            _,
+        ) => Box::new(on_calendar(
+            interval_fires(
+                start_at.unwrap_or(DateTime::UNIX_EPOCH),
+                *interval_secs,
+                after,
+            ),
+            tz,
+            calendar,
+            after,
+        )),
+    }
+}
+
+/// Dispatch times of the next `count` fires after now, each delayed by `jitter`. A fixed-delay
+/// schedule only has its next fire, counted from the end of `last_run`, as later ones depend on
+/// how long runs take.
+pub fn upcoming_fires(
+    schedule: &JobSchedule,
+    cron: Option<&Schedule>,
+    tz: Tz,
+    calendar: Option<&Calendar>,
+    jitter: Duration,
+    last_run: Option<DateTime<Utc>>,
+    count: usize,
+) -> Vec<DateTime<Utc>> {
+    let now = Utc::now();
+    let (after, count) = match schedule {
+        JobSchedule::FixedDelay { .. } => (last_run.unwrap_or(now).min(now), count.min(1)),
+        _ => (now - jitter, count),
+    };
+    schedule_fires(schedule, cron, tz, calendar, after)
+        .take(count)
+        .map(|fire| (fire + jitter).max(now))
+        .collect()
+}
+
+/// Offset in `[0, window_secs)` derived from the job id alone, so every node and every
+/// restart delays the job's fires by the same amount.
+pub fn jitter_offset(job_id: &str, window_secs: u64) -> Duration {
+    if window_secs == 0 {
+        return Duration::zero();
+    }
+    let hash = stable_hash_job_id(job_id) as u64;
+    Duration::seconds((hash % window_secs) as i64)
+}
+
+/// `anchor + k * interval_secs` for every `k >= 0` that lies strictly after `after`.
+fn interval_fires(
+    anchor: DateTime<Utc>,
+    interval_secs: u64,
+    after: DateTime<Utc>,
+) -> impl Iterator<Item = DateTime<Utc>> + Send {
+    let interval = interval_secs.max(1) as i64;
+    let elapsed = (after - anchor).num_seconds();
+    let first = if after < anchor {
+        0
+    } else {
+        elapsed / interval + 1
+    };
+    (first..).map(move |k| anchor + Duration::seconds(k * interval))
+}
+
+/// Drops the fires the calendar excludes, giving up once no allowed fire comes within a year
+/// of `after`.
+fn on_calendar<'a>(
+    fires: impl Iterator<Item = DateTime<Utc>> + Send + 'a,
+    tz: Tz,
+    calendar: Option<&'a Calendar>,
+    after: DateTime<Utc>,
+) -> impl Iterator<Item = DateTime<Utc>> + Send + 'a {
+    let horizon = after + Duration::days(CALENDAR_HORIZON_DAYS);
+    let allowed = move |fire: &DateTime<Utc>| calendar.is_none_or(|c| c.allows(*fire, tz));
+    fires
+        .take_while(move |fire| *fire <= horizon || allowed(fire))
+        .filter(allowed)
+}
+
+fn resolve_local(tz: Tz, local: NaiveDateTime) -> DateTime<Tz> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(dt) => dt,
+        LocalResult::Ambiguous(earlier, _) => earlier,
+        LocalResult::None => {
+            // Clocks jumped forward over `local`: use the first wall-clock minute after the gap
+            let minute = local
+                .with_second(0)
+                .unwrap_or(local)
+                .with_nanosecond(0)
+                .unwrap_or(local);
+            (1..=24 * 60)
+                .find_map(|m| {
+                    tz.from_local_datetime(&(minute + Duration::minutes(m)))
+                        .earliest()
+                })
+                .unwrap_or_else(|| tz.from_utc_datetime(&local))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn utc(at: &str) -> DateTime<Utc> {
+        at.parse().unwrap()
+    }
+
+    fn new_york_fires(expression: &str, after: &str, count: usize) -> Vec<DateTime<Utc>> {
+        let cron = Schedule::from_str(expression).unwrap();
+        let tz: Tz = "America/New_York".parse().unwrap();
+        fires_after(&cron, tz, None, utc(after))
+            .take(count)
+            .collect()
+    }
+
+    #[test]
+    fn daily_fire_in_the_spring_gap_runs_at_the_end_of_it() {
+        // 2026-03-08 02:00 EST jumps to 03:00 EDT, so 02:30 does not exist that day
+        let fires = new_york_fires("0 30 2 * * *", "2026-03-07T12:00:00Z", 2);
+        assert_eq!(
+            fires,
+            vec![utc("2026-03-08T07:00:00Z"), utc("2026-03-09T06:30:00Z")]
+        );
+    }
+
+    #[test]
+    fn daily_fire_in_the_repeated_autumn_hour_runs_once() {
+        // 2026-11-01 02:00 EDT falls back to 01:00 EST, so 01:30 happens twice
+        let fires = new_york_fires("0 30 1 * * *", "2026-10-31T12:00:00Z", 2);
+        assert_eq!(
+            fires,
+            vec![utc("2026-11-01T05:30:00Z"), utc("2026-11-02T06:30:00Z")]
+        );
+    }
+
+    #[test]
+    fn hourly_fires_across_dst_transitions() {
+        // 01:00 EST, then 03:00 EDT for both the skipped 02:00 and 03:00 itself
+        let spring = new_york_fires("0 0 * * * *", "2026-03-08T05:30:00Z", 3);
+        assert_eq!(
+            spring,
+            vec![
+                utc("2026-03-08T06:00:00Z"),
+                utc("2026-03-08T07:00:00Z"),
+                utc("2026-03-08T08:00:00Z"),
+            ]
+        );
+        // 01:00 EDT, then the repeated 01:00 EST is skipped, then 02:00 EST
+        let autumn = new_york_fires("0 0 * * * *", "2026-11-01T04:30:00Z", 2);
+        assert_eq!(
+            autumn,
+            vec![utc("2026-11-01T05:00:00Z"), utc("2026-11-01T07:00:00Z")]
+        );
+    }
+}
@@ -1,4 +1,6 @@
+use crate::utils::parse_json_path;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
@@ -10,6 +12,12 @@ pub enum TaskPayload {
     AwsStepFunction(AwsStepFnConfig),
     #[serde(rename = "shell_command")]
     ShellCommand(ShellCommandConfig),
+    #[serde(rename = "http")]
+    Http(HttpConfig),
+    #[serde(rename = "logic_app")]
+    LogicApp(LogicAppConfig),
+    #[serde(rename = "wasm")]
+    Wasm(WasmConfig),
     #[serde(rename = "print")]
     Print(PrintConfig),
 }
@@ -20,10 +28,26 @@ impl TaskPayload {
             TaskPayload::AdfPipeline(_) => "adf_pipeline",
             TaskPayload::AwsStepFunction(_) => "aws_stepfn",
             TaskPayload::ShellCommand(_) => "shell_command",
+            TaskPayload::Http(_) => "http",
+            TaskPayload::LogicApp(_) => "logic_app",
+            TaskPayload::Wasm(_) => "wasm",
             TaskPayload::Print(_) => "print",
         }
     }
 
+    /// Checks what deserializing cannot, e.g. that a shell command has something to run.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            TaskPayload::ShellCommand(config) => config.validate(),
+            TaskPayload::Http(config) => config.validate(),
+            TaskPayload::AwsStepFunction(config) => config.validate(),
+            TaskPayload::LogicApp(config) => config.validate(),
+            TaskPayload::Wasm(config) => config.validate(),
+            _ => Ok(()),
+        }
+    }
+
+    #[allow(dead_code)]
     pub fn as_print(&self) -> Option<&PrintConfig> {
         match self {
             TaskPayload::Print(config) => Some(config),
@@ -44,12 +68,33 @@ impl TaskPayload {
         }
     }
 
+    pub fn as_http(&self) -> Option<&HttpConfig> {
+        match self {
+            TaskPayload::Http(config) => Some(config),
+            _ => None,
+        }
+    }
+
     pub fn as_stepfn(&self) -> Option<&AwsStepFnConfig> {
         match self {
             TaskPayload::AwsStepFunction(config) => Some(config),
             _ => None,
         }
     }
+
+    pub fn as_logic_app(&self) -> Option<&LogicAppConfig> {
+        match self {
+            TaskPayload::LogicApp(config) => Some(config),
+            _ => None,
+        }
+    }
+
+    pub fn as_wasm(&self) -> Option<&WasmConfig> {
+        match self {
+            TaskPayload::Wasm(config) => Some(config),
+            _ => None,
+        }
+    }
 }
 
 impl Display for TaskPayload {
@@ -69,20 +114,291 @@ pub struct AdfConfig {
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AwsStepFnConfig {
+    /// State machine ARN
     pub arn: String,
     pub input: serde_json::Value,
+    /// Region of the state machine; taken from `arn` if omitted
+    #[serde(default)]
+    pub region: Option<String>,
+    /// Step Functions endpoint to call instead of the regional one, e.g. a local stand-in
+    #[serde(default)]
+    pub endpoint: Option<String>,
+}
+
+impl AwsStepFnConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        // arn:<partition>:states:<region>:<account>:stateMachine:<name>
+        let parts: Vec<&str> = self.arn.split(':').collect();
+        if parts.len() < 7 || parts[0] != "arn" || parts[2] != "states" {
+            return Err(format!("'{}' is not a state machine ARN", self.arn));
+        }
+        if let Some(endpoint) = &self.endpoint {
+            reqwest::Url::parse(endpoint).map_err(|e| format!("invalid endpoint: {}", e))?;
+        }
+        Ok(())
+    }
+
+    pub fn region(&self) -> Option<String> {
+        self.region
+            .clone()
+            .or_else(|| self.arn.split(':').nth(3).map(str::to_string))
+            .filter(|region| !region.is_empty())
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct LogicAppConfig {
+    /// Callback URL of the workflow's HTTP trigger
     pub endpoint: String,
-    pub auth_type: String,
+    #[serde(default)]
+    pub auth_type: LogicAppAuth,
+    /// Scope of the token sent with `azure_ad` auth
+    #[serde(default = "default_logic_app_scope")]
+    pub scope: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Request body; a string is sent as is, any other JSON value as `application/json`
+    #[serde(default)]
+    pub body: Option<serde_json::Value>,
+    /// Bytes of the final response body kept with the run, the first ones if there are more
+    #[serde(default = "default_max_output_bytes")]
+    pub max_output_bytes: usize,
+    /// Workflow resource, needed to cancel a run
+    #[serde(default)]
+    pub workflow: Option<LogicAppWorkflow>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LogicAppWorkflow {
+    pub subscription_id: String,
+    pub resource_group: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogicAppAuth {
+    /// Shared access signature carried by `endpoint` itself (`sig` query parameter)
+    #[default]
+    Sas,
+    /// Microsoft Entra ID token for `scope`, obtained with the engine's Azure identity
+    AzureAd,
+}
+
+fn default_logic_app_scope() -> String {
+    "https://management.azure.com/.default".to_string()
+}
+
+impl LogicAppConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        let url =
+            reqwest::Url::parse(&self.endpoint).map_err(|e| format!("invalid endpoint: {}", e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("unsupported endpoint scheme '{}'", url.scheme()));
+        }
+        // Logic Apps reject a request that carries both a signature and a bearer token
+        let signed = url.query_pairs().any(|(name, _)| name == "sig");
+        match (self.auth_type, signed) {
+            (LogicAppAuth::Sas, false) => Err("SAS endpoint has no 'sig' parameter".to_string()),
+            (LogicAppAuth::AzureAd, true) => {
+                Err("remove 'sig' from the endpoint to use azure_ad auth".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ShellCommandConfig {
-    pub command: String,
+    /// Command line run by `sh -c` (`cmd /C` on Windows)
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Program and arguments run directly, without a shell, instead of `command`
+    #[serde(default)]
+    pub argv: Vec<String>,
+    /// Directory the process starts in; the engine's own if omitted
+    #[serde(default)]
+    pub working_dir: Option<String>,
+    /// Variables set on top of the engine's environment
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Text written to the process's standard input, which is empty otherwise
+    #[serde(default)]
+    pub stdin: Option<String>,
+    /// Exit codes that count as success; `[0]` if empty
+    #[serde(default)]
+    pub success_exit_codes: Vec<i32>,
+    /// Bytes kept of stdout and of stderr each, the last ones if there are more
+    #[serde(default = "default_max_output_bytes")]
+    pub max_output_bytes: usize,
 }
+
+fn default_max_output_bytes() -> usize {
+    64 * 1024
+}
+
+impl ShellCommandConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        match (&self.command, self.argv.is_empty()) {
+            (Some(_), false) => Err("give either command or argv, not both".to_string()),
+            (None, true) => Err("command or argv is required".to_string()),
+            (Some(command), true) if command.trim().is_empty() => {
+                Err("command must not be empty".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn is_success(&self, exit_code: i32) -> bool {
+        match self.success_exit_codes.is_empty() {
+            true => exit_code == 0,
+            false => self.success_exit_codes.contains(&exit_code),
+        }
+    }
+}
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HttpConfig {
+    /// HTTP method, `GET` if omitted
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Sent as [`LogicAppConfig::body`] is
+    #[serde(default)]
+    pub body: Option<serde_json::Value>,
+    #[serde(default)]
+    pub auth: Option<HttpAuth>,
+    /// Status codes that count as success; any 2xx if empty
+    #[serde(default)]
+    pub expected_status: Vec<u16>,
+    /// Check on the JSON response body, made after the status is accepted
+    #[serde(default)]
+    pub assert: Option<ResponseAssertion>,
+    /// As [`LogicAppConfig::max_output_bytes`], for the one response
+    #[serde(default = "default_max_output_bytes")]
+    pub max_output_bytes: usize,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HttpAuth {
+    Bearer {
+        token: String,
+    },
+    Basic {
+        username: String,
+        #[serde(default)]
+        password: Option<String>,
+    },
+    /// As [`LogicAppAuth::AzureAd`]
+    AzureAd {
+        scope: String,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ResponseAssertion {
+    /// JSONPath into the response body, e.g. `$.result.state`
+    pub path: String,
+    /// Value expected at `path`; the path only has to exist if omitted
+    #[serde(default)]
+    pub equals: Option<serde_json::Value>,
+}
+
+impl HttpConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        reqwest::Method::from_bytes(self.method.to_uppercase().as_bytes())
+            .map_err(|_| format!("invalid HTTP method '{}'", self.method))?;
+        let url = reqwest::Url::parse(&self.url).map_err(|e| format!("invalid url: {}", e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("unsupported url scheme '{}'", url.scheme()));
+        }
+        if let Some(assertion) = &self.assert {
+            parse_json_path(&assertion.path)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_expected(&self, status: u16) -> bool {
+        match self.expected_status.is_empty() {
+            true => (200..300).contains(&status),
+            false => self.expected_status.contains(&status),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WasmConfig {
+    /// Path of the WASI module (binary or text format)
+    pub module: String,
+    /// JSON handed to the module on its standard input
+    #[serde(default)]
+    pub input: serde_json::Value,
+    /// Arguments after the module name
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// The module's whole environment; nothing is inherited from the engine
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Execution budget, roughly one unit per WebAssembly instruction
+    #[serde(default = "default_wasm_fuel")]
+    pub fuel: u64,
+    /// Linear memory the module may grow to
+    #[serde(default = "default_wasm_max_memory_bytes")]
+    pub max_memory_bytes: usize,
+    /// Hosts the module may call through the host HTTP function, e.g. `api.example.com`,
+    /// `*.example.com` or `api.example.com:8443`; a host without a port only on the scheme's
+    /// default one. None if empty
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    /// As [`ShellCommandConfig::max_output_bytes`]
+    #[serde(default = "default_max_output_bytes")]
+    pub max_output_bytes: usize,
+}
+
+fn default_wasm_fuel() -> u64 {
+    1_000_000_000
+}
+
+fn default_wasm_max_memory_bytes() -> usize {
+    64 * 1024 * 1024
+}
+
+impl WasmConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.module.trim().is_empty() {
+            return Err("module is required".to_string());
+        }
+        if self.fuel == 0 || self.max_memory_bytes == 0 {
+            return Err("fuel and max_memory_bytes must be positive".to_string());
+        }
+        self.allowed_hosts
+            .iter()
+            .try_for_each(|entry| parse_allowed_host(entry).map(|_| ()))
+    }
+}
+
+/// Splits an `allowed_hosts` entry into its host and the port it is limited to, if any.
+pub fn parse_allowed_host(entry: &str) -> Result<(&str, Option<u16>), String> {
+    let invalid = || format!("'{}' is not a host name or host:port", entry);
+    let (host, port) = match entry.rsplit_once(':') {
+        // The colons of a bracketed IPv6 address are not a port
+        Some((host, port)) if !port.ends_with(']') => {
+            (host, Some(port.parse::<u16>().map_err(|_| invalid())?))
+        }
+        _ => (entry, None),
+    };
+    if host.is_empty() || host.contains('/') || (host.contains(':') && !host.starts_with('[')) {
+        return Err(invalid());
+    }
+    Ok((host, port))
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PrintConfig {
     pub message: String,
@@ -1,12 +1,16 @@
-use chrono::Utc;
-use log::{debug, error, info};
-use std::fmt::format;
-use std::sync::Arc;
-use tokio::time::{Duration, sleep};
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
 
 use crate::config::AppConfig;
-use crate::domain::model::{Job, JobStatus};
+use crate::domain::action::FollowUpAction;
+use crate::domain::model::{Job, JobSchedule, JobStatus, RunTrigger};
+use crate::engine::pool::PoolStats;
+use crate::engine::runner::{FireFinished, JobRunner};
 use crate::job::store::JobStore;
+use crate::scheduler::Scheduler;
 use crate::shard::ShardManager;
 use crate::task::registry::TaskRegistry;
 
@@ -15,6 +19,12 @@ pub struct JobEngine {
     store: Arc<dyn JobStore>,
     shard: Arc<dyn ShardManager>,
     task_registry: Arc<TaskRegistry>,
+    runner: Arc<JobRunner>,
+    scheduler: Scheduler,
+    /// Runs started before this belong to an earlier process
+    started_at: DateTime<Utc>,
+    /// Taken by [`run`](Self::run), which reacts to finished fires
+    finished: Mutex<Option<mpsc::UnboundedReceiver<FireFinished>>>,
 }
 
 impl JobEngine {
@@ -24,89 +34,482 @@ impl JobEngine {
         shard: Arc<dyn ShardManager>,
         task_registry: Arc<TaskRegistry>,
     ) -> Self {
+        let (finished_tx, finished) = mpsc::unbounded_channel();
+        let runner = Arc::new(JobRunner::new(
+            config.clone(),
+            store.clone(),
+            task_registry.clone(),
+            finished_tx,
+        ));
+        let misfire_threshold = chrono::Duration::seconds(config.misfire_threshold_secs as i64);
+        let scheduler = Scheduler::new(
+            runner.clone(),
+            misfire_threshold,
+            config.default_jitter_secs,
+        );
         Self {
             config,
             store,
             shard,
             task_registry,
+            runner,
+            scheduler,
+            started_at: Utc::now(),
+            finished: Mutex::new(Some(finished)),
         }
     }
 
+    /// Re-reads the job from the store and replaces its live schedule.
+    /// Safe to call repeatedly: a job never has more than one schedule.
     pub async fn reload_job_by_id(&self, job_id: &str) {
         debug!("Reloading job by ID: {}", job_id);
 
-        if let Some(job) = self
-            .store
-            .load_jobs()
-            .await
-            .into_iter()
-            .find(|job| job.id == job_id)
-        {
-            debug!("Found job with ID: {}", job_id);
-            self.schedule(job.to_job().unwrap()).await;
+        let Some(raw) = self.store.find_job(job_id).await else {
+            debug!("Job {} no longer exists, unscheduling", job_id);
+            self.unschedule(job_id);
+            return;
+        };
+
+        debug!("Found job with ID: {}", job_id);
+        if matches!(raw.status, JobStatus::Disabled | JobStatus::Completed) {
+            debug!("Job {} is paused or completed", job_id);
+            self.unschedule(job_id);
+            return;
+        }
+        match self.shard.get_local_jobs(vec![raw]).await.pop() {
+            Some(job) => self.schedule(job).await,
+            None => self.unschedule(job_id),
         }
     }
 
+    /// Stops the live schedule of a job. A run already in progress is allowed to finish.
+    pub fn unschedule(&self, job_id: &str) {
+        if self.scheduler.remove_job(job_id) {
+            info!("Unscheduled job {}", job_id);
+        }
+    }
+
+    /// Jitter window of jobs that set none.
+    pub fn default_jitter_secs(&self) -> u64 {
+        self.config.default_jitter_secs
+    }
+
+    /// Current worker usage and the fires waiting for a worker.
+    pub fn worker_stats(&self) -> PoolStats {
+        self.runner.worker_stats()
+    }
+
+    /// Runs the job once right now, outside its schedule and overlap policy.
+    /// Returns the instant recorded as the run's `scheduled_at`.
+    pub fn trigger_now(&self, job: Job, triggered_by: &str) -> DateTime<Utc> {
+        let scheduled_at = Utc::now();
+        info!("[{}] Manual run requested by {}", job.name, triggered_by);
+        self.spawn_run(job, scheduled_at, RunTrigger::Manual, triggered_by, None);
+        scheduled_at
+    }
+
+    fn spawn_run(
+        &self,
+        job: Job,
+        scheduled_at: DateTime<Utc>,
+        trigger: RunTrigger,
+        triggered_by: &str,
+        parent_run_id: Option<String>,
+    ) {
+        let runner = self.runner.clone();
+        let triggered_by = triggered_by.to_string();
+        let cancel = runner.run_token();
+        tokio::spawn(async move {
+            runner
+                .execute_with(
+                    &job,
+                    scheduled_at,
+                    trigger,
+                    Some(triggered_by),
+                    parent_run_id,
+                    cancel,
+                )
+                .await
+        });
+    }
+
     pub async fn schedule(&self, job: Job) {
-        let task_registry = self.task_registry.clone();
-        task_registry.print_all_handlers();
-        let store = self.store.clone();
-        store
+        self.start_schedule(job, None).await;
+    }
+
+    /// Hands the job to the dispatcher. With `catch_up_since`, fires missed after that
+    /// instant are first handled according to the job's misfire policy.
+    async fn start_schedule(&self, job: Job, catch_up_since: Option<DateTime<Utc>>) {
+        self.task_registry.print_all_handlers();
+        if !job.depends_on.is_empty() {
+            // Started by its upstream jobs, not by the dispatcher
+            self.unschedule(&job.id);
+            self.store
+                .update_status(&job.id, JobStatus::Scheduled, "Waiting for upstream jobs")
+                .await;
+            return;
+        }
+        self.store
             .update_status(&job.id, JobStatus::Scheduled, "Preparing for start")
             .await;
-        tokio::spawn(async move {
-            while let Some(next_time) = job.next_run() {
-                let dur = (next_time - Utc::now())
-                    .to_std()
-                    .unwrap_or(Duration::from_secs(1));
-                sleep(dur).await;
-                let task_type = job.task_type.clone();
-
-                match task_registry.get(task_type.task_type_name()) {
-                    Some(handler) => {
-                        store
-                            .update_status(&job.id, JobStatus::Start, "Starting")
-                            .await;
-                        info!("[{}] Executing task", job.name);
-                        store
-                            .update_status(&job.id, JobStatus::Running, "Running")
-                            .await;
-                        if let Err(e) = handler.handle(&task_type).await {
-                            error!("[{}] Task error: {}", job.name, e);
-                            store
-                                .update_status(
-                                    &job.id,
-                                    JobStatus::Failed,
-                                    format!("{}", e).as_str(),
-                                )
-                                .await;
-                        }else{
-                            store
-                                .update_status(&job.id, JobStatus::Success, "Successful")
-                                .await;
-                        }
-                    }
-                    None => error!("[{}] No handler for task type '{}'", job.name, task_type),
+        self.scheduler.add_job(job, catch_up_since);
+    }
+
+    /// Reattaches to runs a previous process left `running`, or records them as lost when
+    /// the task type cannot resume them.
+    async fn recover_runs(&self, jobs: &[Job]) {
+        let jobs: HashMap<&str, &Job> = jobs.iter().map(|job| (job.id.as_str(), job)).collect();
+        let orphaned = self
+            .store
+            .unfinished_runs()
+            .await
+            .into_iter()
+            .filter(|run| run.started_at.is_none_or(|at| at < self.started_at));
+        for run in orphaned {
+            // Runs of jobs owned by another shard are left to that node
+            let Some(job) = jobs.get(run.job_id.as_str()) else {
+                continue;
+            };
+            let resumable = self
+                .task_registry
+                .get(job.task_type.task_type_name())
+                .is_some_and(|handler| handler.can_resume());
+            match (&run.external_ref, resumable) {
+                (Some(_), true) => self.scheduler.resume_run((*job).clone(), run),
+                (None, true) => {
+                    let reason = "Process exited before the remote run was recorded";
+                    self.runner.record_lost(job, run, reason).await;
+                }
+                (_, false) => {
+                    let reason = "Process exited while the run was in progress";
+                    self.runner.record_lost(job, run, reason).await;
                 }
-                store.update_last_run(&job.id, Utc::now()).await;
             }
-            info!("[{}] Invalid cron expression", job.name);
-        });
+        }
     }
 
     pub async fn run(&self) {
-        let my_jobs = self
+        let jobs = self
             .shard
             .get_local_jobs(self.store.load_jobs().await)
             .await;
-        info!("Running scheduler with {} local jobs", my_jobs.len());
+        self.recover_runs(&jobs).await;
+
+        let (idle, my_jobs): (Vec<_>, Vec<_>) = jobs
+            .into_iter()
+            .partition(|job| matches!(job.status, JobStatus::Disabled | JobStatus::Completed));
+        if !idle.is_empty() {
+            info!("Skipping {} paused or completed job(s)", idle.len());
+        }
+        info!(
+            "Running scheduler with {} local jobs on node {}",
+            my_jobs.len(),
+            self.config.node_id
+        );
 
         for job in my_jobs {
-            self.schedule(job).await;
+            let since = self
+                .store
+                .last_scheduled_at(&job.id)
+                .await
+                .max(job.last_run);
+            // A one-shot job that never fired still owes its only fire
+            let since = since.or(match job.schedule {
+                JobSchedule::Once { at } => Some(at - chrono::Duration::milliseconds(1)),
+                _ => None,
+            });
+            self.start_schedule(job, since).await;
+        }
+
+        let finished = self.finished.lock().unwrap().take();
+        tokio::select! {
+            _ = self.scheduler.run() => {}
+            _ = self.follow_up(finished) => {}
+        }
+        info!("Scheduler stopped");
+    }
+
+    /// Reacts to finished fires until the engine stops.
+    async fn follow_up(&self, finished: Option<mpsc::UnboundedReceiver<FireFinished>>) {
+        let Some(mut finished) = finished else {
+            return std::future::pending().await;
+        };
+        while let Some(fire) = finished.recv().await {
+            self.scheduler.run_finished(&fire.job_id);
+            if self.is_shutting_down() {
+                continue;
+            }
+            self.run_actions(&fire).await;
+            if fire.status == JobStatus::Success {
+                self.start_downstream(&fire).await;
+            }
+        }
+    }
+
+    /// Carries out the job's follow-up actions for the outcome of `fire`. Runs they start
+    /// record the fire's last run as their parent.
+    async fn run_actions(&self, fire: &FireFinished) {
+        let Some(raw) = self.store.find_job(&fire.job_id).await else {
+            return;
+        };
+        let job = match raw.to_job() {
+            Ok(job) => job,
+            Err(e) => {
+                warn!("[{}] Cannot run follow-up actions: {}", raw.name, e);
+                return;
+            }
+        };
+
+        let outcome = if fire.status == JobStatus::Success {
+            "on_success"
+        } else {
+            "on_failure"
+        };
+        for action in job.follow_ups(&fire.status) {
+            info!("[{}] Follow-up ({}): {}", job.name, outcome, action);
+            match action {
+                FollowUpAction::TriggerJob { job_id } => {
+                    let Some(target) = self.store.find_job(job_id).await else {
+                        warn!("[{}] Follow-up job {} no longer exists", job.name, job_id);
+                        continue;
+                    };
+                    if target.status == JobStatus::Disabled {
+                        debug!("[{}] Paused, not started by a follow-up", target.name);
+                        continue;
+                    }
+                    match target.to_job() {
+                        Ok(target) => self.spawn_run(
+                            target,
+                            Utc::now(),
+                            RunTrigger::FollowUp,
+                            &job.id,
+                            fire.run_id.clone(),
+                        ),
+                        Err(e) => warn!("[{}] Cannot start follow-up job: {}", target.name, e),
+                    }
+                }
+                FollowUpAction::RunTask { task } => {
+                    let Some(parent_run_id) = fire.run_id.clone() else {
+                        continue;
+                    };
+                    let runner = self.runner.clone();
+                    let cancel = runner.run_token();
+                    let (job, task) = (job.clone(), task.clone());
+                    tokio::spawn(async move {
+                        runner.run_task(&job, &task, &parent_run_id, cancel).await
+                    });
+                }
+                FollowUpAction::DisableJob => {
+                    self.store
+                        .update_status(
+                            &job.id,
+                            JobStatus::Disabled,
+                            &format!("Disabled by {} action", outcome),
+                        )
+                        .await;
+                    self.unschedule(&job.id);
+                }
+            }
+        }
+    }
+
+    /// Starts every job downstream of `fire` whose other upstream jobs have also succeeded
+    /// since it was last started by them, so a diamond's join runs once per round and a
+    /// manually triggered upstream counts as well. The downstream run keeps the fire's
+    /// `scheduled_at`.
+    async fn start_downstream(&self, fire: &FireFinished) {
+        let window = fire.scheduled_at;
+        for raw in self.store.dependents_of(&fire.job_id).await {
+            if raw.status == JobStatus::Disabled {
+                debug!("[{}] Paused, not started by its upstream", raw.name);
+                continue;
+            }
+            let mut ready = true;
+            for upstream in raw.depends_on.iter().filter(|id| **id != fire.job_id) {
+                if !self
+                    .store
+                    .has_succeeded_since(upstream, raw.dependencies_met_at)
+                    .await
+                {
+                    ready = false;
+                    break;
+                }
+            }
+            if !ready {
+                debug!("[{}] Waiting for other upstream jobs", raw.name);
+                continue;
+            }
+            // Persisted before the run starts, so the successes it used are not counted again
+            self.store
+                .set_dependencies_met_at(&raw.id, Utc::now())
+                .await;
+
+            match raw.to_job() {
+                Ok(job) => {
+                    info!(
+                        "[{}] Upstream jobs succeeded for {}, starting",
+                        job.name, window
+                    );
+                    self.spawn_run(
+                        job,
+                        window,
+                        RunTrigger::Dependency,
+                        &fire.job_id,
+                        fire.run_id.clone(),
+                    );
+                }
+                Err(e) => warn!("[{}] Cannot start downstream job: {}", raw.name, e),
+            }
         }
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.runner.is_draining()
+    }
+
+    /// Stops dispatching fires, then gives runs in progress up to `grace` to finish before
+    /// they are interrupted. [`run`](Self::run) returns once the dispatcher has stopped.
+    pub async fn shutdown(&self, grace: std::time::Duration) {
+        info!("Shutting down job engine");
+        self.scheduler.stop();
+        self.runner.shutdown(grace).await;
+        info!("Job engine stopped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::model::JobRun;
+    use crate::shard::LocalShardManager;
+    use crate::utils::testing::{self, Call, Scripted, Step, TempStore};
+    use std::time::{Duration, Instant};
 
-        loop {
-            sleep(Duration::from_secs(3600)).await;
+    fn engine(store: &TempStore, handler: Scripted) -> JobEngine {
+        let config = Arc::new(testing::config());
+        let mut registry = TaskRegistry::new();
+        registry.register(handler);
+        JobEngine::new(
+            config.clone(),
+            (*store).clone(),
+            Arc::new(LocalShardManager::new(config)),
+            Arc::new(registry),
+        )
+    }
+
+    /// Run of `job` left `running` by a process that died a minute ago.
+    async fn orphaned_run(store: &TempStore, external_ref: Option<&str>) -> JobRun {
+        let mut run = JobRun::start("job", Utc::now(), "node", 1);
+        run.started_at = Some(Utc::now() - chrono::Duration::minutes(1));
+        run.external_ref = external_ref.map(str::to_string);
+        store.insert_run(&run).await;
+        run
+    }
+
+    /// Recovers the runs of the `job` job and waits for `count` of them to be closed.
+    async fn recover(engine: &JobEngine, count: usize) {
+        let mut finished = engine.finished.lock().unwrap().take().unwrap();
+        engine.recover_runs(&[testing::job("job")]).await;
+        for _ in 0..count {
+            tokio::time::timeout(Duration::from_secs(10), finished.recv())
+                .await
+                .unwrap();
         }
     }
+
+    #[tokio::test]
+    async fn recovery_resumes_runs_the_handler_can_reattach_to() {
+        let store = TempStore::new().await;
+        store.insert_job(&testing::job_raw("job")).await.unwrap();
+        let handler = Scripted::default().resumable();
+        let engine = engine(&store, handler.clone());
+        let started = orphaned_run(&store, Some("execution-1")).await;
+        let unrecorded = orphaned_run(&store, None).await;
+
+        recover(&engine, 2).await;
+
+        assert_eq!(
+            handler.calls(),
+            [Call::Resume(Some("execution-1".to_string()))]
+        );
+        let resumed = store.run(&started.id).await;
+        assert_eq!(resumed.status, JobStatus::Success);
+        assert_eq!(resumed.attempt, 1);
+        let lost = store.run(&unrecorded.id).await;
+        assert_eq!(lost.status, JobStatus::Lost);
+        assert_eq!(
+            lost.message.as_deref(),
+            Some("Process exited before the remote run was recorded")
+        );
+    }
+
+    #[tokio::test]
+    async fn recovery_marks_runs_lost_when_the_handler_cannot_resume() {
+        let store = TempStore::new().await;
+        store.insert_job(&testing::job_raw("job")).await.unwrap();
+        let handler = Scripted::default();
+        let engine = engine(&store, handler.clone());
+        let orphaned = orphaned_run(&store, Some("execution-1")).await;
+        // Started by this process, so not orphaned
+        let current = JobRun::start("job", Utc::now(), "node", 1);
+        store.insert_run(&current).await;
+
+        recover(&engine, 1).await;
+
+        assert!(handler.calls().is_empty());
+        let lost = store.run(&orphaned.id).await;
+        assert_eq!(lost.status, JobStatus::Lost);
+        assert_eq!(
+            lost.message.as_deref(),
+            Some("Process exited while the run was in progress")
+        );
+        assert_eq!(store.run(&current.id).await.status, JobStatus::Running);
+        let job = store.get_job_by_id("job").await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Lost);
+    }
+
+    #[tokio::test]
+    async fn shutdown_waits_for_runs_that_end_within_the_grace_period() {
+        let store = TempStore::new().await;
+        let handler = Scripted::new(vec![Step::Sleep(Duration::from_millis(500))]);
+        let engine = engine(&store, handler);
+        engine.trigger_now(testing::job("job"), "user");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let started = Instant::now();
+        engine.shutdown(Duration::from_secs(5)).await;
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        let runs = store.runs("job").await;
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].status, JobStatus::Success);
+
+        // Nothing new starts once draining
+        engine.trigger_now(testing::job("job"), "user");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let runs = store.runs("job").await;
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[1].status, JobStatus::Interrupted);
+        assert_eq!(runs[1].started_at, None);
+    }
+
+    #[tokio::test]
+    async fn shutdown_interrupts_runs_past_the_grace_period() {
+        let store = TempStore::new().await;
+        let handler = Scripted::new(vec![Step::Sleep(Duration::from_secs(30))]);
+        let engine = engine(&store, handler.clone());
+        engine.trigger_now(testing::job("job"), "user");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let started = Instant::now();
+        engine.shutdown(Duration::from_millis(300)).await;
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(handler.calls(), [Call::Handle, Call::Cancel]);
+        let runs = store.runs("job").await;
+        assert_eq!(runs[0].status, JobStatus::Interrupted);
+        assert_eq!(
+            runs[0].message.as_deref(),
+            Some("Interrupted by engine shutdown")
+        );
+    }
 }
@@ -1,2 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod engine;
+pub mod pool;
 pub mod runner;
@@ -0,0 +1,370 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+use crate::domain::model::Job;
+
+/// Fires waiting for a worker, in dispatch order: highest priority first, then first come.
+type WaitKey = (Reverse<i32>, u64);
+
+struct Waiter {
+    job_id: String,
+    job_name: String,
+    task_type: String,
+    priority: i32,
+    queued_since: DateTime<Utc>,
+    admit: oneshot::Sender<()>,
+}
+
+#[derive(Default)]
+struct PoolState {
+    running: usize,
+    running_by_type: HashMap<String, usize>,
+    waiting: BTreeMap<WaitKey, Waiter>,
+    next_seq: u64,
+}
+
+/// Bounds how many task handlers run at once, globally and per task type.
+pub struct WorkerPool {
+    max_in_flight: usize,
+    type_limits: HashMap<String, usize>,
+    state: Mutex<PoolState>,
+}
+
+/// A claimed worker; the slot is released when dropped.
+pub struct WorkerPermit {
+    pool: Arc<WorkerPool>,
+    task_type: String,
+}
+
+impl Drop for WorkerPermit {
+    fn drop(&mut self) {
+        self.pool.release(&self.task_type);
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct TaskTypeStats {
+    pub running: usize,
+    pub queued: usize,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueuedFire {
+    pub job_id: String,
+    pub job_name: String,
+    pub task_type: String,
+    pub priority: i32,
+    pub queued_since: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PoolStats {
+    pub max_in_flight: usize,
+    pub running: usize,
+    pub queued: usize,
+    pub by_task_type: HashMap<String, TaskTypeStats>,
+    /// Waiting fires in the order they will be admitted
+    pub waiting: Vec<QueuedFire>,
+}
+
+impl PoolState {
+    fn can_admit(
+        &self,
+        task_type: &str,
+        max_in_flight: usize,
+        limits: &HashMap<String, usize>,
+    ) -> bool {
+        self.running < max_in_flight
+            && limits.get(task_type).is_none_or(|limit| {
+                self.running_by_type.get(task_type).copied().unwrap_or(0) < *limit
+            })
+    }
+
+    fn admit(&mut self, task_type: &str) {
+        self.running += 1;
+        *self
+            .running_by_type
+            .entry(task_type.to_string())
+            .or_default() += 1;
+    }
+
+    fn finish(&mut self, task_type: &str) {
+        self.running -= 1;
+        if let Some(running) = self.running_by_type.get_mut(task_type) {
+            *running -= 1;
+        }
+    }
+
+    fn next_admissible(
+        &self,
+        max_in_flight: usize,
+        limits: &HashMap<String, usize>,
+    ) -> Option<WaitKey> {
+        if self.running >= max_in_flight {
+            return None;
+        }
+        self.waiting
+            .iter()
+            .find(|(_, waiter)| self.can_admit(&waiter.task_type, max_in_flight, limits))
+            .map(|(key, _)| *key)
+    }
+}
+
+impl WorkerPool {
+    pub fn new(max_in_flight: usize, type_limits: HashMap<String, usize>) -> Self {
+        Self {
+            max_in_flight: max_in_flight.max(1),
+            type_limits,
+            state: Mutex::new(PoolState::default()),
+        }
+    }
+
+    /// Waits for a free worker for the job's task type. Returns `None` if `stopped` completes
+    /// first.
+    /// `on_queued` is awaited with the number of fires ahead when the job has to wait.
+    pub async fn acquire<F, Fut>(
+        self: &Arc<Self>,
+        job: &Job,
+        stopped: impl Future<Output = ()>,
+        on_queued: F,
+    ) -> Option<WorkerPermit>
+    where
+        F: FnOnce(usize) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        let task_type = job.task_type.task_type_name().to_string();
+        let (key, ahead, admitted) = {
+            let mut state = self.state.lock().unwrap();
+            if state.can_admit(&task_type, self.max_in_flight, &self.type_limits) {
+                state.admit(&task_type);
+                return Some(self.permit(task_type));
+            }
+
+            let (admit, admitted) = oneshot::channel();
+            let key = (Reverse(job.priority), state.next_seq);
+            state.next_seq += 1;
+            let ahead = state.waiting.range(..key).count();
+            state.waiting.insert(
+                key,
+                Waiter {
+                    job_id: job.id.clone(),
+                    job_name: job.name.clone(),
+                    task_type: task_type.clone(),
+                    priority: job.priority,
+                    queued_since: Utc::now(),
+                    admit,
+                },
+            );
+            (key, ahead, admitted)
+        };
+        on_queued(ahead).await;
+
+        tokio::select! {
+            _ = admitted => Some(self.permit(task_type)),
+            _ = stopped => {
+                let mut state = self.state.lock().unwrap();
+                if state.waiting.remove(&key).is_some() {
+                    return None;
+                }
+                // Admitted just before the cancellation was seen: hand the slot back
+                drop(state);
+                drop(self.permit(task_type));
+                None
+            }
+        }
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        let state = self.state.lock().unwrap();
+        let mut by_task_type: HashMap<String, TaskTypeStats> = self
+            .type_limits
+            .iter()
+            .map(|(task_type, limit)| {
+                let stats = TaskTypeStats {
+                    limit: Some(*limit),
+                    ..Default::default()
+                };
+                (task_type.clone(), stats)
+            })
+            .collect();
+        for (task_type, running) in &state.running_by_type {
+            by_task_type.entry(task_type.clone()).or_default().running = *running;
+        }
+        for waiter in state.waiting.values() {
+            by_task_type
+                .entry(waiter.task_type.clone())
+                .or_default()
+                .queued += 1;
+        }
+
+        PoolStats {
+            max_in_flight: self.max_in_flight,
+            running: state.running,
+            queued: state.waiting.len(),
+            by_task_type,
+            waiting: state
+                .waiting
+                .values()
+                .map(|waiter| QueuedFire {
+                    job_id: waiter.job_id.clone(),
+                    job_name: waiter.job_name.clone(),
+                    task_type: waiter.task_type.clone(),
+                    priority: waiter.priority,
+                    queued_since: waiter.queued_since.to_rfc3339(),
+                })
+                .collect(),
+        }
+    }
+
+    fn permit(self: &Arc<Self>, task_type: String) -> WorkerPermit {
+        WorkerPermit {
+            pool: self.clone(),
+            task_type,
+        }
+    }
+
+    /// Frees a slot and admits waiting fires in priority order. Fires whose task type is at
+    /// its limit are passed over so they don't hold up other types.
+    fn release(&self, task_type: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.finish(task_type);
+        while let Some(key) = state.next_admissible(self.max_in_flight, &self.type_limits) {
+            let Some(waiter) = state.waiting.remove(&key) else {
+                break;
+            };
+            state.admit(&waiter.task_type);
+            if waiter.admit.send(()).is_err() {
+                // The waiter went away without withdrawing
+                state.finish(&waiter.task_type);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing;
+    use std::future::pending;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    use tokio::time::{sleep, timeout};
+
+    fn job(id: &str, task_type: &str, priority: i32) -> Job {
+        let mut raw = testing::job_raw(id);
+        if task_type == "shell_command" {
+            raw.task_type = task_type.to_string();
+            raw.payload = r#"{"command":"true"}"#.to_string();
+        }
+        raw.priority = priority;
+        raw.to_job().unwrap()
+    }
+
+    /// Queues `job` and reports its id once admitted, then releases the worker right away.
+    fn wait_for_worker(
+        pool: &Arc<WorkerPool>,
+        job: Job,
+        admitted: &mpsc::UnboundedSender<String>,
+    ) -> tokio::task::JoinHandle<()> {
+        let pool = pool.clone();
+        let admitted = admitted.clone();
+        tokio::spawn(async move {
+            let permit = pool.acquire(&job, pending(), |_| async {}).await;
+            assert!(permit.is_some());
+            admitted.send(job.id).unwrap();
+        })
+    }
+
+    /// Worker for `job`, which must be free.
+    async fn claim(pool: &Arc<WorkerPool>, job: Job) -> WorkerPermit {
+        pool.acquire(&job, pending(), |_| async {}).await.unwrap()
+    }
+
+    async fn queued(pool: &WorkerPool, count: usize) {
+        timeout(Duration::from_secs(5), async {
+            while pool.stats().queued < count {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn higher_priority_waiter_is_admitted_first() {
+        let pool = Arc::new(WorkerPool::new(1, HashMap::new()));
+        let running = claim(&pool, job("running", "print", 0)).await;
+
+        let (admitted, mut order) = mpsc::unbounded_channel();
+        wait_for_worker(&pool, job("low", "print", 0), &admitted);
+        queued(&pool, 1).await;
+        wait_for_worker(&pool, job("high", "print", 10), &admitted);
+        queued(&pool, 2).await;
+        wait_for_worker(&pool, job("later-low", "print", 0), &admitted);
+        queued(&pool, 3).await;
+
+        let waiting: Vec<_> = pool.stats().waiting.into_iter().map(|w| w.job_id).collect();
+        assert_eq!(waiting, ["high", "low", "later-low"]);
+        drop(running);
+        for expected in ["high", "low", "later-low"] {
+            assert_eq!(order.recv().await.unwrap(), expected);
+        }
+        assert_eq!(pool.stats().running, 0);
+    }
+
+    #[tokio::test]
+    async fn fires_ahead_are_reported_to_the_queued_one() {
+        let pool = Arc::new(WorkerPool::new(1, HashMap::new()));
+        let _running = claim(&pool, job("running", "print", 0)).await;
+        let (admitted, _order) = mpsc::unbounded_channel();
+        wait_for_worker(&pool, job("a", "print", 0), &admitted);
+        queued(&pool, 1).await;
+
+        let (ahead, mut reported) = mpsc::unbounded_channel();
+        let stopped = sleep(Duration::from_millis(100));
+        let on_queued = |count| async move { ahead.send(count).unwrap() };
+        let permit = pool
+            .acquire(&job("b", "print", 0), stopped, on_queued)
+            .await;
+        assert!(permit.is_none());
+        assert_eq!(reported.recv().await, Some(1));
+        // A fire that gave up waiting leaves the queue
+        assert_eq!(pool.stats().queued, 1);
+    }
+
+    #[tokio::test]
+    async fn task_type_limit_holds_while_the_pool_has_room() {
+        let limits = HashMap::from([("shell_command".to_string(), 1)]);
+        let pool = Arc::new(WorkerPool::new(3, limits));
+        let shell = claim(&pool, job("shell-1", "shell_command", 0)).await;
+
+        let second = job("shell-2", "shell_command", 0);
+        let stopped = sleep(Duration::from_millis(100));
+        assert!(pool.acquire(&second, stopped, |_| async {}).await.is_none());
+
+        let print = claim(&pool, job("print-1", "print", 0)).await;
+        let _other = claim(&pool, job("print-2", "print", 0)).await;
+        let stats = pool.stats();
+        assert_eq!(stats.running, 3);
+        assert_eq!(stats.by_task_type["shell_command"].running, 1);
+        assert_eq!(stats.by_task_type["shell_command"].limit, Some(1));
+
+        // A freed worker goes to the print fire queued behind the shell one at its limit
+        let (admitted, mut order) = mpsc::unbounded_channel();
+        wait_for_worker(&pool, job("shell-3", "shell_command", 5), &admitted);
+        queued(&pool, 1).await;
+        wait_for_worker(&pool, job("print-3", "print", 0), &admitted);
+        queued(&pool, 2).await;
+        drop(print);
+        assert_eq!(order.recv().await.unwrap(), "print-3");
+        assert_eq!(pool.stats().queued, 1);
+
+        drop(shell);
+        assert_eq!(order.recv().await.unwrap(), "shell-3");
+    }
+}
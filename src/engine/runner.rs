@@ -1,7 +1,700 @@
-// สำหรับ task runner หรือ async supervisor logic เพิ่มเติม (ยังไม่จำเป็นใน version นี้)
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::time::{Duration, sleep, timeout};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
 
-use log::debug;
+use crate::config::AppConfig;
+use crate::domain::model::{Job, JobRun, JobStatus, RunTrigger};
+use crate::domain::policy::ErrorClass;
+use crate::domain::task_payload::TaskPayload;
+use crate::engine::pool::{PoolStats, WorkerPool};
+use crate::job::store::JobStore;
+use crate::task::handler::{TaskContext, TaskError, TaskHandler};
+use crate::task::registry::TaskRegistry;
+use crate::utils::redact_signatures;
 
-pub fn placeholder_runner() {
-    debug!("This is a placeholder runner module.");
+/// Upper bound for a handler's cleanup after its run was aborted.
+const CANCEL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Outcome of a fire once it stopped retrying, reported to the engine.
+#[derive(Debug, Clone)]
+pub struct FireFinished {
+    pub job_id: String,
+    pub scheduled_at: DateTime<Utc>,
+    pub status: JobStatus,
+    /// Last recorded attempt, if one started
+    pub run_id: Option<String>,
+}
+
+/// Executes a single fire of a job and records it in the run history.
+pub struct JobRunner {
+    config: Arc<AppConfig>,
+    store: Arc<dyn JobStore>,
+    task_registry: Arc<TaskRegistry>,
+    pool: Arc<WorkerPool>,
+    /// Cancelled when shutdown begins; no new attempt starts after that
+    draining: CancellationToken,
+    /// Cancelled when the shutdown grace period runs out; aborts every run still in progress
+    interrupt: CancellationToken,
+    /// Runs in progress, waited for on shutdown
+    active: TaskTracker,
+    finished: mpsc::UnboundedSender<FireFinished>,
+}
+
+impl JobRunner {
+    pub fn new(
+        config: Arc<AppConfig>,
+        store: Arc<dyn JobStore>,
+        task_registry: Arc<TaskRegistry>,
+        finished: mpsc::UnboundedSender<FireFinished>,
+    ) -> Self {
+        let pool = Arc::new(WorkerPool::new(
+            config.max_in_flight,
+            config.task_concurrency.clone(),
+        ));
+        Self {
+            config,
+            store,
+            task_registry,
+            pool,
+            draining: CancellationToken::new(),
+            interrupt: CancellationToken::new(),
+            active: TaskTracker::new(),
+            finished,
+        }
+    }
+
+    /// Token for a new run. Cancelling it aborts the run; shutdown cancels all of them.
+    pub fn run_token(&self) -> CancellationToken {
+        self.interrupt.child_token()
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.is_cancelled()
+    }
+
+    /// Stops starting new attempts and waits up to `grace` for runs in progress. Runs still
+    /// going after that are cancelled and recorded as interrupted.
+    pub async fn shutdown(&self, grace: Duration) {
+        self.draining.cancel();
+        self.active.close();
+        if self.active.is_empty() {
+            return;
+        }
+
+        info!(
+            "Waiting up to {}s for {} run(s) in progress",
+            grace.as_secs(),
+            self.active.len()
+        );
+        if timeout(grace, self.active.wait()).await.is_err() {
+            warn!(
+                "Interrupting {} run(s) still in progress after {}s",
+                self.active.len(),
+                grace.as_secs()
+            );
+            self.interrupt.cancel();
+            self.active.wait().await;
+        }
+    }
+
+    pub fn worker_stats(&self) -> PoolStats {
+        self.pool.stats()
+    }
+
+    /// Records a fire that was not executed.
+    pub async fn record_skipped(&self, job: &Job, scheduled_at: DateTime<Utc>, reason: &str) {
+        let _active = self.active.token();
+        info!(
+            "[{}] Fire at {} skipped: {}",
+            job.name, scheduled_at, reason
+        );
+        let run = JobRun::skipped(&job.id, scheduled_at, &self.config.node_id, reason);
+        self.store.insert_run(&run).await;
+        self.complete_if_last(job, scheduled_at, &JobStatus::Skipped)
+            .await;
+    }
+
+    /// Marks a one-shot job completed once its only fire is over. A fire interrupted by
+    /// shutdown does not count.
+    async fn complete_if_last(&self, job: &Job, scheduled_at: DateTime<Utc>, status: &JobStatus) {
+        if *status == JobStatus::Interrupted || !job.schedule.is_last_fire(scheduled_at) {
+            return;
+        }
+        info!("[{}] One-shot schedule completed", job.name);
+        let message = format!("Ran once at {}: {}", scheduled_at.to_rfc3339(), status);
+        self.store
+            .update_status(&job.id, JobStatus::Completed, &message)
+            .await;
+    }
+
+    /// Runs a scheduled fire of the job.
+    pub async fn execute(&self, job: &Job, scheduled_at: DateTime<Utc>, cancel: CancellationToken) {
+        self.execute_with(job, scheduled_at, RunTrigger::Schedule, None, None, cancel)
+            .await
+    }
+
+    /// Runs the job, applying its retry policy until it succeeds, gives up or is cancelled.
+    /// Every attempt is recorded with the given trigger, requesting user or job, and the run
+    /// that caused it.
+    pub async fn execute_with(
+        &self,
+        job: &Job,
+        scheduled_at: DateTime<Utc>,
+        trigger: RunTrigger,
+        triggered_by: Option<String>,
+        parent_run_id: Option<String>,
+        cancel: CancellationToken,
+    ) {
+        let _active = self.active.token();
+        let new_run = |attempt| {
+            let mut run = JobRun::start(&job.id, scheduled_at, &self.config.node_id, attempt);
+            run.trigger = trigger.clone();
+            run.triggered_by = triggered_by.clone();
+            run.parent_run_id = parent_run_id.clone();
+            run
+        };
+
+        let task_type = job.task_type.clone();
+        let Some(handler) = self.task_registry.get(task_type.task_type_name()) else {
+            let message = format!("No handler for task type '{}'", task_type);
+            error!("[{}] {}", job.name, message);
+            let mut run = new_run(1);
+            run.finish(JobStatus::Failed, &message);
+            self.store.insert_run(&run).await;
+            self.store.update_last_run(&job.id, Utc::now()).await;
+            self.report(job, scheduled_at, JobStatus::Failed, Some(run.id));
+            return;
+        };
+
+        let policy = job.retry_policy.clone().unwrap_or_default();
+        let mut attempt = 1;
+        let mut last_run_id;
+        // Remote run the previous attempt started but lost track of, to be followed again
+        let mut resume_ref: Option<String> = None;
+        let status = loop {
+            // Held for this attempt only, so the retry backoff doesn't occupy a worker
+            let on_queued = |ahead: usize| async move {
+                let message = format!("Waiting for a worker ({} ahead)", ahead);
+                info!("[{}] {}", job.name, message);
+                self.store
+                    .update_status(&job.id, JobStatus::Queued, &message)
+                    .await;
+            };
+            let stopped = async {
+                tokio::select! {
+                    _ = cancel.cancelled() => {}
+                    _ = self.draining.cancelled() => {}
+                }
+            };
+            let permit = match self.is_draining() {
+                true => None,
+                false => self.pool.acquire(job, stopped, on_queued).await,
+            };
+            let Some(_permit) = permit else {
+                let (status, reason) = self.not_started();
+                // Not a skip: the fire is still due, so a one-shot job must not count as done
+                info!(
+                    "[{}] Fire at {} not started: {}",
+                    job.name, scheduled_at, reason
+                );
+                let mut run = new_run(attempt);
+                run.started_at = None;
+                run.finish(status.clone(), reason);
+                self.store.insert_run(&run).await;
+                self.store
+                    .update_status(&job.id, status.clone(), reason)
+                    .await;
+                self.report(job, scheduled_at, status, Some(run.id));
+                return;
+            };
+
+            let mut run = new_run(attempt);
+            last_run_id = Some(run.id.clone());
+            self.store.insert_run(&run).await;
+            self.store
+                .update_status(&job.id, JobStatus::Start, "Starting")
+                .await;
+            info!(
+                "[{}] Executing task (run {}, attempt {}/{})",
+                job.name, run.id, attempt, policy.max_attempts
+            );
+            self.store
+                .update_status(&job.id, JobStatus::Running, "Running")
+                .await;
+
+            let ctx = TaskContext::new(&job.id, &run.id, scheduled_at, None, self.store.clone());
+            let resuming = resume_ref.is_some();
+            if let Some(external_ref) = resume_ref.take() {
+                info!(
+                    "[{}] Resuming {} instead of starting it again",
+                    job.name,
+                    redact_signatures(&external_ref)
+                );
+                ctx.set_external_ref(external_ref).await;
+            }
+            match self
+                .invoke(job, &handler, &task_type, &ctx, &cancel, resuming)
+                .await
+            {
+                Ok(()) => {
+                    run.finish(JobStatus::Success, "Successful");
+                    self.store.update_run(&run).await;
+                    self.store
+                        .update_status(&job.id, JobStatus::Success, "Successful")
+                        .await;
+                    break JobStatus::Success;
+                }
+                Err(e) => {
+                    error!("[{}] Task error ({}): {}", job.name, e.class, e);
+                    let status = self.failure_status(&e);
+                    run.finish(status.clone(), &e.message);
+                    self.store.update_run(&run).await;
+
+                    if !policy.should_retry(attempt, e.class) {
+                        self.store
+                            .update_status(&job.id, status.clone(), &e.message)
+                            .await;
+                        break status;
+                    }
+                    // A transient error once the remote run started means it could not be
+                    // followed, not that it failed; starting it again would run it twice
+                    if e.class == ErrorClass::Transient && handler.can_resume() {
+                        resume_ref = ctx.external_ref();
+                    }
+
+                    let delay = policy.delay_after(attempt);
+                    let message = format!(
+                        "Attempt {}/{} failed: {}; retrying in {}s",
+                        attempt,
+                        policy.max_attempts,
+                        e,
+                        delay.as_secs()
+                    );
+                    warn!("[{}] {}", job.name, message);
+                    self.store
+                        .update_status(&job.id, JobStatus::Retrying, &message)
+                        .await;
+                    tokio::select! {
+                        _ = sleep(delay) => {}
+                        _ = cancel.cancelled() => {
+                            self.store
+                                .update_status(&job.id, JobStatus::Cancelled, "Cancelled while waiting to retry")
+                                .await;
+                            break JobStatus::Cancelled;
+                        }
+                        _ = self.draining.cancelled() => {
+                            self.store
+                                .update_status(&job.id, JobStatus::Interrupted, "Engine shut down while waiting to retry")
+                                .await;
+                            break JobStatus::Interrupted;
+                        }
+                    }
+                    attempt += 1;
+                }
+            }
+        };
+
+        self.store.update_last_run(&job.id, Utc::now()).await;
+        self.complete_if_last(job, scheduled_at, &status).await;
+        self.report(job, scheduled_at, status, last_run_id);
+    }
+
+    /// Runs `task` once on behalf of the job, for an inline follow-up action. The run is
+    /// recorded with the job but neither changes its status nor triggers further follow-ups.
+    pub async fn run_task(
+        &self,
+        job: &Job,
+        task: &TaskPayload,
+        parent_run_id: &str,
+        cancel: CancellationToken,
+    ) {
+        let _active = self.active.token();
+        let mut run = JobRun::start(&job.id, Utc::now(), &self.config.node_id, 1);
+        run.trigger = RunTrigger::FollowUp;
+        run.triggered_by = Some(job.id.clone());
+        run.parent_run_id = Some(parent_run_id.to_string());
+
+        let Some(handler) = self.task_registry.get(task.task_type_name()) else {
+            let message = format!("No handler for task type '{}'", task);
+            error!("[{}] {}", job.name, message);
+            run.finish(JobStatus::Failed, &message);
+            self.store.insert_run(&run).await;
+            return;
+        };
+        let stopped = async {
+            tokio::select! {
+                _ = cancel.cancelled() => {}
+                _ = self.draining.cancelled() => {}
+            }
+        };
+        let on_queued = |_| async {};
+        let Some(_permit) = self.pool.acquire(job, stopped, on_queued).await else {
+            let (status, reason) = self.not_started();
+            info!(
+                "[{}] Follow-up {} task not started: {}",
+                job.name, task, reason
+            );
+            run.started_at = None;
+            run.finish(status, reason);
+            self.store.insert_run(&run).await;
+            return;
+        };
+
+        info!(
+            "[{}] Running follow-up {} task (run {})",
+            job.name, task, run.id
+        );
+        self.store.insert_run(&run).await;
+        let ctx = TaskContext::new(&job.id, &run.id, run.scheduled_at, None, self.store.clone());
+        match self.invoke(job, &handler, task, &ctx, &cancel, false).await {
+            Ok(()) => run.finish(JobStatus::Success, "Successful"),
+            Err(e) => {
+                error!("[{}] Follow-up task error ({}): {}", job.name, e.class, e);
+                run.finish(self.failure_status(&e), &e.message);
+            }
+        }
+        self.store.update_run(&run).await;
+    }
+
+    /// Status and reason of a run stopped while it waited for a worker.
+    fn not_started(&self) -> (JobStatus, &'static str) {
+        match self.is_draining() {
+            true => (
+                JobStatus::Interrupted,
+                "Engine shut down before the run started",
+            ),
+            false => (JobStatus::Cancelled, "Cancelled while waiting for a worker"),
+        }
+    }
+
+    fn report(
+        &self,
+        job: &Job,
+        scheduled_at: DateTime<Utc>,
+        status: JobStatus,
+        run_id: Option<String>,
+    ) {
+        let finished = FireFinished {
+            job_id: job.id.clone(),
+            scheduled_at,
+            status,
+            run_id,
+        };
+        // Only fails once the engine stopped listening
+        let _ = self.finished.send(finished);
+    }
+
+    /// Reattaches to a run left `running` by a previous process and records its outcome.
+    /// The retry policy is not applied; the next regular fire starts afresh.
+    pub async fn resume(&self, job: &Job, mut run: JobRun, cancel: CancellationToken) {
+        let _active = self.active.token();
+        let Some(handler) = self.task_registry.get(job.task_type.task_type_name()) else {
+            self.record_lost(job, run, "No handler to reattach to the run")
+                .await;
+            return;
+        };
+
+        let stopped = async {
+            tokio::select! {
+                _ = cancel.cancelled() => {}
+                _ = self.draining.cancelled() => {}
+            }
+        };
+        let on_queued = |ahead: usize| async move {
+            info!(
+                "[{}] Waiting for a worker to reattach ({} ahead)",
+                job.name, ahead
+            );
+        };
+        // Left `running` if shutdown comes first, so the next start tries again
+        let Some(_permit) = self.pool.acquire(job, stopped, on_queued).await else {
+            return;
+        };
+
+        let message = format!(
+            "Reattached to {}",
+            redact_signatures(run.external_ref.as_deref().unwrap_or_default())
+        );
+        info!("[{}] {} (run {})", job.name, message, run.id);
+        self.store
+            .update_status(&job.id, JobStatus::Running, &message)
+            .await;
+
+        let ctx = TaskContext::new(
+            &job.id,
+            &run.id,
+            run.scheduled_at,
+            run.external_ref.clone(),
+            self.store.clone(),
+        );
+        let (status, message) = match self
+            .invoke(job, &handler, &job.task_type, &ctx, &cancel, true)
+            .await
+        {
+            Ok(()) => (JobStatus::Success, "Successful".to_string()),
+            Err(e) => {
+                error!("[{}] Task error ({}): {}", job.name, e.class, e);
+                (self.failure_status(&e), e.message)
+            }
+        };
+        run.finish(status.clone(), &message);
+        self.store.update_run(&run).await;
+        self.store
+            .update_status(&job.id, status.clone(), &message)
+            .await;
+        self.store.update_last_run(&job.id, Utc::now()).await;
+        self.report(job, run.scheduled_at, status, Some(run.id));
+    }
+
+    /// Closes a run left `running` by a previous process that cannot be reattached.
+    pub async fn record_lost(&self, job: &Job, mut run: JobRun, reason: &str) {
+        warn!("[{}] Run {} lost: {}", job.name, run.id, reason);
+        run.finish(JobStatus::Lost, reason);
+        self.store.update_run(&run).await;
+        self.store
+            .update_status(&job.id, JobStatus::Lost, reason)
+            .await;
+        self.report(job, run.scheduled_at, JobStatus::Lost, Some(run.id));
+    }
+
+    fn failure_status(&self, error: &TaskError) -> JobStatus {
+        match error.class {
+            ErrorClass::Timeout => JobStatus::TimedOut,
+            ErrorClass::Cancelled if self.interrupt.is_cancelled() => JobStatus::Interrupted,
+            ErrorClass::Cancelled => JobStatus::Cancelled,
+            _ => JobStatus::Failed,
+        }
+    }
+
+    fn timeout_for(&self, job: &Job) -> Option<Duration> {
+        job.timeout_secs
+            .or_else(|| {
+                self.config
+                    .task_timeouts
+                    .get(job.task_type.task_type_name())
+                    .copied()
+            })
+            .map(Duration::from_secs)
+    }
+
+    /// Runs the handler once, or resumes an earlier run with `resume`. The handler future is
+    /// dropped and asked to clean up when the timeout elapses or the run is cancelled.
+    async fn invoke(
+        &self,
+        job: &Job,
+        handler: &Arc<dyn TaskHandler>,
+        payload: &TaskPayload,
+        ctx: &TaskContext,
+        cancel: &CancellationToken,
+        resume: bool,
+    ) -> Result<(), TaskError> {
+        let limit = self.timeout_for(job);
+        let deadline = async {
+            match limit {
+                Some(limit) => sleep(limit).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        let work = async {
+            match resume {
+                true => handler.resume(payload, ctx).await,
+                false => handler.handle(payload, ctx).await,
+            }
+        };
+
+        let error = tokio::select! {
+            result = work => return result,
+            _ = deadline => {
+                let secs = limit.unwrap_or_default().as_secs();
+                warn!("[{}] Timed out after {}s, cancelling", job.name, secs);
+                TaskError::timeout(format!("Timed out after {}s", secs))
+            }
+            _ = cancel.cancelled() => {
+                if self.interrupt.is_cancelled() {
+                    warn!("[{}] Run interrupted by shutdown", job.name);
+                    TaskError::cancelled("Interrupted by engine shutdown")
+                } else {
+                    warn!("[{}] Run cancelled", job.name);
+                    TaskError::cancelled("Cancelled by the engine")
+                }
+            }
+        };
+
+        match timeout(CANCEL_TIMEOUT, handler.cancel(payload, ctx)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("[{}] Cleanup after abort failed: {}", job.name, e),
+            Err(_) => warn!("[{}] Cleanup after abort did not finish", job.name),
+        }
+        Err(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::policy::RetryPolicy;
+    use crate::utils::testing::{self, Call, Scripted, Step, TempStore};
+
+    /// Up to `max_attempts` attempts on transient errors, retried without waiting.
+    fn retrying(max_attempts: u32) -> Job {
+        let mut job = testing::job("job");
+        job.retry_policy = Some(RetryPolicy {
+            max_attempts,
+            initial_delay_secs: 0,
+            backoff_multiplier: 1.0,
+            max_delay_secs: 0,
+            retry_on: vec![ErrorClass::Transient],
+        });
+        job
+    }
+
+    fn statuses(runs: &[JobRun]) -> Vec<(u32, JobStatus)> {
+        runs.iter()
+            .map(|run| (run.attempt, run.status.clone()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn transient_failures_are_retried_until_success() {
+        let store = TempStore::new().await;
+        let job = retrying(4);
+        store.insert_job(&testing::job_raw("job")).await.unwrap();
+        let handler = Scripted::new(vec![
+            Step::Fail(TaskError::transient("503")),
+            Step::Fail(TaskError::transient("503")),
+        ]);
+        let (runner, mut finished) = store.runner(testing::config(), handler.clone());
+
+        runner
+            .execute(&job, Utc::now(), CancellationToken::new())
+            .await;
+        assert_eq!(
+            statuses(&store.runs("job").await),
+            [
+                (1, JobStatus::Failed),
+                (2, JobStatus::Failed),
+                (3, JobStatus::Success)
+            ]
+        );
+        assert_eq!(handler.calls(), [Call::Handle, Call::Handle, Call::Handle]);
+        assert_eq!(finished.recv().await.unwrap().status, JobStatus::Success);
+        let job = store.get_job_by_id("job").await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Success);
+    }
+
+    #[tokio::test]
+    async fn retries_stop_at_max_attempts_or_a_failure() {
+        let store = TempStore::new().await;
+        let handler = Scripted::new(vec![
+            Step::Fail(TaskError::transient("503")),
+            Step::Fail(TaskError::transient("503")),
+            Step::Fail(TaskError::transient("503")),
+        ]);
+        let (runner, mut finished) = store.runner(testing::config(), handler);
+        runner
+            .execute(&retrying(2), Utc::now(), CancellationToken::new())
+            .await;
+        assert_eq!(
+            statuses(&store.runs("job").await),
+            [(1, JobStatus::Failed), (2, JobStatus::Failed)]
+        );
+        assert_eq!(finished.recv().await.unwrap().status, JobStatus::Failed);
+
+        let store = TempStore::new().await;
+        let handler = Scripted::new(vec![Step::Fail(TaskError::failed("bad input"))]);
+        let (runner, _finished) = store.runner(testing::config(), handler);
+        runner
+            .execute(&retrying(3), Utc::now(), CancellationToken::new())
+            .await;
+        let runs = store.runs("job").await;
+        assert_eq!(statuses(&runs), [(1, JobStatus::Failed)]);
+        assert_eq!(runs[0].message.as_deref(), Some("bad input"));
+    }
+
+    #[tokio::test]
+    async fn retry_resumes_the_remote_run_the_failed_attempt_started() {
+        let store = TempStore::new().await;
+        let handler = Scripted::new(vec![Step::Start("execution-1")]).resumable();
+        let (runner, _finished) = store.runner(testing::config(), handler.clone());
+        runner
+            .execute(&retrying(3), Utc::now(), CancellationToken::new())
+            .await;
+
+        assert_eq!(
+            handler.calls(),
+            [Call::Handle, Call::Resume(Some("execution-1".to_string()))]
+        );
+        let runs = store.runs("job").await;
+        assert_eq!(
+            statuses(&runs),
+            [(1, JobStatus::Failed), (2, JobStatus::Success)]
+        );
+        assert_eq!(runs[1].external_ref.as_deref(), Some("execution-1"));
+
+        // A handler that cannot resume starts the task again
+        let store = TempStore::new().await;
+        let handler = Scripted::new(vec![Step::Start("execution-1")]);
+        let (runner, _finished) = store.runner(testing::config(), handler.clone());
+        runner
+            .execute(&retrying(3), Utc::now(), CancellationToken::new())
+            .await;
+        assert_eq!(handler.calls(), [Call::Handle, Call::Handle]);
+    }
+
+    #[tokio::test]
+    async fn run_past_its_timeout_is_cancelled_and_timed_out() {
+        let store = TempStore::new().await;
+        let handler = Scripted::new(vec![Step::Sleep(Duration::from_secs(30))]);
+        let (runner, mut finished) = store.runner(testing::config(), handler.clone());
+        let mut job = retrying(3);
+        job.timeout_secs = Some(1);
+
+        let started = std::time::Instant::now();
+        runner
+            .execute(&job, Utc::now(), CancellationToken::new())
+            .await;
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(handler.calls(), [Call::Handle, Call::Cancel]);
+        let runs = store.runs("job").await;
+        // Timeouts are not in the policy's retry_on
+        assert_eq!(statuses(&runs), [(1, JobStatus::TimedOut)]);
+        assert_eq!(runs[0].message.as_deref(), Some("Timed out after 1s"));
+        assert_eq!(finished.recv().await.unwrap().status, JobStatus::TimedOut);
+    }
+
+    #[tokio::test]
+    async fn task_type_timeout_applies_to_jobs_without_their_own() {
+        let mut config = testing::config();
+        config.task_timeouts.insert("print".to_string(), 1);
+
+        let store = TempStore::new().await;
+        let handler = Scripted::new(vec![Step::Sleep(Duration::from_secs(30))]);
+        let (runner, _finished) = store.runner(config.clone(), handler);
+        runner
+            .execute(&testing::job("job"), Utc::now(), CancellationToken::new())
+            .await;
+        assert_eq!(
+            statuses(&store.runs("job").await),
+            [(1, JobStatus::TimedOut)]
+        );
+
+        // The job's own timeout wins over the default
+        let store = TempStore::new().await;
+        let handler = Scripted::new(vec![Step::Sleep(Duration::from_millis(1500))]);
+        let (runner, _finished) = store.runner(config, handler);
+        let mut job = testing::job("job");
+        job.timeout_secs = Some(5);
+        runner
+            .execute(&job, Utc::now(), CancellationToken::new())
+            .await;
+        assert_eq!(
+            statuses(&store.runs("job").await),
+            [(1, JobStatus::Success)]
+        );
+    }
 }
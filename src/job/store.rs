@@ -1,8 +1,15 @@
-use crate::domain::model::{Job, JobRaw, JobStatus};
+use crate::domain::calendar::Calendar;
+use crate::domain::dependency::DependencyGraph;
+use crate::domain::model::{JobRaw, JobRun, JobSchedule, JobStatus, RunTrigger};
+use crate::domain::policy::OverlapPolicy;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use log::debug;
-use sqlx::{Pool, Row, Sqlite, sqlite::SqlitePoolOptions};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use sqlx::sqlite::{SqlitePoolOptions, SqliteRow};
+use sqlx::{Pool, Row, Sqlite};
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use std::str::FromStr;
@@ -13,6 +20,135 @@ pub trait JobStore: Send + Sync {
     async fn load_jobs(&self) -> Vec<JobRaw>;
     async fn update_last_run(&self, job_id: &str, dt: DateTime<Utc>);
     async fn update_status(&self, job_id: &str, status: JobStatus, message: &str);
+    async fn insert_run(&self, run: &JobRun);
+    async fn update_run(&self, run: &JobRun);
+    /// Scheduled time of the most recent recorded run of a job's schedule (manual runs excluded).
+    async fn last_scheduled_at(&self, job_id: &str) -> Option<DateTime<Utc>>;
+    /// Persists the remote execution id of a run so it can be reattached after a restart.
+    async fn set_run_external_ref(&self, run_id: &str, external_ref: &str);
+    /// Stores what the task reported about a run, e.g. captured command output.
+    async fn set_run_output(&self, run_id: &str, output: &serde_json::Value);
+    /// Runs still marked `running`, oldest first.
+    async fn unfinished_runs(&self) -> Vec<JobRun>;
+    /// The job with its upstream jobs and calendar, if it still exists.
+    async fn find_job(&self, job_id: &str) -> Option<JobRaw>;
+    /// Jobs that depend on `job_id`.
+    async fn dependents_of(&self, job_id: &str) -> Vec<JobRaw>;
+    /// Whether the job has a run that succeeded after `since`, or at all if `None`.
+    async fn has_succeeded_since(&self, job_id: &str, since: Option<DateTime<Utc>>) -> bool;
+    /// Records when the job was last started by its upstream jobs.
+    async fn set_dependencies_met_at(&self, job_id: &str, dt: DateTime<Utc>);
+}
+
+fn parse_datetime(value: Option<String>) -> Option<DateTime<Utc>> {
+    value.and_then(|s| {
+        DateTime::parse_from_rfc3339(&s)
+            .ok()
+            .map(|dt| dt.with_timezone(&Utc))
+    })
+}
+
+const JOB_COLUMNS: &str = "id, name, cron, task_type, payload, last_run, status, message, retry_policy, timeout_secs, overlap_policy, misfire_policy, timezone, priority, on_success, on_failure, calendar_id, schedule, jitter_secs, dependencies_met_at";
+
+const CALENDAR_COLUMNS: &str = "id, name, timezone, holidays, include_windows, exclude_windows";
+
+const RUN_COLUMNS: &str = "id, job_id, scheduled_at, started_at, finished_at, duration_ms, status, message, node_id, attempt, trigger_type, triggered_by, external_ref, parent_run_id, output";
+
+fn to_json<T: Serialize>(value: &Option<T>) -> Option<String> {
+    value.as_ref().and_then(|v| serde_json::to_string(v).ok())
+}
+
+fn from_json<T: DeserializeOwned>(value: Option<String>) -> Option<T> {
+    value.and_then(|s| serde_json::from_str(&s).ok())
+}
+
+fn row_to_job(r: &SqliteRow) -> Result<JobRaw, sqlx::Error> {
+    let status: Option<String> = r.try_get("status")?;
+    Ok(JobRaw {
+        id: r.try_get("id")?,
+        name: r.try_get("name")?,
+        // Jobs saved before `schedule` existed only have a cron expression
+        schedule: from_json(r.try_get("schedule")?).unwrap_or(JobSchedule::Cron {
+            expression: r.try_get("cron")?,
+        }),
+        task_type: r.try_get("task_type")?,
+        payload: r
+            .try_get::<Option<String>, _>("payload")?
+            .unwrap_or_default(),
+        last_run: parse_datetime(r.try_get("last_run")?),
+        status: status
+            .and_then(|s| JobStatus::from_str(&s).ok())
+            .unwrap_or(JobStatus::Start),
+        message: r.try_get("message")?,
+        retry_policy: from_json(r.try_get("retry_policy")?),
+        timeout_secs: r
+            .try_get::<Option<i64>, _>("timeout_secs")?
+            .map(|secs| secs as u64),
+        overlap_policy: r
+            .try_get::<Option<String>, _>("overlap_policy")?
+            .and_then(|s| OverlapPolicy::from_str(&s).ok())
+            .unwrap_or_default(),
+        misfire_policy: from_json(r.try_get("misfire_policy")?).unwrap_or_default(),
+        timezone: r.try_get("timezone")?,
+        priority: r.try_get::<Option<i64>, _>("priority")?.unwrap_or_default() as i32,
+        // Filled from `job_dependencies` by the caller
+        depends_on: Vec::new(),
+        on_success: from_json(r.try_get("on_success")?).unwrap_or_default(),
+        on_failure: from_json(r.try_get("on_failure")?).unwrap_or_default(),
+        calendar_id: r.try_get("calendar_id")?,
+        // Filled from `calendars` by the caller
+        calendar: None,
+        jitter_secs: r
+            .try_get::<Option<i64>, _>("jitter_secs")?
+            .map(|secs| secs as u64),
+        dependencies_met_at: parse_datetime(r.try_get("dependencies_met_at")?),
+    })
+}
+
+fn row_to_calendar(r: &SqliteRow) -> Result<Calendar, sqlx::Error> {
+    Ok(Calendar {
+        id: r.try_get("id")?,
+        name: r.try_get("name")?,
+        timezone: r.try_get("timezone")?,
+        holidays: from_json(r.try_get("holidays")?).unwrap_or_default(),
+        include: from_json(r.try_get("include_windows")?).unwrap_or_default(),
+        exclude: from_json(r.try_get("exclude_windows")?).unwrap_or_default(),
+    })
+}
+
+fn row_to_run(r: &SqliteRow) -> Result<JobRun, sqlx::Error> {
+    let status: String = r.try_get("status")?;
+    Ok(JobRun {
+        id: r.try_get("id")?,
+        job_id: r.try_get("job_id")?,
+        scheduled_at: parse_datetime(r.try_get("scheduled_at")?).unwrap_or_default(),
+        started_at: parse_datetime(r.try_get("started_at")?),
+        finished_at: parse_datetime(r.try_get("finished_at")?),
+        duration_ms: r.try_get("duration_ms")?,
+        status: JobStatus::from_str(&status).unwrap_or(JobStatus::Failed),
+        message: r.try_get("message")?,
+        node_id: r.try_get("node_id")?,
+        attempt: r.try_get("attempt")?,
+        trigger: r
+            .try_get::<Option<String>, _>("trigger_type")?
+            .and_then(|s| RunTrigger::from_str(&s).ok())
+            .unwrap_or_default(),
+        triggered_by: r.try_get("triggered_by")?,
+        external_ref: r.try_get("external_ref")?,
+        parent_run_id: r.try_get("parent_run_id")?,
+        output: from_json(r.try_get("output")?),
+    })
+}
+
+/// Brings databases created by older versions up to the current `jobs`/`job_runs` layout.
+async fn add_column_if_missing(pool: &Pool<Sqlite>, table: &str, column: &str, definition: &str) {
+    let sql = format!("SELECT name FROM pragma_table_info('{}')", table);
+    let columns: Vec<String> = sqlx::query_scalar(&sql).fetch_all(pool).await.unwrap();
+    if !columns.iter().any(|c| c == column) {
+        debug!("Adding column {}.{}", table, column);
+        let sql = format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition);
+        sqlx::query(&sql).execute(pool).await.unwrap();
+    }
 }
 
 #[derive(Clone)]
@@ -24,71 +160,194 @@ impl SqliteJobStore {
     pub async fn insert_job(&self, job: &JobRaw) -> Result<(), sqlx::Error> {
         sqlx::query(
             r#"
-            INSERT INTO jobs (id, name, cron, task_type, payload, last_run,status)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            INSERT INTO jobs (id, name, cron, task_type, payload, last_run, status, retry_policy, timeout_secs, overlap_policy, misfire_policy, timezone, priority, on_success, on_failure, calendar_id, schedule, jitter_secs)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)
             "#,
         )
         .bind(&job.id)
         .bind(&job.name)
-        .bind(&job.cron)
+        .bind(job.schedule.cron().unwrap_or_default())
         .bind(&job.task_type)
         .bind(&job.payload)
         .bind(job.last_run.map(|d| d.to_rfc3339()))
-        .bind(&job.status.to_string())
+        .bind(job.status.to_string())
+        .bind(to_json(&job.retry_policy))
+        .bind(job.timeout_secs.map(|secs| secs as i64))
+        .bind(job.overlap_policy.to_string())
+        .bind(to_json(&Some(job.misfire_policy)))
+        .bind(&job.timezone)
+        .bind(job.priority)
+        .bind(to_json(&Some(&job.on_success)))
+        .bind(to_json(&Some(&job.on_failure)))
+        .bind(&job.calendar_id)
+        .bind(to_json(&Some(&job.schedule)))
+        .bind(job.jitter_secs.map(|secs| secs as i64))
         .execute(&*self.pool)
         .await?;
 
-        Ok(())
+        self.set_dependencies(&job.id, &job.depends_on).await
     }
 
     pub async fn get_job_by_id(&self, id: &str) -> Result<Option<JobRaw>, sqlx::Error> {
-        let row = sqlx::query(
-            r#"SELECT id, name, cron, task_type, payload, last_run,status FROM jobs WHERE id = ?"#,
+        let sql = format!("SELECT {} FROM jobs WHERE id = ?", JOB_COLUMNS);
+        let row = sqlx::query(&sql)
+            .bind(id)
+            .fetch_optional(&*self.pool)
+            .await?;
+
+        let Some(mut job) = row.as_ref().map(row_to_job).transpose()? else {
+            return Ok(None);
+        };
+        job.depends_on = sqlx::query_scalar(
+            r#"SELECT depends_on FROM job_dependencies WHERE job_id = ? ORDER BY depends_on"#,
         )
         .bind(id)
-        .fetch_optional(&*self.pool)
+        .fetch_all(&*self.pool)
         .await?;
-
-        if let Some(r) = row {
-            let status = r.try_get("status").unwrap_or("start");
-            let status = JobStatus::from_str(status).unwrap_or(JobStatus::Start);
-
-            Ok(Some(JobRaw {
-                id: r.try_get("id")?,
-                name: r.try_get("name")?,
-                cron: r.try_get("cron")?,
-                task_type: r.try_get("task_type")?,
-                payload: r.try_get("payload").unwrap_or_default(),
-                last_run: r.try_get::<Option<String>, _>("last_run")?.map(|s| {
-                    DateTime::parse_from_rfc3339(&s)
-                        .unwrap()
-                        .with_timezone(&Utc)
-                }),
-                status: status,
-                message: r.try_get("message").unwrap_or_default(),
-            }))
-        } else {
-            Ok(None)
+        if let Some(calendar_id) = &job.calendar_id {
+            job.calendar = self.get_calendar(calendar_id).await?;
         }
+        Ok(Some(job))
     }
 
     pub async fn update_job(&self, job: &JobRaw) -> Result<(), sqlx::Error> {
         sqlx::query(
             r#"
             UPDATE jobs
-            SET name = ?1, cron = ?2, task_type = ?3, payload = ?4 , status = ?5
-            WHERE id = ?6
+            SET name = ?1, cron = ?2, task_type = ?3, payload = ?4 , status = ?5, retry_policy = ?6,
+                timeout_secs = ?7, overlap_policy = ?8, misfire_policy = ?9, timezone = ?10,
+                priority = ?11, on_success = ?12, on_failure = ?13, calendar_id = ?14,
+                schedule = ?15, jitter_secs = ?16
+            WHERE id = ?17
             "#,
         )
         .bind(&job.name)
-        .bind(&job.cron)
+        .bind(job.schedule.cron().unwrap_or_default())
         .bind(&job.task_type)
         .bind(&job.payload)
-        .bind(&job.status.to_string())
+        .bind(job.status.to_string())
+        .bind(to_json(&job.retry_policy))
+        .bind(job.timeout_secs.map(|secs| secs as i64))
+        .bind(job.overlap_policy.to_string())
+        .bind(to_json(&Some(job.misfire_policy)))
+        .bind(&job.timezone)
+        .bind(job.priority)
+        .bind(to_json(&Some(&job.on_success)))
+        .bind(to_json(&Some(&job.on_failure)))
+        .bind(&job.calendar_id)
+        .bind(to_json(&Some(&job.schedule)))
+        .bind(job.jitter_secs.map(|secs| secs as i64))
         .bind(&job.id)
         .execute(&*self.pool)
         .await?;
 
+        self.set_dependencies(&job.id, &job.depends_on).await
+    }
+
+    /// Replaces the upstream jobs of `job_id`.
+    async fn set_dependencies(
+        &self,
+        job_id: &str,
+        depends_on: &[String],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(r#"DELETE FROM job_dependencies WHERE job_id = ?"#)
+            .bind(job_id)
+            .execute(&mut *tx)
+            .await?;
+        for upstream in depends_on {
+            sqlx::query(r#"INSERT INTO job_dependencies (job_id, depends_on) VALUES (?, ?)"#)
+                .bind(job_id)
+                .bind(upstream)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await
+    }
+
+    /// Upstream edges of every job that has dependencies.
+    pub async fn dependency_graph(&self) -> Result<DependencyGraph, sqlx::Error> {
+        let edges: Vec<(String, String)> = sqlx::query_as(
+            r#"SELECT job_id, depends_on FROM job_dependencies ORDER BY job_id, depends_on"#,
+        )
+        .fetch_all(&*self.pool)
+        .await?;
+
+        let mut graph = DependencyGraph::new();
+        for (job_id, upstream) in edges {
+            graph.entry(job_id).or_default().push(upstream);
+        }
+        Ok(graph)
+    }
+
+    pub async fn list_calendars(&self) -> Result<Vec<Calendar>, sqlx::Error> {
+        let sql = format!("SELECT {} FROM calendars ORDER BY name", CALENDAR_COLUMNS);
+        let rows = sqlx::query(&sql).fetch_all(&*self.pool).await?;
+
+        rows.iter().map(row_to_calendar).collect()
+    }
+
+    pub async fn get_calendar(&self, id: &str) -> Result<Option<Calendar>, sqlx::Error> {
+        let sql = format!("SELECT {} FROM calendars WHERE id = ?", CALENDAR_COLUMNS);
+        let row = sqlx::query(&sql)
+            .bind(id)
+            .fetch_optional(&*self.pool)
+            .await?;
+
+        row.as_ref().map(row_to_calendar).transpose()
+    }
+
+    /// Inserts the calendar, or replaces it if one with the same id exists.
+    pub async fn save_calendar(&self, calendar: &Calendar) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO calendars (id, name, timezone, holidays, include_windows, exclude_windows)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+        )
+        .bind(&calendar.id)
+        .bind(&calendar.name)
+        .bind(&calendar.timezone)
+        .bind(to_json(&Some(&calendar.holidays)))
+        .bind(to_json(&Some(&calendar.include)))
+        .bind(to_json(&Some(&calendar.exclude)))
+        .execute(&*self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_calendar(&self, id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(r#"DELETE FROM calendars WHERE id = ?"#)
+            .bind(id)
+            .execute(&*self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Ids of the jobs attached to a calendar.
+    pub async fn jobs_using_calendar(&self, id: &str) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar(r#"SELECT id FROM jobs WHERE calendar_id = ? ORDER BY name"#)
+            .bind(id)
+            .fetch_all(&*self.pool)
+            .await
+    }
+
+    /// Pauses (`disabled`) or resumes (`scheduled`) a job.
+    pub async fn set_enabled(&self, id: &str, enabled: bool) -> Result<(), sqlx::Error> {
+        let (status, message) = if enabled {
+            (JobStatus::Scheduled, "Resumed")
+        } else {
+            (JobStatus::Disabled, "Paused")
+        };
+        sqlx::query(r#"UPDATE jobs SET status = ?, message = ? WHERE id = ?"#)
+            .bind(status.to_string())
+            .bind(message)
+            .bind(id)
+            .execute(&*self.pool)
+            .await?;
+
         Ok(())
     }
 
@@ -97,10 +356,45 @@ impl SqliteJobStore {
             .bind(id)
             .execute(&*self.pool)
             .await?;
+        // Downstream jobs lose this upstream rather than waiting for it forever
+        sqlx::query(r#"DELETE FROM job_dependencies WHERE job_id = ?1 OR depends_on = ?1"#)
+            .bind(id)
+            .execute(&*self.pool)
+            .await?;
 
         Ok(())
     }
 
+    pub async fn list_runs(&self, job_id: &str, limit: i64) -> Result<Vec<JobRun>, sqlx::Error> {
+        let sql = format!(
+            r#"
+            SELECT {}
+            FROM job_runs
+            WHERE job_id = ?1
+            ORDER BY scheduled_at DESC, started_at DESC
+            LIMIT ?2
+            "#,
+            RUN_COLUMNS
+        );
+        let rows = sqlx::query(&sql)
+            .bind(job_id)
+            .bind(limit)
+            .fetch_all(&*self.pool)
+            .await?;
+
+        rows.iter().map(row_to_run).collect()
+    }
+
+    pub async fn get_run_by_id(&self, run_id: &str) -> Result<Option<JobRun>, sqlx::Error> {
+        let sql = format!("SELECT {} FROM job_runs WHERE id = ?", RUN_COLUMNS);
+        let row = sqlx::query(&sql)
+            .bind(run_id)
+            .fetch_optional(&*self.pool)
+            .await?;
+
+        row.as_ref().map(row_to_run).transpose()
+    }
+
     pub async fn new(db_url: &str) -> Self {
         // Ensure the directory exists
         debug!("CWD = {:?}", std::env::current_dir());
@@ -109,10 +403,10 @@ impl SqliteJobStore {
             let path = db_url.trim_start_matches("sqlite://");
 
             let path_obj = Path::new(path);
-            if let Some(parent) = path_obj.parent() {
-                if let Err(e) = fs::create_dir_all(parent) {
-                    panic!("Failed to create DB folder {:?}: {}", parent, e);
-                }
+            if let Some(parent) = path_obj.parent()
+                && let Err(e) = fs::create_dir_all(parent)
+            {
+                panic!("Failed to create DB folder {:?}: {}", parent, e);
             }
         }
 
@@ -133,7 +427,57 @@ impl SqliteJobStore {
                 payload TEXT,
                 last_run TEXT,
                 status TEXT DEFAULT 'start',
-                message TEXT DEFAULT ''
+                message TEXT DEFAULT '',
+                retry_policy TEXT,
+                timeout_secs INTEGER,
+                overlap_policy TEXT DEFAULT 'skip',
+                misfire_policy TEXT,
+                timezone TEXT,
+                priority INTEGER NOT NULL DEFAULT 0,
+                on_success TEXT,
+                on_failure TEXT,
+                calendar_id TEXT,
+                schedule TEXT,
+                jitter_secs INTEGER,
+                dependencies_met_at TEXT
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS job_runs (
+                id TEXT PRIMARY KEY,
+                job_id TEXT NOT NULL,
+                scheduled_at TEXT NOT NULL,
+                started_at TEXT,
+                finished_at TEXT,
+                duration_ms INTEGER,
+                status TEXT NOT NULL,
+                message TEXT,
+                node_id TEXT NOT NULL,
+                attempt INTEGER NOT NULL DEFAULT 1,
+                trigger_type TEXT NOT NULL DEFAULT 'schedule',
+                triggered_by TEXT,
+                external_ref TEXT,
+                parent_run_id TEXT,
+                output TEXT
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS job_dependencies (
+                job_id TEXT NOT NULL,
+                depends_on TEXT NOT NULL,
+                PRIMARY KEY (job_id, depends_on)
             )
             "#,
         )
@@ -141,6 +485,54 @@ impl SqliteJobStore {
         .await
         .unwrap();
 
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS calendars (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                timezone TEXT,
+                holidays TEXT,
+                include_windows TEXT,
+                exclude_windows TEXT
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        add_column_if_missing(&pool, "jobs", "retry_policy", "TEXT").await;
+        add_column_if_missing(&pool, "jobs", "timeout_secs", "INTEGER").await;
+        add_column_if_missing(&pool, "jobs", "overlap_policy", "TEXT DEFAULT 'skip'").await;
+        add_column_if_missing(&pool, "jobs", "misfire_policy", "TEXT").await;
+        add_column_if_missing(&pool, "jobs", "timezone", "TEXT").await;
+        add_column_if_missing(&pool, "jobs", "priority", "INTEGER NOT NULL DEFAULT 0").await;
+        add_column_if_missing(&pool, "jobs", "on_success", "TEXT").await;
+        add_column_if_missing(&pool, "jobs", "on_failure", "TEXT").await;
+        add_column_if_missing(&pool, "jobs", "calendar_id", "TEXT").await;
+        add_column_if_missing(&pool, "jobs", "schedule", "TEXT").await;
+        add_column_if_missing(&pool, "jobs", "jitter_secs", "INTEGER").await;
+        add_column_if_missing(&pool, "jobs", "dependencies_met_at", "TEXT").await;
+        add_column_if_missing(&pool, "job_runs", "attempt", "INTEGER NOT NULL DEFAULT 1").await;
+        add_column_if_missing(
+            &pool,
+            "job_runs",
+            "trigger_type",
+            "TEXT NOT NULL DEFAULT 'schedule'",
+        )
+        .await;
+        add_column_if_missing(&pool, "job_runs", "triggered_by", "TEXT").await;
+        add_column_if_missing(&pool, "job_runs", "external_ref", "TEXT").await;
+        add_column_if_missing(&pool, "job_runs", "parent_run_id", "TEXT").await;
+        add_column_if_missing(&pool, "job_runs", "output", "TEXT").await;
+
+        sqlx::query(
+            r#"CREATE INDEX IF NOT EXISTS idx_job_runs_job_id ON job_runs (job_id, scheduled_at)"#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
         SqliteJobStore {
             pool: Arc::new(pool),
         }
@@ -150,30 +542,26 @@ impl SqliteJobStore {
 #[async_trait]
 impl JobStore for SqliteJobStore {
     async fn load_jobs(&self) -> Vec<JobRaw> {
-        let rows =
-            sqlx::query(r#"SELECT id, name, cron, task_type, payload, last_run,status FROM jobs"#)
-                .fetch_all(&*self.pool)
-                .await
-                .unwrap();
-
-        rows.into_iter()
-            .map(|r| JobRaw {
-                id: r.try_get("id").unwrap(),
-                name: r.try_get("name").unwrap(),
-                cron: r.try_get("cron").unwrap(),
-                task_type: r.try_get("task_type").unwrap(),
-                payload: r.try_get("payload").unwrap_or_default(),
-                last_run: r
-                    .try_get::<Option<String>, _>("last_run")
-                    .unwrap()
-                    .map(|s| {
-                        DateTime::parse_from_rfc3339(&s)
-                            .unwrap()
-                            .with_timezone(&Utc)
-                    }),
-                status: JobStatus::from_str(r.try_get("status").unwrap_or("start"))
-                    .unwrap_or(JobStatus::Start),
-                message: r.try_get("message").unwrap_or_default(),
+        let sql = format!("SELECT {} FROM jobs", JOB_COLUMNS);
+        let rows = sqlx::query(&sql).fetch_all(&*self.pool).await.unwrap();
+        let mut graph = self.dependency_graph().await.unwrap();
+        let calendars: HashMap<String, Calendar> = self
+            .list_calendars()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|calendar| (calendar.id.clone(), calendar))
+            .collect();
+
+        rows.iter()
+            .map(|r| {
+                let mut job = row_to_job(r).unwrap();
+                job.depends_on = graph.remove(&job.id).unwrap_or_default();
+                job.calendar = job
+                    .calendar_id
+                    .as_ref()
+                    .and_then(|id| calendars.get(id).cloned());
+                job
             })
             .collect()
     }
@@ -187,9 +575,141 @@ impl JobStore for SqliteJobStore {
             .unwrap();
     }
     async fn update_status(&self, job_id: &str, status: JobStatus, message: &str) {
-        sqlx::query(r#"UPDATE jobs SET status = ? , message = ? WHERE id = ?"#)
-            .bind(status.to_string())
-            .bind(message.to_string())
+        // A paused job stays paused until resumed, even if a run finishes afterwards
+        sqlx::query(
+            r#"UPDATE jobs SET status = ? , message = ? WHERE id = ? AND status != 'disabled'"#,
+        )
+        .bind(status.to_string())
+        .bind(message.to_string())
+        .bind(job_id)
+        .execute(&*self.pool)
+        .await
+        .unwrap();
+    }
+
+    async fn insert_run(&self, run: &JobRun) {
+        sqlx::query(
+            r#"
+            INSERT INTO job_runs (id, job_id, scheduled_at, started_at, finished_at, duration_ms, status, message, node_id, attempt, trigger_type, triggered_by, external_ref, parent_run_id, output)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)
+            "#,
+        )
+        .bind(&run.id)
+        .bind(&run.job_id)
+        .bind(run.scheduled_at.to_rfc3339())
+        .bind(run.started_at.map(|d| d.to_rfc3339()))
+        .bind(run.finished_at.map(|d| d.to_rfc3339()))
+        .bind(run.duration_ms)
+        .bind(run.status.to_string())
+        .bind(&run.message)
+        .bind(&run.node_id)
+        .bind(run.attempt)
+        .bind(run.trigger.to_string())
+        .bind(&run.triggered_by)
+        .bind(&run.external_ref)
+        .bind(&run.parent_run_id)
+        .bind(to_json(&run.output))
+        .execute(&*self.pool)
+        .await
+        .unwrap();
+    }
+
+    async fn update_run(&self, run: &JobRun) {
+        sqlx::query(
+            r#"
+            UPDATE job_runs
+            SET started_at = ?1, finished_at = ?2, duration_ms = ?3, status = ?4, message = ?5
+            WHERE id = ?6
+            "#,
+        )
+        .bind(run.started_at.map(|d| d.to_rfc3339()))
+        .bind(run.finished_at.map(|d| d.to_rfc3339()))
+        .bind(run.duration_ms)
+        .bind(run.status.to_string())
+        .bind(&run.message)
+        .bind(&run.id)
+        .execute(&*self.pool)
+        .await
+        .unwrap();
+    }
+
+    async fn last_scheduled_at(&self, job_id: &str) -> Option<DateTime<Utc>> {
+        let value: Option<String> = sqlx::query_scalar(
+            r#"SELECT MAX(scheduled_at) FROM job_runs WHERE job_id = ? AND trigger_type = 'schedule'"#,
+        )
+        .bind(job_id)
+        .fetch_one(&*self.pool)
+        .await
+        .unwrap();
+        parse_datetime(value)
+    }
+
+    async fn set_run_external_ref(&self, run_id: &str, external_ref: &str) {
+        sqlx::query(r#"UPDATE job_runs SET external_ref = ? WHERE id = ?"#)
+            .bind(external_ref)
+            .bind(run_id)
+            .execute(&*self.pool)
+            .await
+            .unwrap();
+    }
+
+    async fn set_run_output(&self, run_id: &str, output: &serde_json::Value) {
+        sqlx::query(r#"UPDATE job_runs SET output = ? WHERE id = ?"#)
+            .bind(output.to_string())
+            .bind(run_id)
+            .execute(&*self.pool)
+            .await
+            .unwrap();
+    }
+
+    async fn unfinished_runs(&self) -> Vec<JobRun> {
+        let sql = format!(
+            "SELECT {} FROM job_runs WHERE status = 'running' ORDER BY started_at",
+            RUN_COLUMNS
+        );
+        let rows = sqlx::query(&sql).fetch_all(&*self.pool).await.unwrap();
+
+        rows.iter().map(|r| row_to_run(r).unwrap()).collect()
+    }
+
+    async fn find_job(&self, job_id: &str) -> Option<JobRaw> {
+        self.get_job_by_id(job_id).await.unwrap()
+    }
+
+    async fn dependents_of(&self, job_id: &str) -> Vec<JobRaw> {
+        let ids: Vec<String> = sqlx::query_scalar(
+            r#"SELECT job_id FROM job_dependencies WHERE depends_on = ? ORDER BY job_id"#,
+        )
+        .bind(job_id)
+        .fetch_all(&*self.pool)
+        .await
+        .unwrap();
+
+        let mut jobs = Vec::with_capacity(ids.len());
+        for id in ids {
+            jobs.extend(self.get_job_by_id(&id).await.unwrap());
+        }
+        jobs
+    }
+
+    async fn has_succeeded_since(&self, job_id: &str, since: Option<DateTime<Utc>>) -> bool {
+        let last_success: Option<String> = sqlx::query_scalar(
+            r#"SELECT MAX(finished_at) FROM job_runs WHERE job_id = ? AND status = 'success'"#,
+        )
+        .bind(job_id)
+        .fetch_one(&*self.pool)
+        .await
+        .unwrap();
+        match (parse_datetime(last_success), since) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(finished_at), Some(since)) => finished_at > since,
+        }
+    }
+
+    async fn set_dependencies_met_at(&self, job_id: &str, dt: DateTime<Utc>) {
+        sqlx::query(r#"UPDATE jobs SET dependencies_met_at = ? WHERE id = ?"#)
+            .bind(dt.to_rfc3339())
             .bind(job_id)
             .execute(&*self.pool)
             .await
@@ -1,15 +1,18 @@
-use crate::api::job_routes;
+use crate::api::{calendar_routes, job_routes, run_routes, schedule_routes, worker_routes};
 use crate::engine::engine::JobEngine;
-use crate::job::store::{JobStore, SqliteJobStore};
+use crate::job::store::SqliteJobStore;
 use crate::shard::{DistributedShardManager, LocalShardManager, ShardManager};
 use crate::task::registry::TaskRegistry;
 use actix_files::Files;
-use actix_web::{App, HttpServer, main, web};
-use log::{debug, info};
+use actix_web::{App, HttpServer, web};
+use log::{error, info};
 use std::sync::Arc;
-use crate::auth::auth_routes;
+use std::time::Duration;
+use crate::auth::{Sessions, auth_routes};
+use crate::utils::shutdown_signal;
 
 mod api;
+mod aws;
 mod azure;
 mod config;
 mod domain;
@@ -19,6 +22,7 @@ mod scheduler;
 mod shard;
 mod task;
 mod utils;
+mod wasm;
 mod auth;
 
 #[actix_web::main]
@@ -44,6 +48,14 @@ async fn main() -> std::io::Result<()> {
     let mut registry = TaskRegistry::new();
     registry.register(crate::task::print::PrintTask);
     registry.register(crate::task::adf::AdfTask);
+    registry.register(crate::task::shell::ShellTask);
+    registry.register(crate::task::http::HttpTask::new());
+    registry.register(crate::task::stepfn::StepFnTask);
+    registry.register(crate::task::logic_app::LogicAppTask::new());
+    match crate::task::wasm::WasmTask::new() {
+        Ok(task) => registry.register(task),
+        Err(e) => error!("WASM tasks are unavailable, failed to set up the runtime: {}", e),
+    }
     let task_registry = Arc::new(registry);
 
     let engine = Arc::new(JobEngine::new(
@@ -54,21 +66,45 @@ async fn main() -> std::io::Result<()> {
     ));
 
     let engine_clone = engine.clone();
-    tokio::task::spawn(async move {
+    let engine_task = tokio::task::spawn(async move {
         info!("Starting job engine...");
         engine_clone.run().await;
     });
 
-    HttpServer::new(move || {
+    let http_engine = engine.clone();
+    let sessions = web::Data::new(Sessions::default());
+    let server = HttpServer::new(move || {
         App::new()
             .wrap(actix_web::middleware::Logger::default())
             .app_data(web::Data::new(store.clone()))
-            .app_data(web::Data::new(engine.clone()))
-            .service(web::scope("/api").service(job_routes()))
+            .app_data(web::Data::new(http_engine.clone()))
+            .app_data(sessions.clone())
+            .service(
+                web::scope("/api")
+                    .service(job_routes())
+                    .service(run_routes())
+                    .service(worker_routes())
+                    .service(calendar_routes())
+                    .service(schedule_routes()),
+            )
             .service(auth_routes())
             .service(Files::new("/", "./statics").index_file("index.html"))
     })
     .bind(("0.0.0.0", 8888))?
-    .run()
-    .await
+    // Signals are handled below so the engine can drain before the server stops
+    .disable_signals()
+    .run();
+    let server_handle = server.handle();
+    let mut server_task = actix_web::rt::spawn(server);
+
+    tokio::select! {
+        result = &mut server_task => return result.unwrap_or_else(|e| Err(std::io::Error::other(e))),
+        _ = shutdown_signal() => {}
+    }
+
+    // The API stays up while draining so progress can be followed; new triggers are rejected
+    engine.shutdown(Duration::from_secs(app_conf.shutdown_grace_secs)).await;
+    let _ = engine_task.await;
+    server_handle.stop(true).await;
+    server_task.await.unwrap_or_else(|e| Err(std::io::Error::other(e)))
 }
@@ -0,0 +1,294 @@
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tokio::time::{Duration, sleep};
+use tokio_util::sync::CancellationToken;
+
+use crate::domain::model::{Job, JobRun, JobSchedule};
+use crate::engine::runner::JobRunner;
+use crate::scheduler::slot::JobSlot;
+use crate::scheduler::wheel::TimingWheel;
+
+struct State {
+    /// Next fire of every job, keyed by job id, in whole seconds since the epoch
+    wheel: TimingWheel<String>,
+    jobs: HashMap<String, JobSlot>,
+    /// Jitter window of jobs that set none
+    default_jitter_secs: u64,
+}
+
+impl State {
+    /// Queues the job's first fire after `after`. Fire times are on the job's schedule; the
+    /// wheel holds them delayed by the job's jitter.
+    fn schedule_next(&mut self, job_id: &str, after: DateTime<Utc>) {
+        let Some(slot) = self.jobs.get(job_id) else {
+            return;
+        };
+        let jitter = slot.job.jitter(self.default_jitter_secs);
+        match slot.job.next_run_after(after) {
+            Some(run_at) => self
+                .wheel
+                .insert(job_id.to_string(), to_tick(run_at + jitter)),
+            None if matches!(slot.job.schedule, JobSchedule::Once { .. }) => {
+                debug!("[{}] One-shot fire is over", slot.job.name)
+            }
+            None => warn!("[{}] No upcoming fire, not scheduled", slot.job.name),
+        }
+    }
+}
+
+fn to_tick(at: DateTime<Utc>) -> u64 {
+    let secs = at.timestamp().max(0) as u64;
+    // Round up, a fire must never be dispatched early
+    if at.timestamp_subsec_nanos() > 0 {
+        secs + 1
+    } else {
+        secs
+    }
+}
+
+fn from_tick(tick: u64) -> DateTime<Utc> {
+    DateTime::from_timestamp(tick as i64, 0).unwrap_or_default()
+}
+
+/// Single dispatcher for all local jobs: a timing wheel of next fire times, woken early
+/// whenever jobs are added, replaced or removed.
+pub struct Scheduler {
+    state: Mutex<State>,
+    wake: Notify,
+    runner: Arc<JobRunner>,
+    misfire_threshold: chrono::Duration,
+    stopped: CancellationToken,
+}
+
+impl Scheduler {
+    pub fn new(
+        runner: Arc<JobRunner>,
+        misfire_threshold: chrono::Duration,
+        default_jitter_secs: u64,
+    ) -> Self {
+        Scheduler {
+            state: Mutex::new(State {
+                wheel: TimingWheel::new(Utc::now().timestamp() as u64),
+                jobs: HashMap::new(),
+                default_jitter_secs,
+            }),
+            wake: Notify::new(),
+            runner,
+            misfire_threshold,
+            stopped: CancellationToken::new(),
+        }
+    }
+
+    /// Adds a job or replaces its definition. With `catch_up_since`, fires missed after that
+    /// instant are first handled according to the job's misfire policy. A fixed-delay job
+    /// has no missed fires; it fires its delay after its last run ended, or right away if
+    /// that time has passed.
+    pub fn add_job(&self, job: Job, catch_up_since: Option<DateTime<Utc>>) {
+        let now = Utc::now();
+        let job_id = job.id.clone();
+        {
+            let mut state = self.state.lock().unwrap();
+            // Fires still waiting out their jitter are neither missed nor skipped
+            let (catch_up_since, after) = if job.schedule.is_fixed_delay() {
+                (None, job.last_run.unwrap_or(now).min(now))
+            } else {
+                (catch_up_since, now - job.jitter(state.default_jitter_secs))
+            };
+            let slot = match state.jobs.get_mut(&job_id) {
+                Some(slot) => {
+                    debug!("[{}] Replacing existing schedule", job.name);
+                    slot.replace(job);
+                    slot
+                }
+                None => state
+                    .jobs
+                    .entry(job_id.clone())
+                    .or_insert(JobSlot::new(job)),
+            };
+            if let Some(since) = catch_up_since {
+                slot.catch_up(&self.runner, since, after, "scheduler was not running");
+            }
+            state.schedule_next(&job_id, after);
+        }
+        self.wake.notify_one();
+    }
+
+    /// Schedules the next fire of a fixed-delay job after one of its runs ended, unless a
+    /// fire is already pending.
+    pub fn run_finished(&self, job_id: &str) {
+        {
+            let mut state = self.state.lock().unwrap();
+            let fixed_delay = state
+                .jobs
+                .get(job_id)
+                .is_some_and(|slot| slot.job.schedule.is_fixed_delay());
+            if !fixed_delay || state.wheel.contains(&job_id.to_string()) {
+                return;
+            }
+            state.schedule_next(job_id, Utc::now());
+        }
+        self.wake.notify_one();
+    }
+
+    /// Reattaches to a run of the job left over by a previous process, before the job's
+    /// schedule is (re)started with [`add_job`](Self::add_job).
+    pub fn resume_run(&self, job: Job, run: JobRun) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .jobs
+            .entry(job.id.clone())
+            .or_insert_with(|| JobSlot::new(job))
+            .resume(&self.runner, run);
+    }
+
+    /// Stops scheduling a job. A run already in progress is allowed to finish.
+    pub fn remove_job(&self, job_id: &str) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.wheel.cancel(&job_id.to_string());
+        state.jobs.remove(job_id).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().jobs.len()
+    }
+
+    /// Makes [`run`](Self::run) return. Fires that come due afterwards are not dispatched.
+    pub fn stop(&self) {
+        self.stopped.cancel();
+    }
+
+    /// Dispatches fires as they come due, until [`stop`](Self::stop) is called.
+    pub async fn run(&self) {
+        info!("Dispatcher started with {} job(s)", self.len());
+        let mut expired = Vec::new();
+        while !self.stopped.is_cancelled() {
+            let hint = self.state.lock().unwrap().wheel.next_expiry_hint();
+            let wait = hint.map(|tick| {
+                (from_tick(tick) - Utc::now())
+                    .to_std()
+                    .unwrap_or(Duration::ZERO)
+            });
+            if wait != Some(Duration::ZERO) {
+                let timer = async {
+                    match wait {
+                        Some(wait) => sleep(wait).await,
+                        None => std::future::pending().await,
+                    }
+                };
+                tokio::select! {
+                    _ = timer => {}
+                    _ = self.wake.notified() => continue,
+                    _ = self.stopped.cancelled() => break,
+                }
+            }
+
+            self.dispatch_due(&mut expired);
+        }
+    }
+
+    fn dispatch_due(&self, expired: &mut Vec<(String, u64)>) {
+        let now = Utc::now();
+        let mut state = self.state.lock().unwrap();
+        let default_jitter_secs = state.default_jitter_secs;
+        state.wheel.advance(now.timestamp() as u64, expired);
+        for (job_id, tick) in expired.drain(..) {
+            let Some(slot) = state.jobs.get_mut(&job_id) else {
+                continue;
+            };
+
+            // Runs keep the scheduled time as `scheduled_at`, without the jitter
+            let due = from_tick(tick);
+            let jitter = slot.job.jitter(default_jitter_secs);
+            let run_at = due - jitter;
+            if slot.job.schedule.is_fixed_delay() {
+                // Timed from the end of this run, see `run_finished`
+                slot.fire(&self.runner, run_at);
+                continue;
+            }
+            if now - due > self.misfire_threshold {
+                let since = run_at - chrono::Duration::milliseconds(1);
+                slot.catch_up(&self.runner, since, now - jitter, "scheduler was stalled");
+            } else {
+                slot.fire(&self.runner, run_at);
+            }
+            state.schedule_next(&job_id, (now - jitter).max(run_at));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::model::JobStatus;
+    use crate::domain::policy::MisfirePolicy;
+    use crate::utils::testing::{self, Scripted, TempStore};
+    use chrono::{DurationRound, TimeDelta};
+
+    /// Dispatches `job` as if the dispatcher had last looked at the wheel just before its
+    /// fire at `due`, and waits for `runs` fires to finish.
+    async fn dispatch_late(store: &TempStore, job: Job, due: DateTime<Utc>, runs: usize) {
+        let (runner, mut finished) = store.runner(testing::config(), Scripted::default());
+        let scheduler = Scheduler::new(runner, TimeDelta::seconds(60), 0);
+        {
+            let mut state = scheduler.state.lock().unwrap();
+            state.wheel = TimingWheel::new(to_tick(due) - 1);
+            state.wheel.insert(job.id.clone(), to_tick(due));
+            state.jobs.insert(job.id.clone(), JobSlot::new(job));
+        }
+        scheduler.dispatch_due(&mut Vec::new());
+        for _ in 0..runs {
+            tokio::time::timeout(Duration::from_secs(10), finished.recv())
+                .await
+                .unwrap();
+        }
+    }
+
+    fn hourly(misfire_policy: MisfirePolicy) -> Job {
+        let mut raw = testing::job_raw("job");
+        raw.schedule = JobSchedule::Cron {
+            expression: "0 0 * * * *".to_string(),
+        };
+        raw.misfire_policy = misfire_policy;
+        raw.to_job().unwrap()
+    }
+
+    #[tokio::test]
+    async fn fire_late_within_the_threshold_runs_as_scheduled() {
+        let store = TempStore::new().await;
+        let due =
+            Utc::now().duration_trunc(TimeDelta::seconds(1)).unwrap() - TimeDelta::seconds(30);
+        dispatch_late(&store, hourly(MisfirePolicy::Skip), due, 1).await;
+
+        let runs = store.runs("job").await;
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].scheduled_at, due);
+        assert_eq!(runs[0].status, JobStatus::Success);
+    }
+
+    #[tokio::test]
+    async fn fire_later_than_the_threshold_goes_through_the_misfire_policy() {
+        let store = TempStore::new().await;
+        let hour = Utc::now().duration_trunc(TimeDelta::hours(1)).unwrap();
+        let due = hour - TimeDelta::hours(3);
+        dispatch_late(&store, hourly(MisfirePolicy::FireOnce), due, 1).await;
+
+        let runs: Vec<_> = store
+            .runs("job")
+            .await
+            .into_iter()
+            .map(|run| (run.scheduled_at, run.status))
+            .collect();
+        assert_eq!(
+            runs,
+            [
+                (due, JobStatus::Skipped),
+                (due + TimeDelta::hours(1), JobStatus::Skipped),
+                (due + TimeDelta::hours(2), JobStatus::Skipped),
+                (hour, JobStatus::Success),
+            ]
+        );
+    }
+}
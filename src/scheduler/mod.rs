@@ -1,2 +1,5 @@
-mod engine;
+mod dispatcher;
+mod slot;
 mod wheel;
+
+pub use dispatcher::Scheduler;
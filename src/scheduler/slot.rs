@@ -0,0 +1,431 @@
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::domain::model::{Job, JobRun};
+use crate::domain::policy::OverlapPolicy;
+use crate::engine::runner::JobRunner;
+
+/// Missed fires recorded individually during catch-up; older ones are summarised in one record.
+const MAX_RECORDED_MISFIRES: usize = 100;
+
+/// Work started for a job that may still be in progress.
+pub(super) struct InFlight {
+    task: JoinHandle<()>,
+    cancel: CancellationToken,
+    /// Still waiting for the run it was queued behind
+    waiting: Arc<AtomicBool>,
+}
+
+impl InFlight {
+    /// Spawns `work`, first waiting for `after` to finish. Cancelling the new work also
+    /// cancels the one it waits for.
+    fn spawn<F, Fut>(runner: &JobRunner, after: Option<InFlight>, work: F) -> Self
+    where
+        F: FnOnce(CancellationToken) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let cancel = runner.run_token();
+        let waiting = Arc::new(AtomicBool::new(after.is_some()));
+        let task = tokio::spawn({
+            let cancel = cancel.clone();
+            let waiting = waiting.clone();
+            async move {
+                if let Some(mut previous) = after {
+                    tokio::select! {
+                        _ = &mut previous.task => {}
+                        _ = cancel.cancelled() => {
+                            previous.cancel.cancel();
+                            return;
+                        }
+                    }
+                    waiting.store(false, Ordering::Release);
+                }
+                work(cancel).await
+            }
+        });
+        Self {
+            task,
+            cancel,
+            waiting,
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        !self.task.is_finished()
+    }
+
+    fn is_waiting(&self) -> bool {
+        self.waiting.load(Ordering::Acquire)
+    }
+}
+
+/// Dispatcher-side state of one scheduled job.
+pub(super) struct JobSlot {
+    pub job: Arc<Job>,
+    current: Option<InFlight>,
+}
+
+impl JobSlot {
+    pub fn new(job: Job) -> Self {
+        Self {
+            job: Arc::new(job),
+            current: None,
+        }
+    }
+
+    /// Replaces the job definition, keeping track of the run that may still be going.
+    pub fn replace(&mut self, job: Job) {
+        self.job = Arc::new(job);
+    }
+
+    /// Handles a fire that came due, applying the job's overlap policy.
+    pub fn fire(&mut self, runner: &Arc<JobRunner>, scheduled_at: DateTime<Utc>) {
+        let job = &self.job;
+        let Some(running) = self.current.as_ref().filter(|c| c.is_running()) else {
+            self.current = Some(execute(runner, job, scheduled_at, None));
+            return;
+        };
+
+        match job.overlap_policy {
+            OverlapPolicy::Allow => {
+                // Not tracked: later fires only look at the run started last
+                self.current = Some(execute(runner, job, scheduled_at, None));
+            }
+            OverlapPolicy::Skip => skip(
+                runner,
+                job,
+                scheduled_at,
+                "Skipped due to overlap: previous run still in progress",
+            ),
+            OverlapPolicy::QueueOne => {
+                if running.is_waiting() {
+                    skip(
+                        runner,
+                        job,
+                        scheduled_at,
+                        "Skipped due to overlap: a fire is already queued",
+                    );
+                } else {
+                    debug!(
+                        "[{}] Queued fire at {} behind the running one",
+                        job.name, scheduled_at
+                    );
+                    let previous = self.current.take();
+                    self.current = Some(execute(runner, job, scheduled_at, previous));
+                }
+            }
+            OverlapPolicy::Replace => {
+                info!(
+                    "[{}] Cancelling previous run in favour of fire at {}",
+                    job.name, scheduled_at
+                );
+                running.cancel.cancel();
+                self.current = Some(execute(runner, job, scheduled_at, None));
+            }
+        }
+    }
+
+    /// Reattaches to a run left over by a previous process; later fires see it as the
+    /// run in progress.
+    pub fn resume(&mut self, runner: &Arc<JobRunner>, run: JobRun) {
+        let previous = self.current.take().filter(|c| c.is_running());
+        let job = self.job.clone();
+        self.current = Some(InFlight::spawn(runner, previous, {
+            let runner = runner.clone();
+            move |cancel| async move { runner.resume(&job, run, cancel).await }
+        }));
+    }
+
+    /// Applies the job's misfire policy to fires that came due in `(since, until]`, after
+    /// whatever is currently running. Regular fires meanwhile go through the overlap policy.
+    pub fn catch_up(
+        &mut self,
+        runner: &Arc<JobRunner>,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        reason: &'static str,
+    ) {
+        let previous = self.current.take().filter(|c| c.is_running());
+        let job = self.job.clone();
+        self.current = Some(InFlight::spawn(runner, previous, {
+            let runner = runner.clone();
+            move |cancel| async move { catch_up(&job, &runner, since, until, reason, cancel).await }
+        }));
+    }
+}
+
+fn execute(
+    runner: &Arc<JobRunner>,
+    job: &Arc<Job>,
+    scheduled_at: DateTime<Utc>,
+    after: Option<InFlight>,
+) -> InFlight {
+    let job = job.clone();
+    InFlight::spawn(runner, after, {
+        let runner = runner.clone();
+        move |cancel| async move { runner.execute(&job, scheduled_at, cancel).await }
+    })
+}
+
+fn skip(
+    runner: &Arc<JobRunner>,
+    job: &Arc<Job>,
+    scheduled_at: DateTime<Utc>,
+    reason: &'static str,
+) {
+    let runner = runner.clone();
+    let job = job.clone();
+    tokio::spawn(async move { runner.record_skipped(&job, scheduled_at, reason).await });
+}
+
+/// Fires picked for execution run one after another.
+async fn catch_up(
+    job: &Job,
+    runner: &JobRunner,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    reason: &str,
+    cancel: CancellationToken,
+) {
+    let missed = job.missed_fires(since, until, MAX_RECORDED_MISFIRES);
+    if missed.total == 0 {
+        return;
+    }
+    warn!(
+        "[{}] {} missed fire(s) since {} ({}), policy {:?}",
+        job.name, missed.total, since, reason, job.misfire_policy
+    );
+
+    let run_count = job.misfire_policy.fires_to_run().min(missed.recent.len());
+    let (to_skip, to_run) = missed.recent.split_at(missed.recent.len() - run_count);
+
+    let untracked = missed.total - missed.recent.len();
+    if let (true, Some(first)) = (untracked > 0, missed.first) {
+        let message = format!("Missed {} earlier fire(s): {}", untracked, reason);
+        runner.record_skipped(job, first, &message).await;
+    }
+    for scheduled_at in to_skip {
+        let message = format!("Missed fire: {}", reason);
+        runner.record_skipped(job, *scheduled_at, &message).await;
+    }
+    for scheduled_at in to_run {
+        if cancel.is_cancelled() {
+            break;
+        }
+        info!("[{}] Catching up missed fire at {}", job.name, scheduled_at);
+        runner.execute(job, *scheduled_at, cancel.clone()).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::model::JobStatus;
+    use crate::engine::runner::FireFinished;
+    use crate::utils::testing::{self, Call, Scripted, Step, TempStore};
+    use chrono::TimeZone;
+    use std::time::Duration;
+    use tokio::sync::mpsc::UnboundedReceiver;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn slot(overlap_policy: OverlapPolicy) -> JobSlot {
+        let mut job = testing::job("job");
+        job.overlap_policy = overlap_policy;
+        JobSlot::new(job)
+    }
+
+    /// Waits for `count` fires to finish, then lets skips still being recorded land.
+    async fn finish(finished: &mut UnboundedReceiver<FireFinished>, count: usize) {
+        for _ in 0..count {
+            tokio::time::timeout(Duration::from_secs(10), finished.recv())
+                .await
+                .unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    async fn run_at(store: &TempStore, scheduled_at: DateTime<Utc>) -> JobRun {
+        store
+            .runs("job")
+            .await
+            .into_iter()
+            .find(|run| run.scheduled_at == scheduled_at)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn skip_records_the_overlapping_fire_as_skipped() {
+        let store = TempStore::new().await;
+        let handler = Scripted::new(vec![Step::Sleep(Duration::from_millis(500))]);
+        let (runner, mut finished) = store.runner(testing::config(), handler.clone());
+        let mut slot = slot(OverlapPolicy::Skip);
+
+        slot.fire(&runner, at(0));
+        slot.fire(&runner, at(1));
+        finish(&mut finished, 1).await;
+
+        assert_eq!(run_at(&store, at(0)).await.status, JobStatus::Success);
+        let skipped = run_at(&store, at(1)).await;
+        assert_eq!(skipped.status, JobStatus::Skipped);
+        assert_eq!(
+            skipped.message.as_deref(),
+            Some("Skipped due to overlap: previous run still in progress")
+        );
+        assert_eq!(handler.calls(), [Call::Handle]);
+
+        // Once the run is over the next fire starts normally
+        slot.fire(&runner, at(2));
+        finish(&mut finished, 1).await;
+        assert_eq!(run_at(&store, at(2)).await.status, JobStatus::Success);
+    }
+
+    #[tokio::test]
+    async fn queue_one_runs_one_fire_after_the_current_run() {
+        let store = TempStore::new().await;
+        let handler = Scripted::new(vec![Step::Sleep(Duration::from_millis(500))]);
+        let (runner, mut finished) = store.runner(testing::config(), handler.clone());
+        let mut slot = slot(OverlapPolicy::QueueOne);
+
+        slot.fire(&runner, at(0));
+        slot.fire(&runner, at(1));
+        slot.fire(&runner, at(2));
+        finish(&mut finished, 2).await;
+
+        let first = run_at(&store, at(0)).await;
+        let queued = run_at(&store, at(1)).await;
+        assert_eq!(first.status, JobStatus::Success);
+        assert_eq!(queued.status, JobStatus::Success);
+        assert!(queued.started_at.unwrap() >= first.finished_at.unwrap());
+        let skipped = run_at(&store, at(2)).await;
+        assert_eq!(skipped.status, JobStatus::Skipped);
+        assert_eq!(
+            skipped.message.as_deref(),
+            Some("Skipped due to overlap: a fire is already queued")
+        );
+        assert_eq!(handler.calls(), [Call::Handle, Call::Handle]);
+    }
+
+    #[tokio::test]
+    async fn replace_cancels_the_running_fire() {
+        let store = TempStore::new().await;
+        let handler = Scripted::new(vec![Step::Sleep(Duration::from_secs(30))]);
+        let (runner, mut finished) = store.runner(testing::config(), handler.clone());
+        let mut slot = slot(OverlapPolicy::Replace);
+
+        slot.fire(&runner, at(0));
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        slot.fire(&runner, at(1));
+        finish(&mut finished, 2).await;
+
+        let replaced = run_at(&store, at(0)).await;
+        assert_eq!(replaced.status, JobStatus::Cancelled);
+        assert_eq!(replaced.message.as_deref(), Some("Cancelled by the engine"));
+        assert_eq!(run_at(&store, at(1)).await.status, JobStatus::Success);
+        assert_eq!(handler.calls(), [Call::Handle, Call::Cancel, Call::Handle]);
+    }
+
+    #[tokio::test]
+    async fn allow_runs_overlapping_fires_side_by_side() {
+        let store = TempStore::new().await;
+        let handler = Scripted::new(vec![
+            Step::Sleep(Duration::from_millis(500)),
+            Step::Sleep(Duration::from_millis(500)),
+        ]);
+        let (runner, mut finished) = store.runner(testing::config(), handler.clone());
+        let mut slot = slot(OverlapPolicy::Allow);
+
+        slot.fire(&runner, at(0));
+        slot.fire(&runner, at(1));
+        finish(&mut finished, 2).await;
+
+        let first = run_at(&store, at(0)).await;
+        let second = run_at(&store, at(1)).await;
+        assert_eq!(first.status, JobStatus::Success);
+        assert_eq!(second.status, JobStatus::Success);
+        assert!(second.started_at.unwrap() < first.finished_at.unwrap());
+    }
+
+    #[tokio::test]
+    async fn catch_up_runs_the_fires_the_misfire_policy_picks() {
+        use crate::domain::policy::MisfirePolicy;
+
+        // Daily fires at 02:00 on the 2nd to the 6th are missed
+        let since = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        let until = Utc.with_ymd_and_hms(2024, 3, 6, 12, 0, 0).unwrap();
+        let day = |day: u32| Utc.with_ymd_and_hms(2024, 3, day, 2, 0, 0).unwrap();
+        // policy -> days run
+        let cases = [
+            (MisfirePolicy::Skip, vec![]),
+            (MisfirePolicy::FireOnce, vec![6]),
+            (MisfirePolicy::FireAll { max: 3 }, vec![4, 5, 6]),
+            (MisfirePolicy::FireAll { max: 10 }, vec![2, 3, 4, 5, 6]),
+        ];
+        for (policy, days_run) in cases {
+            let store = TempStore::new().await;
+            let (runner, mut finished) = store.runner(testing::config(), Scripted::default());
+            let mut job = testing::job("job");
+            job.misfire_policy = policy;
+            let mut slot = JobSlot::new(job);
+
+            slot.catch_up(&runner, since, until, "engine was down");
+            finish(&mut finished, days_run.len()).await;
+
+            let runs = store.runs("job").await;
+            assert_eq!(runs.len(), 5, "{:?}", policy);
+            for run in runs {
+                let day = (2..=6).find(|d| day(*d) == run.scheduled_at).unwrap();
+                match days_run.contains(&day) {
+                    true => assert_eq!(run.status, JobStatus::Success, "{:?}", policy),
+                    false => {
+                        assert_eq!(run.status, JobStatus::Skipped, "{:?}", policy);
+                        assert_eq!(run.message.as_deref(), Some("Missed fire: engine was down"));
+                    }
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn catch_up_summarises_fires_past_the_recorded_ones() {
+        let store = TempStore::new().await;
+        let (runner, _finished) = store.runner(testing::config(), Scripted::default());
+        let mut slot = slot(OverlapPolicy::Skip);
+        let since = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let missed = MAX_RECORDED_MISFIRES as i64 + 20;
+
+        slot.catch_up(
+            &runner,
+            since,
+            since + chrono::Duration::days(missed),
+            "down",
+        );
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while slot.current.as_ref().unwrap().is_running() {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        let runs = store.runs("job").await;
+        assert_eq!(runs.len(), MAX_RECORDED_MISFIRES + 1);
+        let summary = &runs[0];
+        assert_eq!(
+            summary.scheduled_at,
+            Utc.with_ymd_and_hms(2024, 1, 1, 2, 0, 0).unwrap()
+        );
+        assert_eq!(
+            summary.message.as_deref(),
+            Some("Missed 20 earlier fire(s): down")
+        );
+        assert!(runs.iter().all(|run| run.status == JobStatus::Skipped));
+    }
+}
@@ -1,102 +1,321 @@
-use crate::domain::model::Job;
-use crate::job::store::JobStore;
-use crate::task::registry::TaskRegistry;
-use chrono::{DateTime, Utc};
-use log::{debug, error, info};
-use std::cmp::Ordering;
-use std::collections::BinaryHeap;
-use std::sync::Arc;
-use tokio::sync::Mutex;
-use tokio::time::{Duration, Instant, sleep_until};
-
-#[derive(Clone)]
-pub struct ScheduledJob {
-    pub job: Job,
-    pub run_at: Instant,
-}
+//! Hierarchical timing wheel keyed by whole seconds.
+//!
+//! Entries live in one of four levels (seconds, minutes, hours, days) chosen by how far away
+//! their deadline is, or in an overflow list beyond the last level. Insert and cancel are O(1);
+//! an entry is moved down a level at most once per level as its deadline approaches.
 
-impl PartialEq for ScheduledJob {
-    fn eq(&self, other: &Self) -> bool {
-        self.run_at.eq(&other.run_at)
-    }
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Slot count and span of one slot, in ticks, for each level.
+const LEVELS: [(usize, u64); 4] = [(60, 1), (60, 60), (24, 3_600), (366, 86_400)];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bucket {
+    Slot { level: usize, slot: usize },
+    Overflow,
 }
-impl Eq for ScheduledJob {}
 
-impl PartialOrd for ScheduledJob {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(other.run_at.cmp(&self.run_at)) // min-heap
+impl Bucket {
+    fn level(&self) -> usize {
+        match self {
+            Bucket::Slot { level, .. } => *level,
+            Bucket::Overflow => LEVELS.len(),
+        }
     }
 }
-impl Ord for ScheduledJob {
-    fn cmp(&self, other: &Self) -> Ordering {
-        other.run_at.cmp(&self.run_at)
-    }
+
+#[derive(Debug, Clone, Copy)]
+struct Location {
+    deadline: u64,
+    bucket: Bucket,
+    /// Position inside the bucket's vector
+    index: usize,
 }
 
-pub struct Scheduler {
-    queue: Arc<Mutex<BinaryHeap<ScheduledJob>>>,
-    task_registry: Arc<TaskRegistry>,
-    store: Arc<dyn JobStore>,
+pub struct TimingWheel<K> {
+    /// Last tick that was processed; deadlines at or before it are already expired
+    current: u64,
+    levels: Vec<Vec<Vec<K>>>,
+    overflow: Vec<K>,
+    /// Entries per level, overflow last
+    counts: [usize; LEVELS.len() + 1],
+    entries: HashMap<K, Location>,
 }
 
-impl Scheduler {
-    pub fn new(task_registry: Arc<TaskRegistry>, store: Arc<dyn JobStore>) -> Self {
-        Scheduler {
-            queue: Arc::new(Mutex::new(BinaryHeap::new())),
-            task_registry,
-            store,
+impl<K: Clone + Eq + Hash> TimingWheel<K> {
+    pub fn new(current: u64) -> Self {
+        Self {
+            current,
+            levels: LEVELS
+                .iter()
+                .map(|(slots, _)| vec![Vec::new(); *slots])
+                .collect(),
+            overflow: Vec::new(),
+            counts: [0; LEVELS.len() + 1],
+            entries: HashMap::new(),
         }
     }
 
-    pub async fn add_job(&self, job: Job) {
-        if let Some(next_time) = job.next_run() {
-            let now = Utc::now();
-            let dur = (next_time - now).to_std().unwrap_or(Duration::from_secs(1));
-            let run_at = Instant::now() + dur;
+    /// Schedules `key` at `deadline`, replacing any earlier entry for it. Deadlines that
+    /// already passed expire on the next tick.
+    pub fn insert(&mut self, key: K, deadline: u64) {
+        self.cancel(&key);
+        let deadline = deadline.max(self.current + 1);
+        self.place(key, deadline);
+    }
 
-            let mut queue = self.queue.lock().await;
-            queue.push(ScheduledJob { job, run_at });
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Removes `key`, returning its deadline.
+    pub fn cancel(&mut self, key: &K) -> Option<u64> {
+        let location = self.entries.remove(key)?;
+        self.counts[location.bucket.level()] -= 1;
+        let bucket = self.bucket_mut(location.bucket);
+        bucket.swap_remove(location.index);
+        if let Some(moved) = bucket.get(location.index).cloned()
+            && let Some(moved) = self.entries.get_mut(&moved)
+        {
+            moved.index = location.index;
         }
+        Some(location.deadline)
     }
 
-    pub async fn run(&self) {
-        loop {
-            let opt_job = {
-                let mut queue = self.queue.lock().await;
-                queue.pop()
+    /// Processes every tick up to and including `to`, appending expired entries to `expired`
+    /// in deadline order.
+    pub fn advance(&mut self, to: u64, expired: &mut Vec<(K, u64)>) {
+        while self.current < to {
+            // Nothing moves before the next boundary of the lowest occupied level
+            self.current = match self.counts.iter().position(|count| *count > 0) {
+                None => to,
+                Some(0) => self.current,
+                Some(level) => {
+                    let span = LEVELS[level.min(LEVELS.len() - 1)].1;
+                    ((self.current / span + 1) * span - 1).clamp(self.current, to)
+                }
             };
+            if self.current == to {
+                break;
+            }
 
-            if let Some(scheduled) = opt_job {
-                let now = Instant::now();
-                if scheduled.run_at > now {
-                    sleep_until(scheduled.run_at).await;
+            self.current += 1;
+            let tick = self.current;
+
+            // Higher levels first, so entries can drop through several levels in one tick
+            if tick.is_multiple_of(LEVELS[LEVELS.len() - 1].1) {
+                let pending = std::mem::take(&mut self.overflow);
+                self.replace_all(LEVELS.len(), pending);
+            }
+            for level in (1..LEVELS.len()).rev() {
+                let (slots, span) = LEVELS[level];
+                if tick.is_multiple_of(span) {
+                    let slot = (tick / span) as usize % slots;
+                    let pending = std::mem::take(&mut self.levels[level][slot]);
+                    self.replace_all(level, pending);
                 }
+            }
 
-                let task_type = scheduled.job.task_type.clone().task_type_name();
-                let job = scheduled.job.clone();
-                // run task
-                let handler_opt = self.task_registry.get(task_type);
-                if let Some(handler) = handler_opt {
-                    let payload = scheduled.job.payload.clone();
-                    let job_name = scheduled.job.name.clone();
-                    let job_id = scheduled.job.id.clone();
-                    let store = self.store.clone();
-
-                    tokio::spawn(async move {
-                        info!("[{}] Execution with Payload: {:?}", job_name, payload);
-
-                        if let Err(err) = handler.handle(&job.task_type).await {
-                            error!("[{}] Error: {}", job_name, err);
-                        }
-                        store.update_last_run(&job_id, Utc::now()).await;
-                    });
+            let slot = tick as usize % LEVELS[0].0;
+            let due = std::mem::take(&mut self.levels[0][slot]);
+            self.counts[0] -= due.len();
+            for key in due {
+                if let Some(location) = self.entries.remove(&key) {
+                    expired.push((key, location.deadline));
                 }
+            }
+        }
+    }
+
+    /// Earliest tick at which [`advance`](Self::advance) may expire something: exact for
+    /// entries within the next minute, otherwise the next minute boundary.
+    pub fn next_expiry_hint(&self) -> Option<u64> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let span = LEVELS[1].1;
+        let boundary = (self.current / span + 1) * span;
+        (self.current + 1..boundary)
+            .find(|tick| !self.levels[0][*tick as usize % LEVELS[0].0].is_empty())
+            .or(Some(boundary))
+    }
+
+    fn replace_all(&mut self, level: usize, keys: Vec<K>) {
+        self.counts[level] -= keys.len();
+        for key in keys {
+            if let Some(location) = self.entries.remove(&key) {
+                self.place(key, location.deadline);
+            }
+        }
+    }
+
+    fn place(&mut self, key: K, deadline: u64) {
+        let delta = deadline - self.current;
+        let bucket = LEVELS
+            .iter()
+            .enumerate()
+            .find(|(_, (slots, span))| delta < *slots as u64 * span)
+            .map(|(level, (slots, span))| Bucket::Slot {
+                level,
+                slot: (deadline / span) as usize % slots,
+            })
+            .unwrap_or(Bucket::Overflow);
+
+        self.counts[bucket.level()] += 1;
+        let target = self.bucket_mut(bucket);
+        let index = target.len();
+        target.push(key.clone());
+        self.entries.insert(
+            key,
+            Location {
+                deadline,
+                bucket,
+                index,
+            },
+        );
+    }
+
+    fn bucket_mut(&mut self, bucket: Bucket) -> &mut Vec<K> {
+        match bucket {
+            Bucket::Slot { level, slot } => &mut self.levels[level][slot],
+            Bucket::Overflow => &mut self.overflow,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 3_600;
+    const DAY: u64 = 86_400;
+
+    /// Advances one tick at a time up to `to`, returning each key with the tick it expired on.
+    fn expire_each_tick(wheel: &mut TimingWheel<u64>, to: u64) -> Vec<(u64, u64)> {
+        let mut fired = Vec::new();
+        for tick in wheel.current + 1..=to {
+            let mut expired = Vec::new();
+            wheel.advance(tick, &mut expired);
+            fired.extend(expired.into_iter().map(|(key, _)| (key, tick)));
+        }
+        fired
+    }
 
-                // reschedule recurring job
-                self.add_job(scheduled.job).await;
-            } else {
-                tokio::time::sleep(Duration::from_secs(1)).await;
+    #[test]
+    fn entries_expire_exactly_at_slot_boundaries() {
+        for start in [0, 59, 1_000_030, 7 * DAY - 1] {
+            let deltas = [
+                1,
+                MINUTE - 1,
+                MINUTE,
+                MINUTE + 1,
+                HOUR - 1,
+                HOUR,
+                HOUR + 1,
+                DAY - 1,
+                DAY,
+                DAY + 1,
+            ];
+            let mut wheel = TimingWheel::new(start);
+            for delta in deltas {
+                wheel.insert(start + delta, start + delta);
             }
+
+            let fired = expire_each_tick(&mut wheel, start + DAY + 1);
+            let expected: Vec<_> = deltas.iter().map(|d| (start + d, start + d)).collect();
+            assert_eq!(fired, expected, "starting at {}", start);
+            assert!(wheel.entries.is_empty());
         }
     }
+
+    #[test]
+    fn entries_cascade_down_every_level() {
+        let start = 12_345;
+        let deadline = start + 3 * DAY + 2 * HOUR + 5 * MINUTE + 7;
+        let mut wheel = TimingWheel::new(start);
+        wheel.insert("job", deadline);
+        assert_eq!(wheel.entries["job"].bucket.level(), 3);
+
+        let mut expired = Vec::new();
+        wheel.advance(deadline - 2 * HOUR, &mut expired);
+        assert_eq!(wheel.entries["job"].bucket.level(), 2);
+        wheel.advance(deadline - 2 * MINUTE, &mut expired);
+        assert_eq!(wheel.entries["job"].bucket.level(), 1);
+        wheel.advance(deadline - 1, &mut expired);
+        assert_eq!(wheel.entries["job"].bucket.level(), 0);
+        assert!(expired.is_empty());
+
+        wheel.advance(deadline, &mut expired);
+        assert_eq!(expired, vec![("job", deadline)]);
+    }
+
+    #[test]
+    fn overflow_entries_come_back_into_the_wheel() {
+        let deadline = 400 * DAY + 17;
+        let mut wheel = TimingWheel::new(0);
+        wheel.insert("far", deadline);
+        assert_eq!(wheel.entries["far"].bucket, Bucket::Overflow);
+
+        let mut expired = Vec::new();
+        wheel.advance(deadline - 1, &mut expired);
+        assert!(expired.is_empty());
+        wheel.advance(deadline, &mut expired);
+        assert_eq!(expired, vec![("far", deadline)]);
+    }
+
+    #[test]
+    fn one_large_advance_expires_in_deadline_order() {
+        let start = 500;
+        let deadlines = [start + DAY + 3, start + 2, start + HOUR, start + MINUTE + 9];
+        let mut wheel = TimingWheel::new(start);
+        for (key, deadline) in deadlines.iter().enumerate() {
+            wheel.insert(key, *deadline);
+        }
+
+        let mut expired = Vec::new();
+        wheel.advance(start + 2 * DAY, &mut expired);
+        let order: Vec<_> = expired.iter().map(|(_, deadline)| *deadline).collect();
+        let mut sorted = deadlines.to_vec();
+        sorted.sort();
+        assert_eq!(order, sorted);
+    }
+
+    #[test]
+    fn past_deadlines_expire_on_the_next_tick() {
+        let mut wheel = TimingWheel::new(100);
+        wheel.insert("late", 40);
+
+        let mut expired = Vec::new();
+        wheel.advance(101, &mut expired);
+        assert_eq!(expired, vec![("late", 101)]);
+    }
+
+    #[test]
+    fn cancel_and_reinsert_keep_the_other_entries() {
+        let mut wheel = TimingWheel::new(0);
+        for key in ["a", "b", "c"] {
+            wheel.insert(key, 10);
+        }
+        assert_eq!(wheel.cancel(&"a"), Some(10));
+        assert_eq!(wheel.cancel(&"a"), None);
+        wheel.insert("c", 20);
+        assert!(wheel.contains(&"b"));
+
+        let mut expired = Vec::new();
+        wheel.advance(30, &mut expired);
+        assert_eq!(expired, vec![("b", 10), ("c", 20)]);
+    }
+
+    #[test]
+    fn expiry_hint_is_exact_within_the_minute() {
+        let mut wheel = TimingWheel::new(65);
+        assert_eq!(wheel.next_expiry_hint(), None);
+        wheel.insert("soon", 70);
+        assert_eq!(wheel.next_expiry_hint(), Some(70));
+        wheel.cancel(&"soon");
+        wheel.insert("later", 65 + HOUR);
+        assert_eq!(wheel.next_expiry_hint(), Some(2 * MINUTE));
+    }
 }
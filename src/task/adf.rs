@@ -1,49 +1,43 @@
-use crate::azure::{AdfClient, AdfPipelineRunStatus, AdfPipelineStatus};
-use crate::domain::task_payload::TaskPayload;
-use crate::task::handler::TaskHandler;
+use crate::azure::{AdfClient, AdfPipelineStatus};
+use crate::domain::task_payload::{AdfConfig, TaskPayload};
+use crate::task::handler::{TaskContext, TaskError, TaskHandler};
 use async_trait::async_trait;
-use log::debug;
+use log::{debug, info};
 
 pub struct AdfTask;
 
-#[async_trait]
-impl TaskHandler for AdfTask {
-    fn task_type(&self) -> &'static str {
-        "adf_pipeline"
-    }
-
-    async fn handle(&self, payload: &TaskPayload) -> Result<(), String> {
-        let adf_config = payload.as_adf().ok_or("Invalid payload for ADF task")?;
-        debug!("ADF task handler");
-
-        let adf_client = AdfClient::new(
+impl AdfTask {
+    fn client(adf_config: &AdfConfig) -> Result<AdfClient, TaskError> {
+        AdfClient::new(
             adf_config.subscription_id.clone(),
             adf_config.resource_group.clone(),
             adf_config.factory_name.clone(),
         )
-            .map_err(|e| e.to_string())?;
-
-        let id = adf_client
-            .trigger_pipeline_run(&adf_config.pipeline, None)
-            .await
-            .map_err(|e| e.to_string())?;
-
-        debug!("Triggered pipeline run: {} with parameters {}", id, payload);
+        .map_err(|e| TaskError::failed(e.to_string()))
+    }
 
+    /// Polls the pipeline run until it reaches a terminal status.
+    async fn wait_for(adf_client: &AdfClient, id: &str) -> Result<(), TaskError> {
         loop {
             let status = adf_client
-                .get_pipeline_status(&id)
+                .get_pipeline_status(id)
                 .await
-                .map_err(|e| e.to_string())?;
+                .map_err(|e| TaskError::transient(e.to_string()))?;
 
             match status.status {
                 AdfPipelineStatus::Succeeded => {
                     debug!("Pipeline run succeeded");
                     break;
                 }
-                AdfPipelineStatus::Failed => {
-                    debug!("Pipeline run failed");
-                    return Err(status.message.unwrap_or_else(|| "Pipeline run failed".to_string()));
+                AdfPipelineStatus::Failed
+                | AdfPipelineStatus::Cancelled
+                | AdfPipelineStatus::TimedOut => {
+                    debug!("Pipeline run ended with {}", status.status);
+                    return Err(TaskError::failed(
+                        status
+                            .message
+                            .unwrap_or_else(|| format!("Pipeline run {}", status.status)),
+                    ));
                 }
                 _ => debug!("Pipeline run status: {:?}", status),
             }
@@ -53,4 +47,61 @@ impl TaskHandler for AdfTask {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[async_trait]
+impl TaskHandler for AdfTask {
+    fn task_type(&self) -> &'static str {
+        "adf_pipeline"
+    }
+
+    async fn handle(&self, payload: &TaskPayload, ctx: &TaskContext) -> Result<(), TaskError> {
+        let adf_config = payload.as_adf().ok_or("Invalid payload for ADF task")?;
+        debug!("ADF task handler");
+
+        let adf_client = Self::client(adf_config)?;
+
+        let id = adf_client
+            .trigger_pipeline_run(&adf_config.pipeline, adf_config.parameters.clone())
+            .await
+            .map_err(|e| TaskError::transient(e.to_string()))?;
+        ctx.set_external_ref(&id).await;
+
+        debug!(
+            "[{}] Triggered pipeline run: {} for run {} with parameters {}",
+            ctx.job_id, id, ctx.run_id, payload
+        );
+
+        Self::wait_for(&adf_client, &id).await
+    }
+
+    async fn cancel(&self, payload: &TaskPayload, ctx: &TaskContext) -> Result<(), TaskError> {
+        let adf_config = payload.as_adf().ok_or("Invalid payload for ADF task")?;
+        let Some(run_id) = ctx.external_ref() else {
+            return Ok(());
+        };
+
+        info!("Cancelling pipeline run {}", run_id);
+        Self::client(adf_config)?
+            .cancel_pipeline_run(&run_id)
+            .await
+            .map_err(|e| TaskError::transient(e.to_string()))
+    }
+
+    fn can_resume(&self) -> bool {
+        true
+    }
+
+    async fn resume(&self, payload: &TaskPayload, ctx: &TaskContext) -> Result<(), TaskError> {
+        let adf_config = payload.as_adf().ok_or("Invalid payload for ADF task")?;
+        let run_id = ctx
+            .external_ref()
+            .ok_or("No pipeline run to resume polling")?;
+
+        info!(
+            "[{}] Resuming polling of pipeline run {} for run {}",
+            ctx.job_id, run_id, ctx.run_id
+        );
+        Self::wait_for(&Self::client(adf_config)?, &run_id).await
+    }
+}
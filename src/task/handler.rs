@@ -1,8 +1,197 @@
+use crate::domain::policy::ErrorClass;
 use crate::domain::task_payload::TaskPayload;
+use crate::job::store::JobStore;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::{RequestBuilder, Response};
+use serde::Serialize;
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone)]
+pub struct TaskError {
+    pub class: ErrorClass,
+    pub message: String,
+}
+
+impl TaskError {
+    pub fn transient(message: impl Into<String>) -> Self {
+        Self {
+            class: ErrorClass::Transient,
+            message: message.into(),
+        }
+    }
+
+    pub fn failed(message: impl Into<String>) -> Self {
+        Self {
+            class: ErrorClass::Failed,
+            message: message.into(),
+        }
+    }
+
+    pub fn timeout(message: impl Into<String>) -> Self {
+        Self {
+            class: ErrorClass::Timeout,
+            message: message.into(),
+        }
+    }
+
+    pub fn cancelled(message: impl Into<String>) -> Self {
+        Self {
+            class: ErrorClass::Cancelled,
+            message: message.into(),
+        }
+    }
+}
+
+/// Per-run state shared between the engine and a handler.
+#[derive(Clone)]
+pub struct TaskContext {
+    pub job_id: String,
+    pub run_id: String,
+    /// Due time of the fire the run belongs to, the same for all its attempts
+    pub scheduled_at: DateTime<Utc>,
+    external_ref: Arc<Mutex<Option<String>>>,
+    store: Arc<dyn JobStore>,
+}
+
+impl TaskContext {
+    pub fn new(
+        job_id: &str,
+        run_id: &str,
+        scheduled_at: DateTime<Utc>,
+        external_ref: Option<String>,
+        store: Arc<dyn JobStore>,
+    ) -> Self {
+        Self {
+            job_id: job_id.to_string(),
+            run_id: run_id.to_string(),
+            scheduled_at,
+            external_ref: Arc::new(Mutex::new(external_ref)),
+            store,
+        }
+    }
+
+    /// Remembers the id of the remote execution (e.g. ADF `runId`) so it can be cancelled later,
+    /// and checkpoints it with the run so a restarted engine can reattach to it.
+    pub async fn set_external_ref(&self, external_ref: impl Into<String>) {
+        let external_ref = external_ref.into();
+        *self.external_ref.lock().unwrap() = Some(external_ref.clone());
+        self.store
+            .set_run_external_ref(&self.run_id, &external_ref)
+            .await;
+    }
+
+    pub fn external_ref(&self) -> Option<String> {
+        self.external_ref.lock().unwrap().clone()
+    }
+
+    /// Stores what the task produced (exit code, captured output, response, ...) with the run.
+    pub async fn set_output(&self, output: &impl Serialize) {
+        if let Ok(output) = serde_json::to_value(output) {
+            self.store.set_run_output(&self.run_id, &output).await;
+        }
+    }
+}
+
+/// Response of an HTTP call a task made, stored with the run.
+#[derive(Debug, Serialize)]
+pub struct ResponseOutput {
+    pub status: u16,
+    pub body: String,
+    /// Whether `body` was cut to its first `max_output_bytes`
+    pub truncated: bool,
+}
+
+impl ResponseOutput {
+    /// Reads the body chunk by chunk, keeping its first `max_bytes` so a large response is
+    /// never held in memory whole. Also returns the bytes kept.
+    pub async fn read(
+        mut response: Response,
+        max_bytes: usize,
+    ) -> reqwest::Result<(Self, Vec<u8>)> {
+        let status = response.status().as_u16();
+        let mut body = Vec::new();
+        let mut truncated = false;
+        while let Some(chunk) = response.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if body.len() > max_bytes {
+                body.truncate(max_bytes);
+                truncated = true;
+                break;
+            }
+        }
+        let output = Self {
+            status,
+            body: String::from_utf8_lossy(&body).into_owned(),
+            truncated,
+        };
+        Ok((output, body))
+    }
+}
+
+/// Adds a configured request body: a string is sent as is, any other JSON value as
+/// `application/json`.
+pub fn with_body(request: RequestBuilder, body: Option<&serde_json::Value>) -> RequestBuilder {
+    match body {
+        Some(serde_json::Value::String(text)) => request.body(text.clone()),
+        Some(json) => request.json(json),
+        None => request,
+    }
+}
+
+/// Captured text as JSON if it is whole and parses, as a string otherwise.
+pub fn json_or_string(text: &str, truncated: bool) -> serde_json::Value {
+    match truncated {
+        false => serde_json::from_str(text).unwrap_or_else(|_| text.into()),
+        true => text.into(),
+    }
+}
+
+/// `reason` followed by the last line of `stderr`, which usually says what went wrong.
+pub fn with_last_line(reason: String, stderr: &str) -> String {
+    match stderr.lines().rev().find(|line| !line.trim().is_empty()) {
+        Some(line) => format!("{}: {}", reason, line.trim()),
+        None => reason,
+    }
+}
+
+impl Display for TaskError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<String> for TaskError {
+    fn from(message: String) -> Self {
+        TaskError::failed(message)
+    }
+}
+
+impl From<&str> for TaskError {
+    fn from(message: &str) -> Self {
+        TaskError::failed(message)
+    }
+}
 
 #[async_trait]
 pub trait TaskHandler: Send + Sync {
     fn task_type(&self) -> &'static str;
-    async fn handle(&self, payload: &TaskPayload) -> Result<(), String>;
+    async fn handle(&self, payload: &TaskPayload, ctx: &TaskContext) -> Result<(), TaskError>;
+
+    /// Called after `handle` was aborted (e.g. on timeout) to release remote resources.
+    async fn cancel(&self, _payload: &TaskPayload, _ctx: &TaskContext) -> Result<(), TaskError> {
+        Ok(())
+    }
+
+    /// Whether [`resume`](Self::resume) can pick up a run started by an earlier process.
+    fn can_resume(&self) -> bool {
+        false
+    }
+
+    /// Continues a run whose process died, or whose previous attempt failed transiently after
+    /// starting it, from the reference in `ctx.external_ref()` until the remote execution ends.
+    async fn resume(&self, _payload: &TaskPayload, _ctx: &TaskContext) -> Result<(), TaskError> {
+        Err(TaskError::failed("Task type cannot resume runs"))
+    }
 }
@@ -0,0 +1,259 @@
+use crate::azure::access_token;
+use crate::domain::task_payload::{HttpAuth, HttpConfig, ResponseAssertion, TaskPayload};
+use crate::task::handler::{ResponseOutput, TaskContext, TaskError, TaskHandler, with_body};
+use crate::utils::{parse_json_path, select};
+use async_trait::async_trait;
+use log::debug;
+use reqwest::{Client, Method, RequestBuilder, StatusCode};
+
+pub struct HttpTask {
+    client: Client,
+}
+
+impl HttpTask {
+    pub fn new() -> Self {
+        Self {
+            client: Client::new(),
+        }
+    }
+
+    async fn request(&self, config: &HttpConfig) -> Result<RequestBuilder, TaskError> {
+        let method = Method::from_bytes(config.method.to_uppercase().as_bytes())
+            .map_err(|_| format!("Invalid HTTP method '{}'", config.method))?;
+        let mut request = self.client.request(method, &config.url);
+        for (name, value) in &config.headers {
+            request = request.header(name, value);
+        }
+        request = with_body(request, config.body.as_ref());
+        Ok(match &config.auth {
+            None => request,
+            Some(HttpAuth::Bearer { token }) => request.bearer_auth(token),
+            Some(HttpAuth::Basic { username, password }) => {
+                request.basic_auth(username, password.as_ref())
+            }
+            Some(HttpAuth::AzureAd { scope }) => {
+                let token = access_token(scope)
+                    .await
+                    .map_err(|e| TaskError::transient(format!("Failed to get token: {}", e)))?;
+                request.bearer_auth(token)
+            }
+        })
+    }
+}
+
+/// Checks the assertion against the response body.
+fn check(assertion: &ResponseAssertion, body: &[u8]) -> Result<(), TaskError> {
+    let steps = parse_json_path(&assertion.path)?;
+    let json: serde_json::Value = serde_json::from_slice(body)
+        .map_err(|e| TaskError::failed(format!("Response is not JSON: {}", e)))?;
+    match (select(&json, &steps), &assertion.equals) {
+        (None, _) => Err(TaskError::failed(format!(
+            "Assertion failed: {} not found",
+            assertion.path
+        ))),
+        (Some(actual), Some(expected)) if actual != expected => Err(TaskError::failed(format!(
+            "Assertion failed: {} is {}, expected {}",
+            assertion.path, actual, expected
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[async_trait]
+impl TaskHandler for HttpTask {
+    fn task_type(&self) -> &'static str {
+        "http"
+    }
+
+    async fn handle(&self, payload: &TaskPayload, ctx: &TaskContext) -> Result<(), TaskError> {
+        let config = payload.as_http().ok_or("Invalid payload for HTTP task")?;
+        debug!(
+            "[{}] {} {} for run {}",
+            ctx.job_id, config.method, config.url, ctx.run_id
+        );
+
+        let response = self
+            .request(config)
+            .await?
+            .send()
+            .await
+            .map_err(|e| TaskError::transient(format!("Request failed: {}", e)))?;
+        let status = response.status();
+        let (output, body) = ResponseOutput::read(response, config.max_output_bytes)
+            .await
+            .map_err(|e| TaskError::transient(format!("Failed to read response: {}", e)))?;
+        let truncated = output.truncated;
+        ctx.set_output(&output).await;
+
+        if !config.is_expected(status.as_u16()) {
+            let message = format!("Unexpected status {}", status);
+            // Overload and server faults tend to pass; anything else will fail the same way again
+            return Err(
+                match status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+                    true => TaskError::transient(message),
+                    false => TaskError::failed(message),
+                },
+            );
+        }
+        match &config.assert {
+            Some(_) if truncated => Err(TaskError::failed(format!(
+                "Response is larger than max_output_bytes ({}), cannot check the assertion",
+                config.max_output_bytes
+            ))),
+            Some(assertion) => check(assertion, &body),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::policy::ErrorClass;
+    use crate::utils::testing::{Reply, Request, TempStore, serve};
+    use serde_json::{Value, json};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    /// Answers one request with `status` and `body`, handing back the request received.
+    async fn stub(status: &str, body: &str) -> (String, JoinHandle<Vec<Request>>) {
+        let (url, served) = serve(vec![Reply::new(status, body)]).await;
+        (format!("{}/check", url), served)
+    }
+
+    /// Result of the task, and the output it stored with the run.
+    async fn run(store: &TempStore, payload: Value) -> (Result<(), TaskError>, Value) {
+        let ctx = store.context(None).await;
+        let payload: TaskPayload =
+            serde_json::from_value(json!({ "task_type": "http", "payload": payload })).unwrap();
+        let result = HttpTask::new().handle(&payload, &ctx).await;
+        let output = store.run(&ctx.run_id).await.output.unwrap_or_default();
+        (result, output)
+    }
+
+    fn class(result: Result<(), TaskError>) -> ErrorClass {
+        result.expect_err("expected the task to fail").class
+    }
+
+    #[tokio::test]
+    async fn sends_auth_headers() {
+        let store = TempStore::new().await;
+        let (url, served) = stub("200 OK", "{}").await;
+        let payload = json!({ "url": url, "auth": { "type": "bearer", "token": "secret" } });
+        run(&store, payload).await.0.unwrap();
+        let requests = served.await.unwrap();
+        assert!(requests[0].head.contains("authorization: bearer secret"));
+
+        let (url, served) = stub("200 OK", "{}").await;
+        let auth = json!({ "type": "basic", "username": "user", "password": "pass" });
+        run(&store, json!({ "url": url, "auth": auth }))
+            .await
+            .0
+            .unwrap();
+        let requests = served.await.unwrap();
+        assert!(
+            requests[0]
+                .head
+                .contains("authorization: basic dxnlcjpwyxnz")
+        );
+    }
+
+    #[tokio::test]
+    async fn expected_status_replaces_any_2xx() {
+        let store = TempStore::new().await;
+        let (url, _) = stub("404 Not Found", r#"{"error":"gone"}"#).await;
+        let (result, output) = run(&store, json!({ "url": url, "expected_status": [404] })).await;
+        result.unwrap();
+        assert_eq!(
+            output,
+            json!({ "status": 404, "body": r#"{"error":"gone"}"#, "truncated": false })
+        );
+
+        let (url, _) = stub("201 Created", "{}").await;
+        let (result, output) = run(&store, json!({ "url": url, "expected_status": [200] })).await;
+        assert_eq!(class(result), ErrorClass::Failed);
+        // The response is kept even when the run fails on it
+        assert_eq!(output["status"], 201);
+    }
+
+    #[tokio::test]
+    async fn overload_and_server_errors_are_transient() {
+        let store = TempStore::new().await;
+        for status in ["503 Service Unavailable", "429 Too Many Requests"] {
+            let (url, _) = stub(status, "{}").await;
+            let (result, _) = run(&store, json!({ "url": url })).await;
+            assert_eq!(class(result), ErrorClass::Transient);
+        }
+        let (url, _) = stub("400 Bad Request", "{}").await;
+        let (result, _) = run(&store, json!({ "url": url })).await;
+        assert_eq!(class(result), ErrorClass::Failed);
+
+        // Nothing listens on a port just released
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        drop(listener);
+        let (result, output) = run(&store, json!({ "url": url })).await;
+        assert_eq!(class(result), ErrorClass::Transient);
+        assert_eq!(output, Value::Null);
+    }
+
+    #[tokio::test]
+    async fn assertion_checks_the_json_body() {
+        let store = TempStore::new().await;
+        let body = r#"{ "result": { "items": [{ "state": "done" }] } }"#;
+        let cases = [
+            (
+                json!({ "path": "$.result.items[0].state", "equals": "done" }),
+                true,
+            ),
+            (json!({ "path": "$.result.items[0].state" }), true),
+            (
+                json!({ "path": "$.result.items[0].state", "equals": "failed" }),
+                false,
+            ),
+            (json!({ "path": "$.result.missing" }), false),
+        ];
+        for (assert, passes) in cases {
+            let (url, _) = stub("200 OK", body).await;
+            let (result, _) = run(&store, json!({ "url": url, "assert": assert })).await;
+            match passes {
+                true => assert!(result.is_ok(), "{}: {:?}", assert, result),
+                false => assert_eq!(class(result), ErrorClass::Failed, "{}", assert),
+            }
+        }
+
+        let (url, _) = stub("200 OK", "not json").await;
+        let (result, _) = run(&store, json!({ "url": url, "assert": { "path": "$.a" } })).await;
+        assert_eq!(class(result), ErrorClass::Failed);
+    }
+
+    #[tokio::test]
+    async fn large_body_is_stored_cut_to_max_output_bytes() {
+        let store = TempStore::new().await;
+        let body = r#"{ "result": { "items": [{ "state": "done" }] } }"#;
+        let (url, _) = stub("200 OK", body).await;
+        let (result, output) = run(&store, json!({ "url": url, "max_output_bytes": 8 })).await;
+        result.unwrap();
+        assert_eq!(
+            output,
+            json!({ "status": 200, "body": &body[..8], "truncated": true })
+        );
+
+        // Cut, the body cannot be checked
+        let (url, _) = stub("200 OK", body).await;
+        let payload =
+            json!({ "url": url, "assert": { "path": "$.result" }, "max_output_bytes": 8 });
+        let (result, output) = run(&store, payload).await;
+        let error = result.unwrap_err();
+        assert_eq!(error.class, ErrorClass::Failed);
+        assert!(error.message.contains("max_output_bytes"));
+        assert_eq!(output["truncated"], true);
+
+        let (url, _) = stub("200 OK", body).await;
+        let payload = json!({ "url": url, "max_output_bytes": body.len() });
+        let (result, output) = run(&store, payload).await;
+        result.unwrap();
+        assert_eq!(output["body"], body);
+        assert_eq!(output["truncated"], false);
+    }
+}
@@ -0,0 +1,433 @@
+use crate::azure::access_token;
+use crate::domain::task_payload::{LogicAppAuth, LogicAppConfig, TaskPayload};
+use crate::task::handler::{ResponseOutput, TaskContext, TaskError, TaskHandler, with_body};
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use reqwest::header::{CONTENT_LENGTH, HeaderMap, LOCATION, RETRY_AFTER};
+use reqwest::{Client, RequestBuilder, Response, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use tokio::time::{Duration, sleep};
+
+/// Header carrying the id of the workflow run a request started or is reporting on.
+const WORKFLOW_RUN_ID: &str = "x-ms-workflow-run-id";
+
+/// Scope of the token for the Azure Resource Manager API, which cancels runs.
+const MANAGEMENT_SCOPE: &str = "https://management.azure.com/.default";
+
+pub struct LogicAppTask {
+    client: Client,
+}
+
+/// Where a workflow run can be found again, kept as the run's `external_ref` so a retry or a
+/// restarted engine polls it instead of triggering the workflow again.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct WorkflowRef {
+    run_id: Option<String>,
+    /// Status URL the workflow asked to poll
+    location: Option<String>,
+}
+
+impl WorkflowRef {
+    fn of(ctx: &TaskContext) -> Self {
+        ctx.external_ref()
+            .and_then(|external_ref| serde_json::from_str(&external_ref).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Final response of the workflow, stored with the run.
+#[derive(Debug, Serialize)]
+struct LogicAppOutput {
+    workflow_run_id: Option<String>,
+    #[serde(flatten)]
+    response: ResponseOutput,
+}
+
+impl LogicAppTask {
+    pub fn new() -> Self {
+        Self {
+            client: Client::new(),
+        }
+    }
+
+    /// Adds a bearer token for `azure_ad` auth, unless `url` is signed by the workflow itself
+    /// (as the status URLs it hands out are) or points to another host than `endpoint`.
+    async fn authorize(
+        request: RequestBuilder,
+        config: &LogicAppConfig,
+        url: &Url,
+    ) -> Result<RequestBuilder, TaskError> {
+        let endpoint =
+            Url::parse(&config.endpoint).map_err(|e| format!("Invalid endpoint: {}", e))?;
+        let signed = url.query_pairs().any(|(name, _)| name == "sig");
+        if config.auth_type != LogicAppAuth::AzureAd
+            || signed
+            || url.host_str() != endpoint.host_str()
+        {
+            return Ok(request);
+        }
+        let token = access_token(&config.scope)
+            .await
+            .map_err(|e| TaskError::transient(format!("Failed to get token: {}", e)))?;
+        Ok(request.bearer_auth(token))
+    }
+
+    async fn trigger(&self, config: &LogicAppConfig) -> Result<Response, TaskError> {
+        let url = Url::parse(&config.endpoint).map_err(|e| format!("Invalid endpoint: {}", e))?;
+        let mut request = self.client.post(url.clone());
+        for (name, value) in &config.headers {
+            request = request.header(name, value);
+        }
+        request = with_body(request, config.body.as_ref());
+        Self::authorize(request, config, &url)
+            .await?
+            .send()
+            .await
+            .map_err(|e| TaskError::transient(format!("Failed to trigger workflow: {}", e)))
+    }
+
+    /// Follows `202 Accepted` responses to their `Location` until the workflow answers with
+    /// anything else.
+    async fn wait_for(
+        &self,
+        mut response: Response,
+        config: &LogicAppConfig,
+        ctx: &TaskContext,
+    ) -> Result<Response, TaskError> {
+        loop {
+            // A 202 without a location means the workflow was started but answers nothing more
+            let location = match response.status() {
+                StatusCode::ACCEPTED => response
+                    .headers()
+                    .get(LOCATION)
+                    .and_then(|location| location.to_str().ok())
+                    .map(|location| response.url().join(location))
+                    .transpose()
+                    .map_err(|e| format!("Invalid status location: {}", e))?,
+                _ => None,
+            };
+            let known = WorkflowRef::of(ctx);
+            let current = WorkflowRef {
+                run_id: workflow_run_id(response.headers()).or(known.run_id.clone()),
+                // The last status URL is kept once the workflow answers, in case reading fails
+                location: location
+                    .as_ref()
+                    .map(Url::to_string)
+                    .or(known.location.clone()),
+            };
+            if current.run_id != known.run_id
+                && let Some(run_id) = &current.run_id
+            {
+                info!(
+                    "[{}] Workflow run {} for run {}",
+                    ctx.job_id, run_id, ctx.run_id
+                );
+            }
+            if current != known
+                && let Ok(external_ref) = serde_json::to_string(&current)
+            {
+                ctx.set_external_ref(external_ref).await;
+            }
+
+            let Some(location) = location else {
+                return Ok(response);
+            };
+            let delay = retry_after(response.headers());
+            debug!(
+                "[{}] Workflow still running, checking again in {}s",
+                ctx.job_id,
+                delay.as_secs()
+            );
+            sleep(delay).await;
+            response = self.poll(config, &location).await?;
+        }
+    }
+
+    async fn poll(&self, config: &LogicAppConfig, location: &Url) -> Result<Response, TaskError> {
+        let request = self.client.get(location.clone());
+        Self::authorize(request, config, location)
+            .await?
+            .send()
+            .await
+            .map_err(|e| TaskError::transient(format!("Failed to get workflow status: {}", e)))
+    }
+
+    /// Records the workflow's final response and turns it into the run's outcome.
+    async fn finish(
+        response: Response,
+        config: &LogicAppConfig,
+        ctx: &TaskContext,
+    ) -> Result<(), TaskError> {
+        let status = response.status();
+        let (response, body) = ResponseOutput::read(response, config.max_output_bytes)
+            .await
+            .map_err(|e| TaskError::transient(format!("Failed to read response: {}", e)))?;
+
+        let output = LogicAppOutput {
+            workflow_run_id: WorkflowRef::of(ctx).run_id,
+            response,
+        };
+        ctx.set_output(&output).await;
+        debug!("[{}] Workflow answered {}", ctx.job_id, status);
+
+        if status.is_success() {
+            return Ok(());
+        }
+        let message = match error_message(&body) {
+            Some(message) => format!("Workflow answered {}: {}", status, message),
+            None => format!("Workflow answered {}", status),
+        };
+        // Without a run id the trigger was refused, and overload or server faults tend to pass;
+        // once the workflow ran, trying again would run it again
+        let refused = output.workflow_run_id.is_none();
+        Err(
+            match refused && (status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS) {
+                true => TaskError::transient(message),
+                false => TaskError::failed(message),
+            },
+        )
+    }
+}
+
+fn workflow_run_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(WORKFLOW_RUN_ID)
+        .and_then(|id| id.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Wait asked for by `Retry-After` (in seconds), 5 seconds if it does not say.
+fn retry_after(headers: &HeaderMap) -> Duration {
+    let secs = headers
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .unwrap_or(5);
+    Duration::from_secs(secs.max(1))
+}
+
+/// `error.message` of a workflow error response, if it is one.
+fn error_message(body: &[u8]) -> Option<String> {
+    let json: serde_json::Value = serde_json::from_slice(body).ok()?;
+    json["error"]["message"].as_str().map(str::to_string)
+}
+
+#[async_trait]
+impl TaskHandler for LogicAppTask {
+    fn task_type(&self) -> &'static str {
+        "logic_app"
+    }
+
+    async fn handle(&self, payload: &TaskPayload, ctx: &TaskContext) -> Result<(), TaskError> {
+        let config = payload
+            .as_logic_app()
+            .ok_or("Invalid payload for Logic App task")?;
+
+        let response = self.trigger(config).await?;
+        let response = self.wait_for(response, config, ctx).await?;
+        Self::finish(response, config, ctx).await
+    }
+
+    async fn cancel(&self, payload: &TaskPayload, ctx: &TaskContext) -> Result<(), TaskError> {
+        let config = payload
+            .as_logic_app()
+            .ok_or("Invalid payload for Logic App task")?;
+        let Some(run_id) = WorkflowRef::of(ctx).run_id else {
+            return Ok(());
+        };
+        let Some(workflow) = &config.workflow else {
+            warn!(
+                "[{}] Cannot cancel workflow run {} without a workflow resource",
+                ctx.job_id, run_id
+            );
+            return Ok(());
+        };
+
+        info!("Cancelling workflow run {}", run_id);
+        let token = access_token(MANAGEMENT_SCOPE)
+            .await
+            .map_err(|e| TaskError::transient(format!("Failed to get token: {}", e)))?;
+        let url = format!(
+            "https://management.azure.com/subscriptions/{}/resourceGroups/{}/providers/Microsoft.Logic/workflows/{}/runs/{}/cancel?api-version=2016-06-01",
+            workflow.subscription_id, workflow.resource_group, workflow.name, run_id
+        );
+        let response = self
+            .client
+            .post(&url)
+            .bearer_auth(token)
+            .header(CONTENT_LENGTH, "0")
+            .send()
+            .await
+            .map_err(|e| TaskError::transient(format!("Failed to cancel workflow run: {}", e)))?;
+        if response.status().is_success() {
+            return Ok(());
+        }
+        let status = response.status();
+        let body = response.bytes().await.unwrap_or_default();
+        Err(TaskError::transient(match error_message(&body) {
+            Some(message) => format!("Failed to cancel workflow run: {}", message),
+            None => format!("Failed to cancel workflow run: {}", status),
+        }))
+    }
+
+    fn can_resume(&self) -> bool {
+        true
+    }
+
+    async fn resume(&self, payload: &TaskPayload, ctx: &TaskContext) -> Result<(), TaskError> {
+        let config = payload
+            .as_logic_app()
+            .ok_or("Invalid payload for Logic App task")?;
+        let location = WorkflowRef::of(ctx)
+            .location
+            .ok_or("No workflow status to resume polling")?;
+        let location =
+            Url::parse(&location).map_err(|e| format!("Invalid status location: {}", e))?;
+
+        info!(
+            "[{}] Resuming polling of workflow status {} for run {}",
+            ctx.job_id,
+            location.path(),
+            ctx.run_id
+        );
+        let response = self.poll(config, &location).await?;
+        let response = self.wait_for(response, config, ctx).await?;
+        Self::finish(response, config, ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::policy::ErrorClass;
+    use crate::utils::testing::{Reply, TempStore, serve};
+    use serde_json::json;
+
+    const STATUS: &str = "/status/1?api-version=2016-10-01&sig=s2";
+
+    fn payload(url: &str) -> TaskPayload {
+        let endpoint = format!("{}/workflows/w/triggers/manual/paths/invoke?sig=s1", url);
+        serde_json::from_value(json!({
+            "task_type": "logic_app",
+            "payload": { "endpoint": endpoint, "body": { "day": 1 } },
+        }))
+        .unwrap()
+    }
+
+    fn accepted(run_id: Option<&str>) -> Reply {
+        let reply = Reply::new("202 Accepted", "")
+            .header("Location", STATUS)
+            .header("Retry-After", "1");
+        match run_id {
+            Some(run_id) => reply.header(WORKFLOW_RUN_ID, run_id),
+            None => reply,
+        }
+    }
+
+    #[tokio::test]
+    async fn polls_the_status_location_until_the_workflow_answers() {
+        let replies = vec![
+            accepted(Some("08585")),
+            // Status responses need not repeat the run id
+            accepted(None),
+            Reply::new("200 OK", r#"{"rows":3}"#),
+        ];
+        let (url, served) = serve(replies).await;
+        let store = TempStore::new().await;
+        let ctx = store.context(None).await;
+
+        LogicAppTask::new()
+            .handle(&payload(&url), &ctx)
+            .await
+            .unwrap();
+
+        let requests = served.await.unwrap();
+        assert!(
+            requests[0]
+                .head
+                .starts_with("post /workflows/w/triggers/manual/paths/invoke?sig=s1 ")
+        );
+        assert_eq!(requests[0].body, r#"{"day":1}"#);
+        for request in &requests[1..] {
+            assert!(request.head.starts_with(&format!("get {} ", STATUS)));
+        }
+
+        let run = store.run(&ctx.run_id).await;
+        let output = run.output.unwrap();
+        assert_eq!(output["workflow_run_id"], "08585");
+        assert_eq!(output["status"], 200);
+        assert_eq!(output["body"], r#"{"rows":3}"#);
+        let external_ref: WorkflowRef = serde_json::from_str(&run.external_ref.unwrap()).unwrap();
+        assert_eq!(external_ref.run_id.as_deref(), Some("08585"));
+        assert_eq!(external_ref.location, Some(format!("{}{}", url, STATUS)));
+    }
+
+    #[tokio::test]
+    async fn resume_polls_the_stored_location_without_triggering_again() {
+        let (url, served) = serve(vec![Reply::new("200 OK", "{}")]).await;
+        let store = TempStore::new().await;
+        let stored = WorkflowRef {
+            run_id: Some("08585".to_string()),
+            location: Some(format!("{}{}", url, STATUS)),
+        };
+        let ctx = store
+            .context(Some(&serde_json::to_string(&stored).unwrap()))
+            .await;
+
+        assert!(LogicAppTask::new().can_resume());
+        LogicAppTask::new()
+            .resume(&payload(&url), &ctx)
+            .await
+            .unwrap();
+
+        let requests = served.await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].head.starts_with(&format!("get {} ", STATUS)));
+        let output = store.run(&ctx.run_id).await.output.unwrap();
+        assert_eq!(output["workflow_run_id"], "08585");
+    }
+
+    #[tokio::test]
+    async fn only_a_refused_trigger_is_transient() {
+        let busy = r#"{"error":{"code":"Busy","message":"throttled"}}"#;
+        let failed = r#"{"error":{"code":"ActionFailed","message":"Step 'Copy' failed"}}"#;
+        let cases = [
+            // Refused before a workflow run started
+            (
+                vec![Reply::new("503 Service Unavailable", busy)],
+                ErrorClass::Transient,
+                "Workflow answered 503 Service Unavailable: throttled",
+            ),
+            (
+                vec![Reply::new("429 Too Many Requests", busy)],
+                ErrorClass::Transient,
+                "Workflow answered 429 Too Many Requests: throttled",
+            ),
+            (
+                vec![Reply::new("400 Bad Request", "{}")],
+                ErrorClass::Failed,
+                "Workflow answered 400 Bad Request",
+            ),
+            // The workflow ran and failed; running it again is up to the retry policy
+            (
+                vec![
+                    accepted(Some("08585")),
+                    Reply::new("500 Internal Server Error", failed)
+                        .header(WORKFLOW_RUN_ID, "08585"),
+                ],
+                ErrorClass::Failed,
+                "Workflow answered 500 Internal Server Error: Step 'Copy' failed",
+            ),
+        ];
+        for (replies, class, message) in cases {
+            let (url, _) = serve(replies).await;
+            let store = TempStore::new().await;
+            let ctx = store.context(None).await;
+            let error = LogicAppTask::new()
+                .handle(&payload(&url), &ctx)
+                .await
+                .unwrap_err();
+            assert_eq!((error.class, error.message.as_str()), (class, message));
+        }
+    }
+}
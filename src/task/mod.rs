@@ -1,4 +1,9 @@
 pub mod adf;
 pub mod handler;
+pub mod http;
+pub mod logic_app;
 pub mod print;
 pub mod registry;
+pub mod shell;
+pub mod stepfn;
+pub mod wasm;
@@ -1,8 +1,7 @@
 use crate::domain::task_payload::TaskPayload;
-use crate::task::handler::TaskHandler;
+use crate::task::handler::{TaskContext, TaskError, TaskHandler};
 use async_trait::async_trait;
 use log::debug;
-use std::thread::sleep;
 
 pub struct PrintTask;
 
@@ -12,7 +11,7 @@ impl TaskHandler for PrintTask {
         "print"
     }
 
-    async fn handle(&self, payload: &TaskPayload) -> Result<(), String> {
+    async fn handle(&self, _payload: &TaskPayload, _ctx: &TaskContext) -> Result<(), TaskError> {
         debug!("Printing task");
         tokio::time::sleep(std::time::Duration::from_secs(10)).await;
         Ok(())
@@ -17,7 +17,7 @@ impl TaskRegistry {
         reg
     }
     pub fn print_all_handlers(&self) {
-        for (key, handler) in &self.handlers {
+        for key in self.handlers.keys() {
             debug!("Task Type: {}", key);
         }
     }
@@ -0,0 +1,366 @@
+use crate::domain::task_payload::{ShellCommandConfig, TaskPayload};
+use crate::task::handler::{TaskContext, TaskError, TaskHandler, with_last_line};
+use async_trait::async_trait;
+use log::{debug, info};
+use serde::Serialize;
+use std::process::Stdio;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+use tokio::time::{Duration, sleep};
+use tokio_util::sync::CancellationToken;
+
+/// Program and flag a `command` line is handed to.
+const SHELL: (&str, &str) = if cfg!(windows) {
+    ("cmd", "/C")
+} else {
+    ("sh", "-c")
+};
+
+/// How long output is still read once the command exited. Processes it left in the
+/// background may hold its stdout or stderr open long after.
+const OUTPUT_DRAIN: Duration = Duration::from_secs(1);
+
+pub struct ShellTask;
+
+/// Exit status and captured output of a command, stored with the run.
+#[derive(Debug, Serialize)]
+struct ShellOutput {
+    /// `None` if the process was killed by a signal
+    exit_code: Option<i32>,
+    stdout: String,
+    stderr: String,
+    /// Whether stdout or stderr was cut to its last `max_output_bytes`
+    truncated: bool,
+}
+
+/// Kills the process group of a command abandoned before it exited (timeout, cancellation,
+/// shutdown) or that left processes holding its output open, so processes started by `sh -c`
+/// do not outlive the run.
+struct ProcessGroup(Option<u32>);
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(pid) = self.0 {
+            // The command leads a group of its own, so this reaches it and its children only
+            unsafe {
+                libc::kill(-(pid as i32), libc::SIGKILL);
+            }
+        }
+    }
+}
+
+impl ShellTask {
+    fn command(config: &ShellCommandConfig) -> Result<Command, TaskError> {
+        let mut command = match config.argv.split_first() {
+            Some((program, args)) => {
+                let mut command = Command::new(program);
+                command.args(args);
+                command
+            }
+            None => {
+                let line = config
+                    .command
+                    .as_deref()
+                    .ok_or("Shell task needs a command or argv")?;
+                let mut command = Command::new(SHELL.0);
+                command.args([SHELL.1, line]);
+                command
+            }
+        };
+        if let Some(dir) = &config.working_dir {
+            command.current_dir(dir);
+        }
+        command
+            .envs(&config.env)
+            .stdin(match config.stdin {
+                Some(_) => Stdio::piped(),
+                None => Stdio::null(),
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        #[cfg(unix)]
+        command.process_group(0);
+        Ok(command)
+    }
+}
+
+/// What [`capture`] kept of a stream.
+#[derive(Debug, Default, PartialEq)]
+struct Captured {
+    text: String,
+    /// Whether earlier bytes were dropped to keep the last `limit`
+    truncated: bool,
+    /// Whether reading stopped before the end, the stream still being held open
+    abandoned: bool,
+}
+
+/// Reads `reader` to the end, keeping only its last `limit` bytes. Stops [`OUTPUT_DRAIN`]
+/// after `exited` is cancelled if the end has not come by then.
+async fn capture(
+    reader: Option<impl AsyncRead + Unpin>,
+    limit: usize,
+    exited: &CancellationToken,
+) -> std::io::Result<Captured> {
+    let Some(mut reader) = reader else {
+        return Ok(Captured::default());
+    };
+    let drained = async {
+        exited.cancelled().await;
+        sleep(OUTPUT_DRAIN).await;
+    };
+    tokio::pin!(drained);
+
+    let mut kept = Vec::new();
+    let mut truncated = false;
+    let mut abandoned = false;
+    let mut chunk = [0u8; 8192];
+    loop {
+        let n = tokio::select! {
+            n = reader.read(&mut chunk) => n?,
+            _ = &mut drained => {
+                abandoned = true;
+                break;
+            }
+        };
+        if n == 0 {
+            break;
+        }
+        kept.extend_from_slice(&chunk[..n]);
+        if kept.len() > limit {
+            kept.drain(..kept.len() - limit);
+            truncated = true;
+        }
+    }
+    Ok(Captured {
+        text: String::from_utf8_lossy(&kept).into_owned(),
+        truncated,
+        abandoned,
+    })
+}
+
+#[async_trait]
+impl TaskHandler for ShellTask {
+    fn task_type(&self) -> &'static str {
+        "shell_command"
+    }
+
+    async fn handle(&self, payload: &TaskPayload, ctx: &TaskContext) -> Result<(), TaskError> {
+        let config = payload
+            .as_shell_command()
+            .ok_or("Invalid payload for shell task")?;
+
+        let mut child = Self::command(config)?
+            .spawn()
+            .map_err(|e| TaskError::failed(format!("Failed to start command: {}", e)))?;
+        let mut group = ProcessGroup(child.id());
+        info!(
+            "[{}] Started process {} for run {}",
+            ctx.job_id,
+            child.id().unwrap_or_default(),
+            ctx.run_id
+        );
+
+        let stdin = child.stdin.take();
+        let feed = async {
+            if let (Some(mut pipe), Some(text)) = (stdin, &config.stdin) {
+                // A command that exits without reading its input closes the pipe early
+                if let Err(e) = pipe.write_all(text.as_bytes()).await {
+                    debug!("[{}] Writing stdin stopped: {}", ctx.job_id, e);
+                }
+            }
+        };
+        let (stdout, stderr) = (child.stdout.take(), child.stderr.take());
+        let exited = CancellationToken::new();
+        let wait = async {
+            let status = child.wait().await;
+            exited.cancel();
+            status
+        };
+        let (_, stdout, stderr, status) = tokio::join!(
+            feed,
+            capture(stdout, config.max_output_bytes, &exited),
+            capture(stderr, config.max_output_bytes, &exited),
+            wait
+        );
+        let status =
+            status.map_err(|e| TaskError::failed(format!("Failed to wait for command: {}", e)))?;
+
+        let read_error =
+            |e: std::io::Error| TaskError::failed(format!("Failed to read output: {}", e));
+        let stdout = stdout.map_err(read_error)?;
+        let stderr = stderr.map_err(read_error)?;
+        if stdout.abandoned || stderr.abandoned {
+            info!(
+                "[{}] Killing processes the command left holding its output",
+                ctx.job_id
+            );
+        } else {
+            group.0 = None;
+        }
+        drop(group);
+        let output = ShellOutput {
+            exit_code: status.code(),
+            stdout: stdout.text,
+            stderr: stderr.text,
+            truncated: stdout.truncated || stderr.truncated,
+        };
+        ctx.set_output(&output).await;
+        debug!("[{}] Command ended with {}", ctx.job_id, status);
+
+        let reason = match status.code() {
+            Some(code) if config.is_success(code) => return Ok(()),
+            Some(code) => format!("Exited with code {}", code),
+            None => format!("Terminated: {}", status),
+        };
+        Err(TaskError::failed(with_last_line(reason, &output.stderr)))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::utils::testing::TempStore;
+    use serde_json::{Value, json};
+    use std::time::Instant;
+
+    async fn run(store: &TempStore, config: Value) -> (Result<(), TaskError>, Value) {
+        let ctx = store.context(None).await;
+        let payload: TaskPayload =
+            serde_json::from_value(json!({ "task_type": "shell_command", "payload": config }))
+                .unwrap();
+        let result = ShellTask.handle(&payload, &ctx).await;
+        let output = store.run(&ctx.run_id).await.output.unwrap_or_default();
+        (result, output)
+    }
+
+    /// Whether process `pid` is gone, a zombie waiting to be reaped counting as gone.
+    fn is_gone(pid: &str) -> bool {
+        match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+            Ok(stat) => stat
+                .rsplit(')')
+                .next()
+                .unwrap()
+                .trim_start()
+                .starts_with('Z'),
+            Err(_) => true,
+        }
+    }
+
+    #[tokio::test]
+    async fn argv_is_run_without_a_shell() {
+        let store = TempStore::new().await;
+        let (result, output) = run(&store, json!({ "argv": ["echo", "$((1 + 2))", "a  b"] })).await;
+        result.unwrap();
+        assert_eq!(output["stdout"], "$((1 + 2)) a  b\n");
+
+        let (result, output) = run(&store, json!({ "command": "echo $((1 + 2)) a  b" })).await;
+        result.unwrap();
+        assert_eq!(output["stdout"], "3 a b\n");
+    }
+
+    #[tokio::test]
+    async fn env_and_stdin_reach_the_command() {
+        let store = TempStore::new().await;
+        let config = json!({
+            "command": "cat; echo \"$GREETING\"",
+            "env": { "GREETING": "hello" },
+            "stdin": "input\n",
+        });
+        let (result, output) = run(&store, config).await;
+        result.unwrap();
+        assert_eq!(output["stdout"], "input\nhello\n");
+
+        let (result, output) = run(&store, json!({ "command": "cat" })).await;
+        result.unwrap();
+        assert_eq!(output["stdout"], "");
+    }
+
+    #[tokio::test]
+    async fn success_exit_codes_replace_zero() {
+        let store = TempStore::new().await;
+        let (result, output) = run(
+            &store,
+            json!({ "command": "exit 3", "success_exit_codes": [0, 3] }),
+        )
+        .await;
+        result.unwrap();
+        assert_eq!(output["exit_code"], 3);
+
+        let (result, _) = run(
+            &store,
+            json!({ "command": "echo boom >&2; exit 0", "success_exit_codes": [3] }),
+        )
+        .await;
+        assert_eq!(result.unwrap_err().message, "Exited with code 0: boom");
+
+        let (result, output) = run(&store, json!({ "command": "echo oops >&2; exit 4" })).await;
+        assert_eq!(result.unwrap_err().message, "Exited with code 4: oops");
+        assert_eq!(output["stderr"], "oops\n");
+    }
+
+    #[tokio::test]
+    async fn capture_keeps_the_tail() {
+        let exited = CancellationToken::new();
+        let captured = capture(Some(&b"abcdefgh"[..]), 3, &exited).await.unwrap();
+        assert_eq!(
+            captured,
+            Captured {
+                text: "fgh".to_string(),
+                truncated: true,
+                abandoned: false,
+            }
+        );
+        let captured = capture(Some(&b"abc"[..]), 3, &exited).await.unwrap();
+        assert!(!captured.truncated);
+
+        let store = TempStore::new().await;
+        let config = json!({ "command": "seq 1000", "max_output_bytes": 9 });
+        let (result, output) = run(&store, config).await;
+        result.unwrap();
+        assert_eq!(output["stdout"], "999\n1000\n");
+        assert_eq!(output["truncated"], true);
+    }
+
+    #[tokio::test]
+    async fn background_process_holding_the_output_is_killed() {
+        let store = TempStore::new().await;
+        let pid_file =
+            std::env::temp_dir().join(format!("shell-task-{}.pid", uuid::Uuid::new_v4()));
+        let command = format!("sleep 60 & echo $! > {}; echo started", pid_file.display());
+        let started = Instant::now();
+        let (result, output) = run(&store, json!({ "command": command })).await;
+        result.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(output["stdout"], "started\n");
+
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        let _ = std::fs::remove_file(&pid_file);
+        sleep(Duration::from_millis(200)).await;
+        assert!(is_gone(pid.trim()));
+    }
+
+    #[tokio::test]
+    async fn abandoned_run_kills_the_process_group() {
+        let store = TempStore::new().await;
+        let pid_file =
+            std::env::temp_dir().join(format!("shell-task-{}.pid", uuid::Uuid::new_v4()));
+        let command = format!(
+            "sleep 60 > /dev/null 2>&1 & echo $! > {}; wait",
+            pid_file.display()
+        );
+        // The runner drops the handler's future on timeout and cancellation
+        let timed_out = tokio::time::timeout(
+            Duration::from_millis(500),
+            run(&store, json!({ "command": command })),
+        )
+        .await;
+        assert!(timed_out.is_err());
+
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        let _ = std::fs::remove_file(&pid_file);
+        sleep(Duration::from_millis(200)).await;
+        assert!(is_gone(pid.trim()));
+    }
+}
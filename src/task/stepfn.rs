@@ -0,0 +1,320 @@
+use crate::aws::{StepFnClient, StepFnError, StepFnExecution, StepFnExecutionStatus};
+use crate::domain::task_payload::{AwsStepFnConfig, TaskPayload};
+use crate::task::handler::{TaskContext, TaskError, TaskHandler, json_or_string};
+use async_trait::async_trait;
+use log::{debug, info};
+use serde_json::json;
+
+pub struct StepFnTask;
+
+/// Name of the execution for the run's fire. Every attempt of the fire uses it, so starting
+/// again after a start whose response was lost returns the execution already running.
+fn execution_name(ctx: &TaskContext) -> String {
+    format!(
+        "{}-{}",
+        ctx.job_id,
+        ctx.scheduled_at.format("%Y%m%dT%H%M%S%3fZ")
+    )
+}
+
+fn already_exists(error: &(dyn std::error::Error + 'static)) -> bool {
+    error
+        .downcast_ref::<StepFnError>()
+        .is_some_and(|error| error.kind == "ExecutionAlreadyExists")
+}
+
+impl StepFnTask {
+    fn client(config: &AwsStepFnConfig) -> Result<StepFnClient, TaskError> {
+        let region = config.region().ok_or("No region for the state machine")?;
+        StepFnClient::new(region, config.endpoint.as_deref())
+            .map_err(|e| TaskError::failed(e.to_string()))
+    }
+
+    /// Starts the execution named `name`; `None` if that name belongs to one that has ended.
+    async fn start(
+        client: &StepFnClient,
+        config: &AwsStepFnConfig,
+        name: &str,
+    ) -> Result<Option<String>, TaskError> {
+        match client
+            .start_execution(&config.arn, name, &config.input)
+            .await
+        {
+            Ok(execution_arn) => Ok(Some(execution_arn)),
+            Err(e) if already_exists(e.as_ref()) => Ok(None),
+            Err(e) => Err(TaskError::transient(e.to_string())),
+        }
+    }
+
+    /// Polls the execution until it reaches a terminal status, then stores its outcome with
+    /// the run.
+    async fn wait_for(
+        client: &StepFnClient,
+        execution_arn: &str,
+        ctx: &TaskContext,
+    ) -> Result<(), TaskError> {
+        loop {
+            let execution = client
+                .describe_execution(execution_arn)
+                .await
+                .map_err(|e| TaskError::transient(e.to_string()))?;
+
+            let reason = || match (&execution.error, &execution.cause) {
+                (Some(error), Some(cause)) => format!("{}: {}", error, cause),
+                (Some(message), None) | (None, Some(message)) => message.clone(),
+                (None, None) => format!("Execution {}", execution.status),
+            };
+            let outcome = match execution.status {
+                StepFnExecutionStatus::Succeeded => Ok(()),
+                StepFnExecutionStatus::Failed | StepFnExecutionStatus::PendingRedrive => {
+                    Err(TaskError::failed(reason()))
+                }
+                StepFnExecutionStatus::TimedOut => Err(TaskError::timeout(reason())),
+                StepFnExecutionStatus::Aborted => Err(TaskError::failed(reason())),
+                StepFnExecutionStatus::Running | StepFnExecutionStatus::Unknown => {
+                    debug!("Execution status: {}", execution.status);
+                    tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                    continue;
+                }
+            };
+
+            debug!("Execution ended with {}", execution.status);
+            Self::record(&execution, ctx).await;
+            return outcome;
+        }
+    }
+
+    async fn record(execution: &StepFnExecution, ctx: &TaskContext) {
+        let output = execution
+            .output
+            .as_ref()
+            .map(|output| json_or_string(output, false));
+        ctx.set_output(&json!({
+            "execution_arn": execution.execution_arn,
+            "status": execution.status.to_string(),
+            "output": output,
+            "error": execution.error,
+            "cause": execution.cause,
+        }))
+        .await;
+    }
+}
+
+#[async_trait]
+impl TaskHandler for StepFnTask {
+    fn task_type(&self) -> &'static str {
+        "aws_stepfn"
+    }
+
+    async fn handle(&self, payload: &TaskPayload, ctx: &TaskContext) -> Result<(), TaskError> {
+        let config = payload
+            .as_stepfn()
+            .ok_or("Invalid payload for Step Functions task")?;
+        let client = Self::client(config)?;
+
+        let name = execution_name(ctx);
+        let execution_arn = match Self::start(&client, config, &name).await? {
+            Some(execution_arn) => execution_arn,
+            // An earlier attempt's execution has ended, e.g. failed and is being retried;
+            // names of ended executions cannot be reused
+            None => {
+                let run = ctx.run_id.split('-').next().unwrap_or_default();
+                let name = format!("{}-{}", name, run);
+                Self::start(&client, config, &name)
+                    .await?
+                    .ok_or_else(|| format!("Execution {} already exists", name))?
+            }
+        };
+        ctx.set_external_ref(&execution_arn).await;
+        info!(
+            "[{}] Started execution {} for run {}",
+            ctx.job_id, execution_arn, ctx.run_id
+        );
+
+        Self::wait_for(&client, &execution_arn, ctx).await
+    }
+
+    async fn cancel(&self, payload: &TaskPayload, ctx: &TaskContext) -> Result<(), TaskError> {
+        let config = payload
+            .as_stepfn()
+            .ok_or("Invalid payload for Step Functions task")?;
+        let Some(execution_arn) = ctx.external_ref() else {
+            return Ok(());
+        };
+
+        info!("Stopping execution {}", execution_arn);
+        Self::client(config)?
+            .stop_execution(&execution_arn, &format!("Run {} was cancelled", ctx.run_id))
+            .await
+            .map_err(|e| TaskError::transient(e.to_string()))
+    }
+
+    fn can_resume(&self) -> bool {
+        true
+    }
+
+    async fn resume(&self, payload: &TaskPayload, ctx: &TaskContext) -> Result<(), TaskError> {
+        let config = payload
+            .as_stepfn()
+            .ok_or("Invalid payload for Step Functions task")?;
+        let execution_arn = ctx.external_ref().ok_or("No execution to resume polling")?;
+
+        info!(
+            "[{}] Resuming polling of execution {} for run {}",
+            ctx.job_id, execution_arn, ctx.run_id
+        );
+        Self::wait_for(&Self::client(config)?, &execution_arn, ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::model::JobRun;
+    use crate::domain::policy::ErrorClass;
+    use crate::job::store::JobStore;
+    use crate::utils::testing::{Reply, Request, TempStore, serve};
+    use chrono::{TimeZone, Utc};
+    use std::sync::{Arc, Once};
+
+    const STATE_MACHINE: &str = "arn:aws:states:us-east-1:123456789012:stateMachine:load";
+    const EXECUTION: &str = "arn:aws:states:us-east-1:123456789012:execution:load:run";
+
+    fn credentials() {
+        static SET: Once = Once::new();
+        // SAFETY: set once with the same values, and no other test reads or writes them
+        SET.call_once(|| unsafe {
+            std::env::set_var("AWS_ACCESS_KEY_ID", "AKIDEXAMPLE");
+            std::env::set_var("AWS_SECRET_ACCESS_KEY", "secret");
+        });
+    }
+
+    fn started() -> Reply {
+        Reply::new("200 OK", &json!({ "executionArn": EXECUTION }).to_string())
+    }
+
+    fn described(status: &str) -> Reply {
+        let body = json!({
+            "executionArn": EXECUTION,
+            "status": status,
+            "output": "{\"rows\":3}",
+            "error": "States.TaskFailed",
+            "cause": "Lambda failed",
+        });
+        Reply::new("200 OK", &body.to_string())
+    }
+
+    /// Runs the task against `replies` for a fire due at 10:30, as the run `run_id`.
+    async fn run(
+        replies: Vec<Reply>,
+        run_id_prefix: &str,
+    ) -> (Result<(), TaskError>, JobRun, Vec<Request>) {
+        credentials();
+        let (url, served) = serve(replies).await;
+        let store = TempStore::new().await;
+        let scheduled_at = Utc.with_ymd_and_hms(2026, 10, 18, 10, 30, 0).unwrap();
+        let mut run = JobRun::start("job", scheduled_at, "node", 1);
+        run.id = format!("{}-{}", run_id_prefix, run.id);
+        store.insert_run(&run).await;
+        let ctx = TaskContext::new("job", &run.id, scheduled_at, None, store.clone() as Arc<_>);
+        let payload: TaskPayload = serde_json::from_value(json!({
+            "task_type": "aws_stepfn",
+            "payload": { "arn": STATE_MACHINE, "input": { "day": 1 }, "endpoint": url },
+        }))
+        .unwrap();
+
+        let result = StepFnTask.handle(&payload, &ctx).await;
+        let run = store.get_run_by_id(&run.id).await.unwrap().unwrap();
+        (result, run, served.await.unwrap())
+    }
+
+    fn target(request: &Request) -> &str {
+        request
+            .head
+            .lines()
+            .find_map(|line| line.strip_prefix("x-amz-target: awsstepfunctions."))
+            .unwrap_or_default()
+    }
+
+    fn body(request: &Request) -> serde_json::Value {
+        serde_json::from_str(&request.body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn starts_the_fire_execution_and_polls_until_it_ends() {
+        let replies = vec![started(), described("RUNNING"), described("SUCCEEDED")];
+        let (result, run, requests) = run(replies, "first").await;
+        assert!(result.is_ok(), "{:?}", result);
+
+        let targets: Vec<&str> = requests.iter().map(target).collect();
+        assert_eq!(
+            targets,
+            ["startexecution", "describeexecution", "describeexecution"]
+        );
+        let start = body(&requests[0]);
+        assert_eq!(start["stateMachineArn"], STATE_MACHINE);
+        assert_eq!(start["name"], "job-20261018T103000000Z");
+        assert_eq!(start["input"], "{\"day\":1}");
+        assert!(requests[0].head.contains("authorization: aws4-hmac-sha256"));
+        assert_eq!(body(&requests[1])["executionArn"], EXECUTION);
+
+        assert_eq!(run.external_ref.as_deref(), Some(EXECUTION));
+        let output = run.output.unwrap();
+        assert_eq!(output["status"], "SUCCEEDED");
+        assert_eq!(output["output"], json!({ "rows": 3 }));
+    }
+
+    #[tokio::test]
+    async fn every_attempt_of_a_fire_starts_the_same_execution() {
+        let (_, _, first) = run(vec![started(), described("SUCCEEDED")], "first").await;
+        let (_, _, second) = run(vec![started(), described("SUCCEEDED")], "second").await;
+        assert_eq!(body(&first[0])["name"], body(&second[0])["name"]);
+    }
+
+    #[tokio::test]
+    async fn ended_execution_of_the_fire_is_started_under_a_new_name() {
+        let taken = json!({
+            "__type": "com.amazonaws.states#ExecutionAlreadyExists",
+            "message": "Execution Already Exists",
+        });
+        let replies = vec![
+            Reply::new("400 Bad Request", &taken.to_string()),
+            started(),
+            described("SUCCEEDED"),
+        ];
+        let (result, _, requests) = run(replies, "retry").await;
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(body(&requests[0])["name"], "job-20261018T103000000Z");
+        assert_eq!(body(&requests[1])["name"], "job-20261018T103000000Z-retry");
+    }
+
+    #[tokio::test]
+    async fn terminal_statuses_map_to_outcomes() {
+        let cases = [
+            ("FAILED", ErrorClass::Failed),
+            ("TIMED_OUT", ErrorClass::Timeout),
+            ("ABORTED", ErrorClass::Failed),
+        ];
+        for (status, class) in cases {
+            let (result, run, _) = run(vec![started(), described(status)], "first").await;
+            let error = result.expect_err(status);
+            assert_eq!(error.class, class, "{}", status);
+            assert_eq!(error.message, "States.TaskFailed: Lambda failed");
+            assert_eq!(run.output.unwrap()["status"], status);
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_start_is_transient() {
+        let throttled = json!({
+            "__type": "com.amazonaws.states#ThrottlingException",
+            "message": "Rate exceeded",
+        });
+        let replies = vec![Reply::new("400 Bad Request", &throttled.to_string())];
+        let (result, run, _) = run(replies, "first").await;
+        let error = result.unwrap_err();
+        assert_eq!(error.class, ErrorClass::Transient);
+        assert!(error.message.contains("ThrottlingException"));
+        assert_eq!(run.external_ref, None);
+    }
+}
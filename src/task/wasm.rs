@@ -0,0 +1,142 @@
+use crate::domain::task_payload::TaskPayload;
+use crate::task::handler::{TaskContext, TaskError, TaskHandler, json_or_string, with_last_line};
+use crate::wasm::{WasmExit, WasmRuntime};
+use async_trait::async_trait;
+use serde::Serialize;
+
+/// Exit code a module uses to ask for a retry (`EX_TEMPFAIL` from `sysexits.h`).
+const EXIT_TEMPFAIL: i32 = 75;
+
+pub struct WasmTask {
+    runtime: WasmRuntime,
+}
+
+/// Outcome and captured output of a module, stored with the run.
+#[derive(Debug, Serialize)]
+struct WasmOutput {
+    /// `None` if the module trapped or ran out of fuel
+    exit_code: Option<i32>,
+    /// What the module wrote to stdout, parsed when it is whole and JSON
+    result: serde_json::Value,
+    stderr: String,
+    /// Whether stdout or stderr was cut to its last `max_output_bytes`
+    truncated: bool,
+    fuel_consumed: u64,
+}
+
+impl WasmTask {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            runtime: WasmRuntime::new()?,
+        })
+    }
+}
+
+#[async_trait]
+impl TaskHandler for WasmTask {
+    fn task_type(&self) -> &'static str {
+        "wasm"
+    }
+
+    async fn handle(&self, payload: &TaskPayload, ctx: &TaskContext) -> Result<(), TaskError> {
+        let config = payload.as_wasm().ok_or("Invalid payload for WASM task")?;
+
+        let run = self
+            .runtime
+            .run(config, &ctx.job_id)
+            .await
+            .map_err(|e| TaskError::failed(format!("Failed to start module: {}", e)))?;
+        let output = WasmOutput {
+            exit_code: match run.exit {
+                WasmExit::Exited(code) => Some(code),
+                _ => None,
+            },
+            result: json_or_string(&run.stdout, run.truncated),
+            stderr: run.stderr,
+            truncated: run.truncated,
+            fuel_consumed: run.fuel_consumed,
+        };
+        ctx.set_output(&output).await;
+
+        let reason = match &run.exit {
+            WasmExit::Exited(0) => return Ok(()),
+            WasmExit::Exited(code) => format!("Exited with code {}", code),
+            WasmExit::OutOfFuel => format!("Ran out of fuel ({} units)", config.fuel),
+            WasmExit::Trapped(trap) => format!("Trapped: {}", trap),
+        };
+        let reason = with_last_line(reason, &output.stderr);
+        match run.exit {
+            WasmExit::Exited(EXIT_TEMPFAIL) => Err(TaskError::transient(reason)),
+            _ => Err(TaskError::failed(reason)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::policy::ErrorClass;
+    use crate::utils::testing::{TempFile, TempStore};
+    use serde_json::{Value, json};
+
+    /// Module that logs `len` bytes from address 0, then exits with `code`.
+    fn exiting(code: i32, len: i32) -> String {
+        format!(
+            r#"(module
+                (import "wasi_snapshot_preview1" "proc_exit" (func $exit (param i32)))
+                (import "nixscheduler" "log" (func $log (param i32 i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "_start")
+                    (call $log (i32.const 3) (i32.const 0) (i32.const {len}))
+                    (call $exit (i32.const {code}))))"#
+        )
+    }
+
+    async fn run(wat: &str, config: Value) -> (Result<(), TaskError>, Value) {
+        let store = TempStore::new().await;
+        let ctx = store.context(None).await;
+        let module = TempFile::new("wat", wat);
+        let mut payload = json!({ "module": module.path() });
+        crate::utils::merge_patch(&mut payload, &config);
+        let payload: TaskPayload =
+            serde_json::from_value(json!({ "task_type": "wasm", "payload": payload })).unwrap();
+        let result = WasmTask::new().unwrap().handle(&payload, &ctx).await;
+        (result, store.run(&ctx.run_id).await.output.unwrap())
+    }
+
+    #[tokio::test]
+    async fn exit_code_decides_the_outcome() {
+        let (result, output) = run(&exiting(0, 0), json!({})).await;
+        result.unwrap();
+        assert_eq!(output["exit_code"], 0);
+
+        let (result, _) = run(&exiting(75, 0), json!({})).await;
+        assert_eq!(result.unwrap_err().class, ErrorClass::Transient);
+
+        let (result, _) = run(&exiting(3, 0), json!({})).await;
+        let error = result.unwrap_err();
+        assert_eq!(error.class, ErrorClass::Failed);
+        assert_eq!(error.message, "Exited with code 3");
+    }
+
+    #[tokio::test]
+    async fn running_out_of_fuel_fails_without_retry() {
+        let wat = r#"(module (memory (export "memory") 1) (func (export "_start") (loop (br 0))))"#;
+        let (result, output) = run(wat, json!({ "fuel": 5000 })).await;
+        let error = result.unwrap_err();
+        assert_eq!(error.class, ErrorClass::Failed);
+        assert_eq!(error.message, "Ran out of fuel (5000 units)");
+        assert_eq!(output["exit_code"], Value::Null);
+        assert_eq!(output["fuel_consumed"], 5000);
+    }
+
+    #[tokio::test]
+    async fn log_message_is_cut_to_max_output_bytes() {
+        // Past the end of memory if read in full
+        let (result, _) = run(&exiting(0, i32::MAX), json!({ "max_output_bytes": 1024 })).await;
+        result.unwrap();
+
+        let (result, _) = run(&exiting(0, 70_000), json!({ "max_output_bytes": 70_000 })).await;
+        assert!(result.unwrap_err().message.contains("out of bounds"));
+    }
+}
@@ -0,0 +1,76 @@
+use serde_json::Value;
+
+/// Applies an RFC 7396 JSON merge patch: objects are merged recursively, `null` removes a key
+/// and any other value replaces the target.
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let target = target.as_object_mut().unwrap();
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// One step of a JSONPath: a member name or an array index.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathStep {
+    Key(String),
+    Index(usize),
+}
+
+/// Parses the member and index subset of JSONPath, e.g. `$.items[0].name` or `$['a key']`.
+pub fn parse_json_path(path: &str) -> Result<Vec<PathStep>, String> {
+    let mut rest = path
+        .trim()
+        .strip_prefix('$')
+        .ok_or_else(|| format!("JSONPath '{}' must start with '$'", path))?;
+    let mut steps = Vec::new();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            if end == 0 {
+                return Err(format!("empty member name in JSONPath '{}'", path));
+            }
+            steps.push(PathStep::Key(after[..end].to_string()));
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after
+                .find(']')
+                .ok_or_else(|| format!("unclosed '[' in JSONPath '{}'", path))?;
+            let inner = after[..end].trim();
+            let quoted = ['\'', '"']
+                .iter()
+                .find_map(|q| inner.strip_prefix(*q)?.strip_suffix(*q));
+            steps.push(match quoted {
+                Some(key) => PathStep::Key(key.to_string()),
+                None => PathStep::Index(inner.parse().map_err(|_| {
+                    format!(
+                        "'{}' is not an index or a quoted name in JSONPath '{}'",
+                        inner, path
+                    )
+                })?),
+            });
+            rest = &after[end + 1..];
+        } else {
+            return Err(format!("unexpected '{}' in JSONPath '{}'", rest, path));
+        }
+    }
+    Ok(steps)
+}
+
+/// The value `steps` lead to, if there is one.
+pub fn select<'a>(value: &'a Value, steps: &[PathStep]) -> Option<&'a Value> {
+    steps.iter().try_fold(value, |value, step| match step {
+        PathStep::Key(key) => value.get(key),
+        PathStep::Index(index) => value.get(index),
+    })
+}
@@ -1,3 +1,11 @@
 mod hash;
+mod json;
+mod redact;
+mod signal;
+#[cfg(test)]
+pub mod testing;
 
-pub use hash::hash_job_id;
+pub use hash::{hash_job_id, stable_hash_job_id};
+pub use json::{merge_patch, parse_json_path, select};
+pub use redact::redact_signatures;
+pub use signal::shutdown_signal;
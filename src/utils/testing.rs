@@ -0,0 +1,343 @@
+use crate::config::{AppConfig, ShardMode};
+use crate::domain::model::{Job, JobRaw, JobRun, JobSchedule, JobStatus};
+use crate::domain::task_payload::TaskPayload;
+use crate::engine::runner::{FireFinished, JobRunner};
+use crate::job::store::{JobStore, SqliteJobStore};
+use crate::task::handler::{TaskContext, TaskError, TaskHandler};
+use crate::task::registry::TaskRegistry;
+use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::{HashMap, VecDeque};
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Local node `node` with room for 4 runs, no timeouts and a one second shutdown grace.
+pub fn config() -> AppConfig {
+    AppConfig {
+        shard_mode: ShardMode::Local,
+        database_url: String::new(),
+        tick_interval_secs: 1,
+        node_id: "node".to_string(),
+        task_timeouts: HashMap::new(),
+        misfire_threshold_secs: 60,
+        max_in_flight: 4,
+        task_concurrency: HashMap::new(),
+        shutdown_grace_secs: 1,
+        default_jitter_secs: 0,
+    }
+}
+
+/// Row of a `print` job firing daily at 02:00 UTC.
+pub fn job_raw(id: &str) -> JobRaw {
+    JobRaw {
+        id: id.to_string(),
+        name: id.to_string(),
+        schedule: JobSchedule::Cron {
+            expression: "0 0 2 * * *".to_string(),
+        },
+        task_type: "print".to_string(),
+        payload: r#"{"message":"hi"}"#.to_string(),
+        last_run: None,
+        status: JobStatus::Scheduled,
+        message: None,
+        retry_policy: None,
+        timeout_secs: None,
+        overlap_policy: Default::default(),
+        misfire_policy: Default::default(),
+        timezone: None,
+        priority: 0,
+        depends_on: Vec::new(),
+        on_success: Vec::new(),
+        on_failure: Vec::new(),
+        calendar_id: None,
+        calendar: None,
+        jitter_secs: None,
+        dependencies_met_at: None,
+    }
+}
+
+pub fn job(id: &str) -> Job {
+    job_raw(id).to_job().unwrap()
+}
+
+/// SQLite store in a file of its own, removed when dropped.
+pub struct TempStore {
+    store: Arc<SqliteJobStore>,
+    path: PathBuf,
+}
+
+impl TempStore {
+    pub async fn new() -> Self {
+        let path = std::env::temp_dir().join(format!("nixscheduler-{}.db", uuid::Uuid::new_v4()));
+        let store = SqliteJobStore::new(&format!("sqlite://{}?mode=rwc", path.display())).await;
+        Self {
+            store: Arc::new(store),
+            path,
+        }
+    }
+}
+
+impl TempStore {
+    /// Context of a new run of the job `job`, recorded so what the handler stores can be read
+    /// back with [`TempStore::run`].
+    pub async fn context(&self, external_ref: Option<&str>) -> TaskContext {
+        let run = JobRun::start("job", Utc::now(), "node", 1);
+        self.store.insert_run(&run).await;
+        TaskContext::new(
+            "job",
+            &run.id,
+            run.scheduled_at,
+            external_ref.map(str::to_string),
+            self.store.clone(),
+        )
+    }
+
+    pub async fn run(&self, run_id: &str) -> JobRun {
+        self.store.get_run_by_id(run_id).await.unwrap().unwrap()
+    }
+
+    /// Runs of `job_id`, oldest attempt first.
+    pub async fn runs(&self, job_id: &str) -> Vec<JobRun> {
+        let mut runs = self.store.list_runs(job_id, 1000).await.unwrap();
+        runs.reverse();
+        runs
+    }
+
+    /// Runner over this store with `handler` in place of the `print` task, and the receiver
+    /// of the fires it finishes.
+    pub fn runner(
+        &self,
+        config: AppConfig,
+        handler: Scripted,
+    ) -> (Arc<JobRunner>, mpsc::UnboundedReceiver<FireFinished>) {
+        let mut registry = TaskRegistry::new();
+        registry.register(handler);
+        let (finished, receiver) = mpsc::unbounded_channel();
+        let runner = JobRunner::new(
+            Arc::new(config),
+            self.store.clone(),
+            Arc::new(registry),
+            finished,
+        );
+        (Arc::new(runner), receiver)
+    }
+}
+
+impl Deref for TempStore {
+    type Target = Arc<SqliteJobStore>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.store
+    }
+}
+
+impl Drop for TempStore {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// What a [`Scripted`] handler does when called.
+pub enum Step {
+    Succeed,
+    Fail(TaskError),
+    /// Succeeds after a while
+    Sleep(Duration),
+    /// Records `external_ref` as the remote run it started, then loses track of it
+    Start(&'static str),
+}
+
+/// Call a [`Scripted`] handler received.
+#[derive(Debug, PartialEq)]
+pub enum Call {
+    Handle,
+    /// With the external reference it was asked to resume
+    Resume(Option<String>),
+    Cancel,
+}
+
+/// `print` handler taking its steps in order, succeeding once they run out.
+#[derive(Clone, Default)]
+pub struct Scripted {
+    steps: Arc<Mutex<VecDeque<Step>>>,
+    calls: Arc<Mutex<Vec<Call>>>,
+    resumable: bool,
+}
+
+impl Scripted {
+    pub fn new(steps: Vec<Step>) -> Self {
+        Self {
+            steps: Arc::new(Mutex::new(steps.into())),
+            ..Self::default()
+        }
+    }
+
+    /// Same handler, able to resume runs.
+    pub fn resumable(mut self) -> Self {
+        self.resumable = true;
+        self
+    }
+
+    pub fn calls(&self) -> Vec<Call> {
+        std::mem::take(&mut self.calls.lock().unwrap())
+    }
+
+    async fn step(&self, call: Call, ctx: &TaskContext) -> Result<(), TaskError> {
+        self.calls.lock().unwrap().push(call);
+        let step = self.steps.lock().unwrap().pop_front();
+        match step.unwrap_or(Step::Succeed) {
+            Step::Succeed => Ok(()),
+            Step::Fail(error) => Err(error),
+            Step::Sleep(duration) => {
+                tokio::time::sleep(duration).await;
+                Ok(())
+            }
+            Step::Start(external_ref) => {
+                ctx.set_external_ref(external_ref.to_string()).await;
+                Err(TaskError::transient("Lost track of the remote run"))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl TaskHandler for Scripted {
+    fn task_type(&self) -> &'static str {
+        "print"
+    }
+
+    async fn handle(&self, _payload: &TaskPayload, ctx: &TaskContext) -> Result<(), TaskError> {
+        self.step(Call::Handle, ctx).await
+    }
+
+    async fn cancel(&self, _payload: &TaskPayload, _ctx: &TaskContext) -> Result<(), TaskError> {
+        self.calls.lock().unwrap().push(Call::Cancel);
+        Ok(())
+    }
+
+    fn can_resume(&self) -> bool {
+        self.resumable
+    }
+
+    async fn resume(&self, _payload: &TaskPayload, ctx: &TaskContext) -> Result<(), TaskError> {
+        self.step(Call::Resume(ctx.external_ref()), ctx).await
+    }
+}
+
+/// File in the temporary directory, removed when dropped.
+pub struct TempFile {
+    path: PathBuf,
+}
+
+impl TempFile {
+    pub fn new(extension: &str, contents: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "nixscheduler-{}.{}",
+            uuid::Uuid::new_v4(),
+            extension
+        ));
+        std::fs::write(&path, contents).unwrap();
+        Self { path }
+    }
+
+    pub fn path(&self) -> String {
+        self.path.display().to_string()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Scripted answer of a [`serve`]d stub.
+pub struct Reply {
+    status: String,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Reply {
+    /// `status` is the whole status, e.g. `200 OK`.
+    pub fn new(status: &str, body: &str) -> Self {
+        Self {
+            status: status.to_string(),
+            headers: Vec::new(),
+            body: body.to_string(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// Request received by a [`serve`]d stub.
+#[derive(Debug)]
+pub struct Request {
+    /// Request line and headers, lowercased
+    pub head: String,
+    pub body: String,
+}
+
+/// Answers one request per connection with `replies`, in order, on `http://127.0.0.1:<port>`.
+/// Hands back the requests once every reply was sent.
+pub async fn serve(replies: Vec<Reply>) -> (String, JoinHandle<Vec<Request>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let served = tokio::spawn(async move {
+        let mut requests = Vec::new();
+        for reply in replies {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            let mut buf = [0; 4096];
+            let head_end = loop {
+                if let Some(end) = received.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break end + 4;
+                }
+                let n = socket.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break received.len();
+                }
+                received.extend_from_slice(&buf[..n]);
+            };
+            let head = String::from_utf8_lossy(&received[..head_end]).to_lowercase();
+            let length = head
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length:"))
+                .and_then(|length| length.trim().parse::<usize>().ok())
+                .unwrap_or(0);
+            while received.len() < head_end + length {
+                let n = socket.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                received.extend_from_slice(&buf[..n]);
+            }
+            let body = String::from_utf8_lossy(&received[head_end..]).into_owned();
+
+            let mut response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+                reply.status,
+                reply.body.len()
+            );
+            for (name, value) in &reply.headers {
+                response.push_str(&format!("{}: {}\r\n", name, value));
+            }
+            response.push_str("\r\n");
+            response.push_str(&reply.body);
+            socket.write_all(response.as_bytes()).await.unwrap();
+            requests.push(Request { head, body });
+        }
+        requests
+    });
+    (url, served)
+}
@@ -0,0 +1,295 @@
+use crate::domain::task_payload::parse_allowed_host;
+use crate::wasm::runtime::WasmState;
+use log::{Level, log};
+use reqwest::{Client, Method, Url};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use wasmtime::{Caller, Linker, Memory};
+
+/// Module the host functions are imported from.
+pub const HOST_MODULE: &str = "nixscheduler";
+
+/// Bytes of an HTTP response body handed to a module, the first ones if there are more.
+const MAX_RESPONSE_BYTES: usize = 1024 * 1024;
+
+/// All a module can reach beyond WASI: the engine's log, and the hosts it is allowed to call.
+pub struct HostApi {
+    pub job_id: String,
+    pub allowed_hosts: Vec<String>,
+    /// Bytes of a `log` message written, the first ones if there are more
+    pub max_log_bytes: usize,
+    pub client: Client,
+    /// Last HTTP response, until `http_response` copies it into the module's memory
+    pub response: Vec<u8>,
+}
+
+/// Request a module passes to `http_request`, as JSON.
+#[derive(Debug, Deserialize)]
+struct HttpRequest {
+    #[serde(default = "default_method")]
+    method: String,
+    url: String,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    #[serde(default)]
+    body: Option<String>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+/// What `http_request` hands back, as JSON.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum HttpResponse {
+    Received {
+        status: u16,
+        headers: HashMap<String, String>,
+        body: String,
+        /// Whether `body` was cut to its first `MAX_RESPONSE_BYTES`
+        truncated: bool,
+    },
+    Refused {
+        error: String,
+    },
+}
+
+impl HostApi {
+    fn allows(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        matches!(url.scheme(), "http" | "https")
+            && self
+                .allowed_hosts
+                .iter()
+                .filter_map(|entry| parse_allowed_host(entry).ok())
+                .any(|(allowed, port)| {
+                    let allowed = allowed.to_ascii_lowercase();
+                    let host_matches = match allowed.strip_prefix("*.") {
+                        Some(domain) => host
+                            .strip_suffix(domain)
+                            .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+                        None => host == allowed,
+                    };
+                    host_matches
+                        && match port {
+                            Some(port) => url.port_or_known_default() == Some(port),
+                            // `Url` leaves out a port that is the scheme's default
+                            None => url.port().is_none(),
+                        }
+                })
+    }
+
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+        let url = Url::parse(&request.url).map_err(|e| format!("invalid url: {}", e))?;
+        if !self.allows(&url) {
+            return Err(format!(
+                "{} is not an allowed host",
+                url.host_str().unwrap_or("url")
+            ));
+        }
+        let method = Method::from_bytes(request.method.to_uppercase().as_bytes())
+            .map_err(|_| format!("invalid HTTP method '{}'", request.method))?;
+
+        let mut builder = self.client.request(method, url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+        let mut response = builder.send().await.map_err(|e| e.to_string())?;
+
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let mut body = Vec::new();
+        let mut truncated = false;
+        while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+            body.extend_from_slice(&chunk);
+            if body.len() > MAX_RESPONSE_BYTES {
+                body.truncate(MAX_RESPONSE_BYTES);
+                truncated = true;
+                break;
+            }
+        }
+        Ok(HttpResponse::Received {
+            status,
+            headers,
+            body: String::from_utf8_lossy(&body).into_owned(),
+            truncated,
+        })
+    }
+}
+
+/// Level for the numbers `log` takes: 1 error, 2 warn, 3 info, 4 debug, 5 trace.
+fn level(level: i32) -> Level {
+    match level {
+        1 => Level::Error,
+        2 => Level::Warn,
+        4 => Level::Debug,
+        5 => Level::Trace,
+        _ => Level::Info,
+    }
+}
+
+fn memory(caller: &mut Caller<'_, WasmState>) -> wasmtime::Result<Memory> {
+    caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+        .ok_or_else(|| wasmtime::Error::msg("module exports no memory"))
+}
+
+/// Copies `len` bytes at `ptr` out of the module's memory.
+fn read(caller: &mut Caller<'_, WasmState>, ptr: i32, len: i32) -> wasmtime::Result<Vec<u8>> {
+    let memory = memory(caller)?;
+    let start = ptr as u32 as usize;
+    memory
+        .data(&caller)
+        .get(start..start + len as u32 as usize)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| wasmtime::Error::msg("pointer out of bounds"))
+}
+
+/// Adds the host functions, imported from [`HOST_MODULE`]:
+///
+/// - `log(level, ptr, len)` writes a UTF-8 message to the engine's log, cut to its first
+///   `max_log_bytes`.
+/// - `http_request(ptr, len) -> len` sends the JSON request `{ method, url, headers, body }` if
+///   the host is allowed, and returns the length of the JSON response
+///   `{ status, headers, body, truncated }` or `{ error }`.
+/// - `http_response(ptr, len) -> len` copies that response into the module's memory and
+///   returns how many bytes it copied.
+pub fn add_to_linker(linker: &mut Linker<WasmState>) -> wasmtime::Result<()> {
+    linker.func_wrap(
+        HOST_MODULE,
+        "log",
+        |mut caller: Caller<'_, WasmState>, lvl: i32, ptr: i32, len: i32| {
+            let len = (len as u32).min(caller.data().host.max_log_bytes as u32);
+            let message = read(&mut caller, ptr, len as i32)?;
+            log!(
+                level(lvl),
+                "[{}] {}",
+                caller.data().host.job_id,
+                String::from_utf8_lossy(&message)
+            );
+            Ok(())
+        },
+    )?;
+    linker.func_wrap_async(
+        HOST_MODULE,
+        "http_request",
+        |mut caller: Caller<'_, WasmState>, (ptr, len): (i32, i32)| {
+            Box::new(async move {
+                let request = read(&mut caller, ptr, len)?;
+                let response = match serde_json::from_slice::<HttpRequest>(&request) {
+                    Ok(request) => caller.data().host.send(request).await,
+                    Err(e) => Err(format!("invalid request: {}", e)),
+                }
+                .unwrap_or_else(|error| HttpResponse::Refused { error });
+                let response = serde_json::to_vec(&response)?;
+                let len = response.len() as i32;
+                caller.data_mut().host.response = response;
+                Ok(len)
+            })
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "http_response",
+        |mut caller: Caller<'_, WasmState>, ptr: i32, len: i32| {
+            let response = std::mem::take(&mut caller.data_mut().host.response);
+            let copied = response.len().min(len as u32 as usize);
+            memory(&mut caller)?.write(&mut caller, ptr as u32 as usize, &response[..copied])?;
+            Ok(copied as i32)
+        },
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api(allowed_hosts: &[&str]) -> HostApi {
+        HostApi {
+            job_id: "job".to_string(),
+            allowed_hosts: allowed_hosts.iter().map(|host| host.to_string()).collect(),
+            max_log_bytes: 1024,
+            client: Client::new(),
+            response: Vec::new(),
+        }
+    }
+
+    fn allows(api: &HostApi, url: &str) -> bool {
+        api.allows(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn exact_host_is_allowed_on_its_default_port_only() {
+        let api = api(&["API.example.com"]);
+        assert!(allows(&api, "https://api.example.com/v1"));
+        assert!(allows(&api, "http://api.example.com:80/"));
+        assert!(!allows(&api, "https://api.example.com:8443/"));
+        assert!(!allows(&api, "https://sub.api.example.com/"));
+        assert!(!allows(&api, "https://example.com/"));
+        assert!(!allows(&api, "ftp://api.example.com/"));
+    }
+
+    #[test]
+    fn wildcard_covers_subdomains_only() {
+        let api = api(&["*.example.com"]);
+        assert!(allows(&api, "https://a.example.com/"));
+        assert!(allows(&api, "https://a.b.example.com/"));
+        assert!(!allows(&api, "https://example.com/"));
+        assert!(!allows(&api, "https://badexample.com/"));
+        assert!(!allows(&api, "https://a.example.com:8080/"));
+    }
+
+    #[test]
+    fn host_with_port_is_allowed_on_that_port() {
+        let api = api(&["api.example.com:8443", "*.example.org:8080", "localhost:80"]);
+        assert!(allows(&api, "https://api.example.com:8443/"));
+        assert!(!allows(&api, "https://api.example.com/"));
+        assert!(allows(&api, "http://a.example.org:8080/"));
+        assert!(!allows(&api, "http://a.example.org:8081/"));
+        assert!(allows(&api, "http://localhost/"));
+        assert!(!allows(&api, "https://localhost/"));
+    }
+
+    #[test]
+    fn hosts_not_listed_are_refused() {
+        assert!(!allows(&api(&[]), "https://api.example.com/"));
+        let api = api(&["api.example.com", "[::1]:8080"]);
+        assert!(!allows(&api, "https://api.example.com.evil.com/"));
+        assert!(!allows(&api, "https://127.0.0.1/"));
+        assert!(allows(&api, "http://[::1]:8080/"));
+        assert!(!allows(&api, "http://[::1]/"));
+    }
+
+    #[test]
+    fn entries_are_hosts_with_an_optional_port() {
+        assert_eq!(parse_allowed_host("example.com"), Ok(("example.com", None)));
+        assert_eq!(
+            parse_allowed_host("example.com:8443"),
+            Ok(("example.com", Some(8443)))
+        );
+        assert_eq!(parse_allowed_host("[::1]"), Ok(("[::1]", None)));
+        assert_eq!(parse_allowed_host("[::1]:80"), Ok(("[::1]", Some(80))));
+        for entry in [
+            "",
+            ":80",
+            "example.com:",
+            "example.com:http",
+            "a:b:80",
+            "example.com/x",
+        ] {
+            assert!(parse_allowed_host(entry).is_err(), "{}", entry);
+        }
+    }
+}
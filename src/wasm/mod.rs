@@ -0,0 +1,5 @@
+mod host;
+mod pipe;
+mod runtime;
+
+pub use runtime::*;
@@ -0,0 +1,73 @@
+use bytes::Bytes;
+use std::sync::{Arc, Mutex};
+use wasmtime_wasi::{OutputStream, Pollable, StdoutStream, StreamError};
+
+/// Stdout or stderr of a module, keeping only the last `limit` bytes written so a chatty
+/// module is cut short in what is kept rather than failing its writes.
+#[derive(Clone)]
+pub struct CapturePipe {
+    limit: usize,
+    captured: Arc<Mutex<Captured>>,
+}
+
+#[derive(Default)]
+struct Captured {
+    bytes: Vec<u8>,
+    truncated: bool,
+}
+
+impl CapturePipe {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            captured: Arc::default(),
+        }
+    }
+
+    /// What was kept, and whether some was dropped.
+    pub fn contents(&self) -> (String, bool) {
+        let captured = self.captured.lock().unwrap();
+        (
+            String::from_utf8_lossy(&captured.bytes).into_owned(),
+            captured.truncated,
+        )
+    }
+}
+
+#[wasmtime_wasi::async_trait]
+impl OutputStream for CapturePipe {
+    fn write(&mut self, bytes: Bytes) -> Result<(), StreamError> {
+        let mut captured = self.captured.lock().unwrap();
+        captured.bytes.extend_from_slice(&bytes);
+        if captured.bytes.len() > self.limit {
+            let excess = captured.bytes.len() - self.limit;
+            captured.bytes.drain(..excess);
+            captured.truncated = true;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), StreamError> {
+        Ok(())
+    }
+
+    fn check_write(&mut self) -> Result<usize, StreamError> {
+        // Always ready, whatever the size
+        Ok(usize::MAX)
+    }
+}
+
+#[wasmtime_wasi::async_trait]
+impl Pollable for CapturePipe {
+    async fn ready(&mut self) {}
+}
+
+impl StdoutStream for CapturePipe {
+    fn stream(&self) -> Box<dyn OutputStream> {
+        Box::new(self.clone())
+    }
+
+    fn isatty(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,301 @@
+use crate::domain::task_payload::WasmConfig;
+use crate::wasm::host::{self, HostApi};
+use crate::wasm::pipe::CapturePipe;
+use log::debug;
+use reqwest::Client;
+use reqwest::redirect::Policy;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use wasmtime::{Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, Trap};
+use wasmtime_wasi::pipe::MemoryInputPipe;
+use wasmtime_wasi::preview1::{self, WasiP1Ctx};
+use wasmtime_wasi::{I32Exit, WasiCtxBuilder};
+
+/// Fuel a module burns between handing control back to the executor, so that a long
+/// computation can still be timed out or cancelled.
+const YIELD_INTERVAL: u64 = 100_000;
+
+/// Bound on each HTTP call a module makes.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Bounds on what a module may create besides linear memory. A command module is a single
+/// instance, which rarely needs more than one table.
+const MAX_INSTANCES: usize = 1;
+const MAX_TABLES: usize = 4;
+const MAX_TABLE_ELEMENTS: usize = 100_000;
+
+/// What the store of a run of `config` may grow to.
+fn limits(config: &WasmConfig) -> StoreLimits {
+    StoreLimitsBuilder::new()
+        .memory_size(config.max_memory_bytes)
+        .instances(MAX_INSTANCES)
+        .tables(MAX_TABLES)
+        .table_elements(MAX_TABLE_ELEMENTS)
+        .trap_on_grow_failure(true)
+        .build()
+}
+
+pub struct WasmState {
+    wasi: WasiP1Ctx,
+    limits: StoreLimits,
+    pub host: HostApi,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WasmExit {
+    /// `_start` returned (code 0) or the module called `proc_exit`
+    Exited(i32),
+    OutOfFuel,
+    Trapped(String),
+}
+
+/// How a module run ended and what it wrote.
+#[derive(Debug)]
+pub struct WasmRun {
+    pub exit: WasmExit,
+    pub stdout: String,
+    pub stderr: String,
+    /// Whether stdout or stderr was cut to its last `max_output_bytes`
+    pub truncated: bool,
+    pub fuel_consumed: u64,
+}
+
+/// Runs WASI command modules, each in a store of its own with no files, no sockets and no
+/// inherited environment.
+pub struct WasmRuntime {
+    engine: Engine,
+    linker: Linker<WasmState>,
+    client: Client,
+    /// Compiled modules by path, with the modification time of the file they were compiled from
+    modules: Mutex<HashMap<PathBuf, (SystemTime, Module)>>,
+}
+
+impl WasmRuntime {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let mut config = Config::new();
+        config.async_support(true).consume_fuel(true);
+        let engine = Engine::new(&config)?;
+
+        let mut linker = Linker::new(&engine);
+        preview1::add_to_linker_async(&mut linker, |state: &mut WasmState| &mut state.wasi)?;
+        host::add_to_linker(&mut linker)?;
+
+        // Redirects could lead outside the allowed hosts
+        let client = Client::builder()
+            .redirect(Policy::none())
+            .timeout(HTTP_TIMEOUT)
+            .build()?;
+        Ok(Self {
+            engine,
+            linker,
+            client,
+            modules: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Compiled module at `path`, compiled again only when the file has changed since.
+    async fn module(&self, path: &str) -> Result<Module, Box<dyn std::error::Error>> {
+        let path = PathBuf::from(path);
+        let modified = std::fs::metadata(&path)
+            .and_then(|meta| meta.modified())
+            .ok();
+        if let Some(modified) = modified
+            && let Some((compiled_at, module)) = self.modules.lock().unwrap().get(&path)
+            && *compiled_at == modified
+        {
+            return Ok(module.clone());
+        }
+
+        let engine = self.engine.clone();
+        let file = path.clone();
+        // Compiling is CPU-bound and can take a while for a large module
+        let module = tokio::task::spawn_blocking(move || Module::from_file(&engine, file))
+            .await?
+            .map_err(|e| format!("{}: {:#}", path.display(), e))?;
+        if let Some(modified) = modified {
+            debug!("Compiled {}", path.display());
+            self.modules
+                .lock()
+                .unwrap()
+                .insert(path, (modified, module.clone()));
+        }
+        Ok(module)
+    }
+
+    /// Runs the module's `_start` with `input` on stdin until it exits, traps or runs out of
+    /// fuel. Errors are for a module that cannot be loaded or started.
+    pub async fn run(
+        &self,
+        config: &WasmConfig,
+        job_id: &str,
+    ) -> Result<WasmRun, Box<dyn std::error::Error>> {
+        let module = self.module(&config.module).await?;
+
+        let stdout = CapturePipe::new(config.max_output_bytes);
+        let stderr = CapturePipe::new(config.max_output_bytes);
+        let args: Vec<&str> = std::iter::once(config.module.as_str())
+            .chain(config.args.iter().map(String::as_str))
+            .collect();
+        let env: Vec<(&str, &str)> = config
+            .env
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        let wasi = WasiCtxBuilder::new()
+            .stdin(MemoryInputPipe::new(config.input.to_string()))
+            .stdout(stdout.clone())
+            .stderr(stderr.clone())
+            .args(&args)
+            .envs(&env)
+            .build_p1();
+        let state = WasmState {
+            wasi,
+            limits: limits(config),
+            host: HostApi {
+                job_id: job_id.to_string(),
+                allowed_hosts: config.allowed_hosts.clone(),
+                max_log_bytes: config.max_output_bytes,
+                client: self.client.clone(),
+                response: Vec::new(),
+            },
+        };
+
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(config.fuel)?;
+        store.fuel_async_yield_interval(Some(YIELD_INTERVAL))?;
+        let instance = self
+            .linker
+            .instantiate_async(&mut store, &module)
+            .await
+            .map_err(|e| format!("{:#}", e))?;
+        let start = instance
+            .get_typed_func::<(), ()>(&mut store, "_start")
+            .map_err(|e| format!("{:#}", e))?;
+
+        let result = start.call_async(&mut store, ()).await;
+        let exit = match result {
+            Ok(()) => WasmExit::Exited(0),
+            Err(e) => match (e.downcast_ref::<I32Exit>(), e.downcast_ref::<Trap>()) {
+                (Some(exit), _) => WasmExit::Exited(exit.0),
+                (_, Some(Trap::OutOfFuel)) => WasmExit::OutOfFuel,
+                (_, Some(trap)) => WasmExit::Trapped(trap.to_string()),
+                // e.g. memory growth past the limit
+                (None, None) => WasmExit::Trapped(e.root_cause().to_string()),
+            },
+        };
+        let fuel_consumed = config.fuel - store.get_fuel().unwrap_or_default();
+        debug!("[{}] Module ended with {:?}", job_id, exit);
+
+        let (stdout, stdout_truncated) = stdout.contents();
+        let (stderr, stderr_truncated) = stderr.contents();
+        Ok(WasmRun {
+            exit,
+            stdout,
+            stderr,
+            truncated: stdout_truncated || stderr_truncated,
+            fuel_consumed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::TempFile;
+    use serde_json::json;
+    use wasmtime::Instance;
+
+    fn config(module: &TempFile) -> WasmConfig {
+        serde_json::from_value(json!({ "module": module.path() })).unwrap()
+    }
+
+    async fn run(wat: &str, configure: impl FnOnce(&mut WasmConfig)) -> Result<WasmRun, String> {
+        let module = TempFile::new("wat", wat);
+        let mut config = config(&module);
+        configure(&mut config);
+        WasmRuntime::new()
+            .unwrap()
+            .run(&config, "job")
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    #[tokio::test]
+    async fn running_out_of_fuel_ends_the_run() {
+        let wat = r#"(module (memory (export "memory") 1) (func (export "_start") (loop (br 0))))"#;
+        let run = run(wat, |config| config.fuel = 10_000).await.unwrap();
+        assert_eq!(run.exit, WasmExit::OutOfFuel);
+        assert_eq!(run.fuel_consumed, 10_000);
+    }
+
+    #[tokio::test]
+    async fn growing_memory_past_the_limit_traps() {
+        let wat = r#"(module (memory (export "memory") 1)
+            (func (export "_start") (drop (memory.grow (i32.const 2)))))"#;
+        let grown = run(wat, |config| config.max_memory_bytes = 2 * 65536)
+            .await
+            .unwrap();
+        assert!(
+            matches!(grown.exit, WasmExit::Trapped(_)),
+            "{:?}",
+            grown.exit
+        );
+
+        let grown = run(wat, |config| config.max_memory_bytes = 3 * 65536)
+            .await
+            .unwrap();
+        assert_eq!(grown.exit, WasmExit::Exited(0));
+    }
+
+    #[tokio::test]
+    async fn tables_are_limited() {
+        let tables = |count: usize, elements: usize| {
+            format!(
+                r#"(module {} (func (export "_start")))"#,
+                format!("(table {} funcref)", elements).repeat(count)
+            )
+        };
+        assert!(run(&tables(MAX_TABLES, 1), |_| {}).await.is_ok());
+        assert!(run(&tables(MAX_TABLES + 1, 1), |_| {}).await.is_err());
+        assert!(run(&tables(1, MAX_TABLE_ELEMENTS), |_| {}).await.is_ok());
+        assert!(
+            run(&tables(1, MAX_TABLE_ELEMENTS + 1), |_| {})
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn store_holds_a_single_instance() {
+        let runtime = WasmRuntime::new().unwrap();
+        let file = TempFile::new("wat", "(module)");
+        let module = Module::new(&runtime.engine, "(module)").unwrap();
+        let mut store = Store::new(&runtime.engine, limits(&config(&file)));
+        store.limiter(|limits| limits);
+        assert!(Instance::new_async(&mut store, &module, &[]).await.is_ok());
+        assert!(Instance::new_async(&mut store, &module, &[]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn module_is_compiled_again_only_when_its_file_changes() {
+        let runtime = WasmRuntime::new().unwrap();
+        let file = TempFile::new("wat", r#"(module (func (export "_start")))"#);
+        let path = file.path();
+        runtime.module(&path).await.unwrap();
+
+        // Unchanged modification time: the compiled module is reused
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        std::fs::write(&path, "not a module").unwrap();
+        let handle = std::fs::File::options().write(true).open(&path).unwrap();
+        handle.set_modified(modified).unwrap();
+        runtime.module(&path).await.unwrap();
+
+        handle
+            .set_modified(modified + Duration::from_secs(1))
+            .unwrap();
+        assert!(runtime.module(&path).await.is_err());
+    }
+}
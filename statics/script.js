@@ -79,10 +79,18 @@ async function fetchTasks() {
         const statusMap = {
             start: '🟡 Start',
             scheduled: '📅 Scheduled',
+            queued: '⏳ Queued',
             running: '🔄 Running',
             success: '✅ Success',
             failed: '❌ Failed',
-            disabled: '🚫 Disabled',
+            retrying: '🔁 Retrying',
+            timed_out: '⏱️ Timed Out',
+            cancelled: '⛔ Cancelled',
+            interrupted: '🛑 Interrupted',
+            lost: '❓ Lost',
+            skipped: '⏭️ Skipped',
+            disabled: '⏸️ Paused',
+            completed: '🏁 Completed',
         };
 
         Object.entries(grouped).forEach(([name, jobs], idx) => {
@@ -113,20 +121,26 @@ async function fetchTasks() {
 
             jobs
                 .sort((a, b) => new Date(b.last_run || 0) - new Date(a.last_run || 0))
-                .forEach(({ task_type = '-', status = 'unknown', last_run, payload, message, execution_count = 0 }) => {
+                .forEach(({ id, task_type = '-', status = 'unknown', last_run, next_run_local, timezone, payload, message, execution_count = 0 }) => {
                     const statusKey = status.toLowerCase();
                     const displayStatus = statusMap[statusKey] || statusKey;
                     const safeMessage = message?.trim() ? escapeHtml(message.trim()) : '';
                     const errorMessageHtml = safeMessage
                         ? `<div class="error-message">💬 ${safeMessage}</div>`
                         : '';
+                    const isPaused = statusKey === 'disabled';
+                    const pauseButton = `<button class="pause-toggle" onclick="togglePause('${escapeHtml(id)}', ${isPaused})">${isPaused ? '▶ Resume' : '⏸ Pause'}</button>`;
+                    const runNowButton = `<button class="pause-toggle" onclick="triggerJob('${escapeHtml(id)}')">⚡ Run now</button>`;
 
                     tbody.insertAdjacentHTML('beforeend', `
-                        <tr class="${toggleId}" style="display: ${isOpen ? '' : 'none'};">
+                        <tr class="${toggleId}${isPaused ? ' paused' : ''}" style="display: ${isOpen ? '' : 'none'};">
                             <td></td>
                             <td>${escapeHtml(task_type)}</td>
-                            <td><span class="status ${statusKey}">${displayStatus}</span></td>
-                            <td>${last_run || '-'}</td>
+                            <td><span class="status ${statusKey}">${displayStatus}</span> ${pauseButton} ${runNowButton}</td>
+                            <td>
+                                ${last_run || '-'}
+                                ${next_run_local ? `<div class="next-run">⏭ ${escapeHtml(next_run_local)} (${escapeHtml(timezone || 'UTC')})</div>` : ''}
+                            </td>
                             <td>
                                 <pre>${escapeHtml(JSON.stringify(payload, null, 2))}</pre>
                                 ${errorMessageHtml}
@@ -145,6 +159,31 @@ async function fetchTasks() {
     }
 }
 
+async function togglePause(id, isPaused) {
+    const action = isPaused ? 'resume' : 'pause';
+    try {
+        const res = await fetch(`/api/jobs/${encodeURIComponent(id)}/${action}`, { method: 'POST' });
+        if (!res.ok) {
+            console.error(`Failed to ${action} job ${id}:`, res.status);
+        }
+    } catch (error) {
+        console.error(`Failed to ${action} job ${id}:`, error);
+    }
+    fetchTasks();
+}
+
+async function triggerJob(id) {
+    try {
+        const res = await fetch(`/api/jobs/${encodeURIComponent(id)}/trigger`, { method: 'POST' });
+        if (!res.ok) {
+            console.error(`Failed to trigger job ${id}:`, res.status);
+        }
+    } catch (error) {
+        console.error(`Failed to trigger job ${id}:`, error);
+    }
+    fetchTasks();
+}
+
 function toggleGroup(groupClass, btn) {
     const rows = document.querySelectorAll(`.${groupClass}`);
     const shouldShow = rows[0]?.style.display === 'none';